@@ -0,0 +1,386 @@
+//! Command line interface
+//!
+//! "Marvin, I want you to open that door." "Which door?" "That one." "Oh, I see."
+
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::output::naming::Trigger;
+use crate::rollup::PeriodKind;
+use crate::web::index::ReportKind;
+
+/// Config file used when `--config` isn't given
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/marvinous/marvinous.toml";
+
+/// Marvinous - Server monitoring with existential despair
+///
+/// Without a subcommand, runs `collect`.
+///
+/// Exit codes: 0 success, 1 configuration/usage error or nothing to do,
+/// 2 data collection failed, 3 LLM request failed, 4 reading or writing
+/// reports, state or archives failed.
+#[derive(Parser, Debug)]
+#[command(name = "marvinous")]
+#[command(about = "Server monitoring with the personality of a paranoid android")]
+#[command(version)]
+pub struct Cli {
+    /// Path to configuration file [default: /etc/marvinous/marvinous.toml]
+    #[arg(short, long, global = true)]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Deprecated: use `collect --dry-run`
+    #[arg(long, hide = true)]
+    dry_run: bool,
+
+    /// Deprecated: use `prompt render`
+    #[arg(long, hide = true)]
+    show_prompt: bool,
+
+    /// Deprecated: use `serve`
+    #[arg(long, hide = true)]
+    web_server: bool,
+
+    /// Deprecated: use `daemon`
+    #[arg(long, hide = true)]
+    daemon: bool,
+
+    /// Deprecated: use `daily`
+    #[arg(long, hide = true)]
+    daily_summary: bool,
+
+    /// Deprecated: use `hash-password`
+    #[arg(long, hide = true)]
+    hash_password: bool,
+
+    /// Deprecated: use `export`
+    #[arg(long, hide = true, value_name = "FILE")]
+    export: Option<PathBuf>,
+
+    #[arg(long, hide = true, requires = "export")]
+    from: Option<NaiveDate>,
+
+    #[arg(long, hide = true, requires = "export")]
+    to: Option<NaiveDate>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Collect data, ask the LLM for a report and write it (the default)
+    Collect {
+        /// Print collected data as JSON without calling the LLM
+        #[arg(long)]
+        dry_run: bool,
+        /// What started this run, recorded in the report name
+        #[arg(long, value_enum, default_value = "manual")]
+        trigger: TriggerArg,
+    },
+
+    /// Run the web dashboard
+    Serve,
+
+    /// Run the web dashboard and scheduled jobs ([daemon] in config)
+    Daemon,
+
+    /// Generate a daily summary and archive that day's hourly reports
+    ///
+    /// Summaries are rebuilt from the archive when the hourly reports have
+    /// already been archived.
+    Daily {
+        /// Day to summarise (YYYY-MM-DD, default yesterday)
+        #[arg(long, conflicts_with_all = ["from", "backfill"])]
+        date: Option<NaiveDate>,
+
+        /// Summarise every day from this date (YYYY-MM-DD) ...
+        #[arg(long, requires = "to", conflicts_with = "backfill")]
+        from: Option<NaiveDate>,
+
+        /// ... to this one, inclusive
+        #[arg(long, requires = "from")]
+        to: Option<NaiveDate>,
+
+        /// Summarise every past day that has hourly reports but no summary
+        #[arg(long)]
+        backfill: bool,
+    },
+
+    /// Roll daily summaries up into a weekly or monthly summary
+    Rollup {
+        #[arg(value_enum)]
+        period: RollupPeriodArg,
+
+        /// Any day in the week or month (YYYY-MM-DD, default the last complete one)
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+
+    /// Delete reports, archives and history past the [retention] limits
+    Prune {
+        /// List what would be removed without removing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// List or show reports
+    Report {
+        #[command(subcommand)]
+        command: ReportCommand,
+    },
+
+    /// Inspect the state saved for trend comparison
+    State {
+        #[command(subcommand)]
+        command: StateCommand,
+    },
+
+    /// Validate the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Hardware baseline for IPMI filtering
+    Baseline {
+        #[command(subcommand)]
+        command: BaselineCommand,
+    },
+
+    /// Inspect the LLM prompt
+    Prompt {
+        #[command(subcommand)]
+        command: PromptCommand,
+    },
+
+    /// Export reports to a single .html or .pdf file
+    Export {
+        /// Output file; the extension picks the format
+        output: PathBuf,
+
+        /// First day to export (YYYY-MM-DD, default today)
+        #[arg(long)]
+        from: Option<NaiveDate>,
+
+        /// Last day to export (YYYY-MM-DD, default --from)
+        #[arg(long)]
+        to: Option<NaiveDate>,
+    },
+
+    /// Read a password from stdin and print its argon2 hash for [[web.auth.user]]
+    HashPassword,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ReportCommand {
+    /// List reports, newest first
+    List {
+        /// Comma-separated severities (ok, watch, concern, critical, unknown)
+        #[arg(long)]
+        severity: Option<String>,
+
+        /// Only hourly, daily, weekly or monthly reports
+        #[arg(long, value_enum)]
+        kind: Option<ReportKindArg>,
+
+        /// Reports at or after this time (RFC 3339)
+        #[arg(long)]
+        from: Option<DateTime<Utc>>,
+
+        /// Reports at or before this time (RFC 3339)
+        #[arg(long)]
+        to: Option<DateTime<Utc>>,
+
+        /// Case-insensitive text search
+        #[arg(short, long)]
+        query: Option<String>,
+
+        /// Maximum number of reports
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print a report, falling back to the daily archives
+    Show {
+        /// Report filename, e.g. 2025-12-14-150002-scheduled.md
+        filename: String,
+
+        /// Render as standalone HTML
+        #[arg(long)]
+        html: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum StateCommand {
+    /// Print the previous run's saved state as JSON
+    Show,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Check the config file parses and its paths, schedules and credentials are usable
+    Check,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BaselineCommand {
+    /// Build a baseline from the DIMMs and fans IPMI currently reports
+    Generate {
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PromptCommand {
+    /// Collect data and print the prompt that would be sent to the LLM
+    Render,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum ReportKindArg {
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl From<ReportKindArg> for ReportKind {
+    fn from(kind: ReportKindArg) -> Self {
+        match kind {
+            ReportKindArg::Hourly => ReportKind::Hourly,
+            ReportKindArg::Daily => ReportKind::Daily,
+            ReportKindArg::Weekly => ReportKind::Weekly,
+            ReportKindArg::Monthly => ReportKind::Monthly,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum RollupPeriodArg {
+    Weekly,
+    Monthly,
+}
+
+impl From<RollupPeriodArg> for PeriodKind {
+    fn from(period: RollupPeriodArg) -> Self {
+        match period {
+            RollupPeriodArg::Weekly => PeriodKind::Weekly,
+            RollupPeriodArg::Monthly => PeriodKind::Monthly,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum TriggerArg {
+    Scheduled,
+    Manual,
+    Api,
+}
+
+impl From<TriggerArg> for Trigger {
+    fn from(trigger: TriggerArg) -> Self {
+        match trigger {
+            TriggerArg::Scheduled => Trigger::Scheduled,
+            TriggerArg::Manual => Trigger::Manual,
+            TriggerArg::Api => Trigger::Api,
+        }
+    }
+}
+
+impl Cli {
+    /// The command to run, translating the deprecated mode flags
+    ///
+    /// The second value is the replacement to suggest when a deprecated flag
+    /// was used. Flags keep their old precedence.
+    pub fn resolve(self) -> (Command, Option<&'static str>) {
+        if let Some(command) = self.command {
+            return (command, None);
+        }
+
+        if self.hash_password {
+            (Command::HashPassword, Some("marvinous hash-password"))
+        } else if let Some(output) = self.export {
+            let command = Command::Export {
+                output,
+                from: self.from,
+                to: self.to,
+            };
+            (command, Some("marvinous export FILE --from DATE --to DATE"))
+        } else if self.daemon {
+            (Command::Daemon, Some("marvinous daemon"))
+        } else if self.daily_summary {
+            let command = Command::Daily {
+                date: None,
+                from: None,
+                to: None,
+                backfill: false,
+            };
+            (command, Some("marvinous daily"))
+        } else if self.web_server {
+            (Command::Serve, Some("marvinous serve"))
+        } else if self.dry_run {
+            (
+                Command::Collect {
+                    dry_run: true,
+                    trigger: TriggerArg::Manual,
+                },
+                Some("marvinous collect --dry-run"),
+            )
+        } else if self.show_prompt {
+            (
+                Command::Prompt {
+                    command: PromptCommand::Render,
+                },
+                Some("marvinous prompt render"),
+            )
+        } else {
+            (
+                Command::Collect {
+                    dry_run: false,
+                    trigger: TriggerArg::Manual,
+                },
+                None,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(args: &[&str]) -> (Command, Option<&'static str>) {
+        Cli::try_parse_from(std::iter::once("marvinous").chain(args.iter().copied()))
+            .unwrap()
+            .resolve()
+    }
+
+    #[test]
+    fn test_subcommands_and_deprecated_flags() {
+        assert!(matches!(resolve(&[]), (Command::Collect { dry_run: false, .. }, None)));
+        assert!(matches!(
+            resolve(&["daily", "--date", "2025-12-14", "-c", "/tmp/m.toml"]),
+            (Command::Daily { date: Some(_), .. }, None)
+        ));
+        assert!(matches!(
+            resolve(&["report", "list", "--kind", "daily", "--limit", "5"]),
+            (Command::Report { command: ReportCommand::List { limit: 5, .. } }, None)
+        ));
+
+        assert!(matches!(resolve(&["--web-server"]), (Command::Serve, Some(_))));
+        assert!(matches!(resolve(&["--dry-run"]), (Command::Collect { dry_run: true, .. }, Some(_))));
+        // --daily-summary used to win over --web-server
+        assert!(matches!(
+            resolve(&["--web-server", "--daily-summary"]),
+            (Command::Daily { date: None, .. }, Some(_))
+        ));
+    }
+}
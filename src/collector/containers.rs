@@ -0,0 +1,698 @@
+//! Container and VM inventory collector (Docker/Podman API and libvirt)
+//!
+//! "I'm not getting you down at all, am I? The containers keep dying anyway."
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::process::Command;
+
+use super::{with_timeout, CollectorFailure};
+use crate::config::ContainersConfig;
+
+#[derive(Error, Debug)]
+pub enum ContainerError {
+    #[error("Container API socket error: {0}")]
+    SocketError(#[from] std::io::Error),
+    #[error("Container API returned {0}")]
+    HttpStatus(String),
+    #[error("Failed to parse container API response: {0}")]
+    ParseError(String),
+    #[error("virsh failed: {0}")]
+    VirshFailed(String),
+}
+
+/// Snapshot of one container, as reported by the engine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContainerStatus {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    /// created, running, paused, restarting, exited, dead
+    pub state: String,
+    /// healthy, unhealthy, starting - `None` when no healthcheck is defined
+    pub health: Option<String>,
+    pub exit_code: i64,
+    pub oom_killed: bool,
+    pub restart_count: u64,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+/// Snapshot of one libvirt domain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VmStatus {
+    pub name: String,
+    /// running, paused, shut off, crashed, ...
+    pub state: String,
+}
+
+/// Containers and VMs present on the host
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Inventory {
+    pub containers: Vec<ContainerStatus>,
+    pub vms: Vec<VmStatus>,
+}
+
+/// Something that happened to a container or VM since the previous run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InventoryChange {
+    pub kind: ChangeKind,
+    pub subject: String,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Restarted,
+    ExitedWithError,
+    OomKilled,
+    Unhealthy,
+    Disappeared,
+    VmStateChanged,
+}
+
+/// Collector names the two inventory sources fail under
+const CONTAINERS_SOURCE: &str = "containers";
+const LIBVIRT_SOURCE: &str = "libvirt";
+
+/// Inventory gathered this run, plus the sources that failed
+#[derive(Debug, Clone, Default)]
+pub struct InventoryCollection {
+    pub inventory: Inventory,
+    pub failures: Vec<CollectorFailure>,
+}
+
+/// Collect containers and VMs concurrently, each source under the configured timeout
+///
+/// A missing Docker/Podman socket or missing `virsh` just means that source is
+/// not in use on this host and is skipped rather than reported as a failure.
+pub async fn collect_inventory(config: &ContainersConfig) -> InventoryCollection {
+    let timeout = Duration::from_secs(config.timeout_secs);
+
+    let (containers, vms) = tokio::join!(
+        async {
+            if !config.docker || !config.socket.exists() {
+                return None;
+            }
+            Some(with_timeout(CONTAINERS_SOURCE, timeout, collect_containers(&config.socket)).await)
+        },
+        async {
+            if !config.libvirt || which::which("virsh").is_err() {
+                return None;
+            }
+            Some(with_timeout(LIBVIRT_SOURCE, timeout, collect_vms(&config.libvirt_uri)).await)
+        },
+    );
+
+    let mut collection = InventoryCollection::default();
+
+    match containers {
+        Some(Ok(containers)) => collection.inventory.containers = containers,
+        Some(Err(failure)) => collection.failures.push(failure),
+        None => {}
+    }
+    match vms {
+        Some(Ok(vms)) => collection.inventory.vms = vms,
+        Some(Err(failure)) => collection.failures.push(failure),
+        None => {}
+    }
+
+    collection
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContainerSummary {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContainerInspect {
+    id: String,
+    name: String,
+    #[serde(default)]
+    restart_count: u64,
+    state: InspectState,
+    config: InspectConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InspectState {
+    status: String,
+    #[serde(default)]
+    exit_code: i64,
+    #[serde(rename = "OOMKilled", default)]
+    oom_killed: bool,
+    started_at: Option<String>,
+    finished_at: Option<String>,
+    health: Option<InspectHealth>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InspectHealth {
+    status: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InspectConfig {
+    image: String,
+}
+
+/// List every container (running or not) via the Docker-compatible API
+pub async fn collect_containers(socket: &Path) -> Result<Vec<ContainerStatus>, ContainerError> {
+    let body = unix_http_get(socket, "/containers/json?all=true").await?;
+    let summaries: Vec<ContainerSummary> =
+        serde_json::from_slice(&body).map_err(|e| ContainerError::ParseError(e.to_string()))?;
+
+    let mut containers = Vec::new();
+    for summary in summaries {
+        let body = unix_http_get(socket, &format!("/containers/{}/json", summary.id)).await?;
+        let inspect: ContainerInspect =
+            serde_json::from_slice(&body).map_err(|e| ContainerError::ParseError(e.to_string()))?;
+
+        containers.push(ContainerStatus {
+            id: inspect.id.chars().take(12).collect(),
+            name: inspect.name.trim_start_matches('/').to_string(),
+            image: inspect.config.image,
+            state: inspect.state.status,
+            health: inspect.state.health.map(|h| h.status),
+            exit_code: inspect.state.exit_code,
+            oom_killed: inspect.state.oom_killed,
+            restart_count: inspect.restart_count,
+            started_at: inspect.state.started_at,
+            finished_at: inspect.state.finished_at,
+        });
+    }
+
+    containers.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(containers)
+}
+
+/// Minimal HTTP/1.0 GET over a unix socket, returning the response body
+///
+/// HTTP/1.0 makes the engine close the connection after responding, so the
+/// body is simply everything after the headers (de-chunked if need be).
+async fn unix_http_get(socket: &Path, path: &str) -> Result<Vec<u8>, ContainerError> {
+    let mut stream = UnixStream::connect(socket).await?;
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: localhost\r\nAccept: application/json\r\n\r\n",
+        path
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+
+    let header_end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| ContainerError::ParseError("Truncated HTTP response".to_string()))?;
+    let head = String::from_utf8_lossy(&response[..header_end]).to_string();
+    let body = &response[header_end + 4..];
+
+    let status_line = head.lines().next().unwrap_or_default();
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+    if !status.starts_with('2') {
+        return Err(ContainerError::HttpStatus(format!(
+            "{} for {}",
+            status_line.trim(),
+            path
+        )));
+    }
+
+    let chunked = head.lines().any(|line| {
+        line.to_ascii_lowercase().starts_with("transfer-encoding:")
+            && line.to_ascii_lowercase().contains("chunked")
+    });
+
+    if chunked {
+        dechunk(body)
+    } else {
+        Ok(body.to_vec())
+    }
+}
+
+fn dechunk(mut body: &[u8]) -> Result<Vec<u8>, ContainerError> {
+    let malformed = || ContainerError::ParseError("Malformed chunked response".to_string());
+    let mut out = Vec::new();
+
+    loop {
+        let line_end = body
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or_else(malformed)?;
+        let size_text = String::from_utf8_lossy(&body[..line_end]);
+        let size_hex = size_text.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size_hex, 16).map_err(|_| malformed())?;
+        body = &body[line_end + 2..];
+
+        if size == 0 {
+            return Ok(out);
+        }
+        if body.len() < size + 2 {
+            return Err(malformed());
+        }
+        out.extend_from_slice(&body[..size]);
+        body = &body[size + 2..];
+    }
+}
+
+/// List libvirt domains and their states with `virsh list --all`
+pub async fn collect_vms(uri: &str) -> Result<Vec<VmStatus>, ContainerError> {
+    let output = Command::new("virsh")
+        .args(["-c", uri, "list", "--all"])
+        .kill_on_drop(true)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(ContainerError::VirshFailed(stderr.trim().to_string()));
+    }
+
+    Ok(parse_virsh_list(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse the `virsh list --all` table:
+///
+/// ```text
+///  Id   Name     State
+/// ---------------------------
+///  1    web01    running
+///  -    build    shut off
+/// ```
+pub fn parse_virsh_list(output: &str) -> Vec<VmStatus> {
+    output
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("---"))
+        .skip(1)
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let _id = parts.next()?;
+            let name = parts.next()?;
+            let state = parts.collect::<Vec<_>>().join(" ");
+            Some(VmStatus {
+                name: name.to_string(),
+                state,
+            })
+        })
+        .collect()
+}
+
+/// Diff a collection against the previous inventory and return what to save
+///
+/// A source that failed this run keeps its previous containers or VMs in the
+/// returned inventory and is left out of the diff, so an unreachable engine
+/// doesn't report everything as disappeared and forget it for next time.
+pub fn diff_collection(
+    previous: Option<&Inventory>,
+    collection: InventoryCollection,
+) -> (Inventory, Vec<InventoryChange>) {
+    let failed = |source: &str| collection.failures.iter().any(|f| f.collector == source);
+    let mut before = previous.cloned().unwrap_or_default();
+    let mut seen = collection.inventory.clone();
+    let mut inventory = collection.inventory;
+
+    if failed(CONTAINERS_SOURCE) {
+        inventory.containers = std::mem::take(&mut before.containers);
+        seen.containers.clear();
+    }
+    if failed(LIBVIRT_SOURCE) {
+        inventory.vms = std::mem::take(&mut before.vms);
+        seen.vms.clear();
+    }
+
+    let changes = diff_inventory(previous.map(|_| &before), &seen);
+    (inventory, changes)
+}
+
+/// Work out what happened since the previous run
+///
+/// Containers are matched by name so a recreated container (new ID) is still
+/// compared against its predecessor. With no previous inventory, only
+/// currently-bad states are reported.
+pub fn diff_inventory(previous: Option<&Inventory>, current: &Inventory) -> Vec<InventoryChange> {
+    let mut changes = Vec::new();
+
+    for container in &current.containers {
+        let before = previous.and_then(|p| p.containers.iter().find(|c| c.name == container.name));
+        let subject = format!("container {} ({})", container.name, container.image);
+
+        // A new start time on a container we've seen before means it restarted
+        if let Some(before) = before {
+            let restarts = container.restart_count.saturating_sub(before.restart_count);
+            if restarts > 0
+                || (container.started_at != before.started_at && before.started_at.is_some())
+            {
+                changes.push(InventoryChange {
+                    kind: ChangeKind::Restarted,
+                    subject: subject.clone(),
+                    detail: format!(
+                        "restarted since last run (restart count {} -> {})",
+                        before.restart_count, container.restart_count
+                    ),
+                });
+            }
+        }
+
+        // Only report exits that are new: a different finish time, or a container not seen before
+        let newly_finished = before.is_none_or(|b| b.finished_at != container.finished_at);
+
+        if container.oom_killed && newly_finished {
+            changes.push(InventoryChange {
+                kind: ChangeKind::OomKilled,
+                subject: subject.clone(),
+                detail: format!(
+                    "OOM-killed (state {}, exit code {})",
+                    container.state, container.exit_code
+                ),
+            });
+        } else if container.state == "exited" && container.exit_code != 0 && newly_finished {
+            changes.push(InventoryChange {
+                kind: ChangeKind::ExitedWithError,
+                subject: subject.clone(),
+                detail: format!("exited with code {}", container.exit_code),
+            });
+        }
+
+        if container.health.as_deref() == Some("unhealthy") {
+            let already = before.is_some_and(|b| b.health.as_deref() == Some("unhealthy"));
+            changes.push(InventoryChange {
+                kind: ChangeKind::Unhealthy,
+                subject,
+                detail: if already {
+                    "still unhealthy".to_string()
+                } else {
+                    "became unhealthy".to_string()
+                },
+            });
+        }
+    }
+
+    if let Some(previous) = previous {
+        for gone in previous
+            .containers
+            .iter()
+            .filter(|p| !current.containers.iter().any(|c| c.name == p.name))
+        {
+            changes.push(InventoryChange {
+                kind: ChangeKind::Disappeared,
+                subject: format!("container {} ({})", gone.name, gone.image),
+                detail: format!("no longer present (was {})", gone.state),
+            });
+        }
+    }
+
+    for vm in &current.vms {
+        let before = previous.and_then(|p| p.vms.iter().find(|v| v.name == vm.name));
+        match before {
+            Some(before) if before.state != vm.state => changes.push(InventoryChange {
+                kind: ChangeKind::VmStateChanged,
+                subject: format!("vm {}", vm.name),
+                detail: format!("{} -> {}", before.state, vm.state),
+            }),
+            None if vm.state == "crashed" => changes.push(InventoryChange {
+                kind: ChangeKind::VmStateChanged,
+                subject: format!("vm {}", vm.name),
+                detail: "crashed".to_string(),
+            }),
+            _ => {}
+        }
+    }
+
+    changes
+}
+
+impl std::fmt::Display for InventoryChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.subject, self.detail)
+    }
+}
+
+impl std::fmt::Display for ContainerStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}): {}", self.name, self.image, self.state)?;
+        if let Some(health) = &self.health {
+            write!(f, ", {}", health)?;
+        }
+        if self.state == "exited" {
+            write!(f, ", exit code {}", self.exit_code)?;
+        }
+        if self.restart_count > 0 {
+            write!(f, ", {} restarts", self.restart_count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::UnixListener;
+
+    fn container(name: &str, state: &str) -> ContainerStatus {
+        ContainerStatus {
+            id: format!("{}0000000000", name),
+            name: name.to_string(),
+            image: "nginx:latest".to_string(),
+            state: state.to_string(),
+            health: None,
+            exit_code: 0,
+            oom_killed: false,
+            restart_count: 0,
+            started_at: Some("2025-12-14T10:00:00Z".to_string()),
+            finished_at: Some("0001-01-01T00:00:00Z".to_string()),
+        }
+    }
+
+    /// Serve canned Docker API responses on a temporary unix socket
+    async fn mock_engine(chunked: bool) -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("docker.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut buf = vec![0u8; 4096];
+                let n = stream.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = request
+                    .split_whitespace()
+                    .nth(1)
+                    .unwrap_or_default()
+                    .to_string();
+
+                let body = match path.as_str() {
+                    "/containers/json?all=true" => {
+                        r#"[{"Id":"aaa111"},{"Id":"bbb222"}]"#.to_string()
+                    }
+                    "/containers/aaa111/json" => {
+                        r#"{"Id":"aaa111aaa111aaa111","Name":"/web","RestartCount":3,
+                        "State":{"Status":"running","ExitCode":0,"OOMKilled":false,
+                        "StartedAt":"2025-12-14T11:00:00Z","FinishedAt":"2025-12-14T10:59:00Z",
+                        "Health":{"Status":"unhealthy"}},"Config":{"Image":"nginx:latest"}}"#
+                            .to_string()
+                    }
+                    "/containers/bbb222/json" => {
+                        r#"{"Id":"bbb222bbb222bbb222","Name":"/worker","RestartCount":0,
+                        "State":{"Status":"exited","ExitCode":137,"OOMKilled":true,
+                        "StartedAt":"2025-12-14T09:00:00Z","FinishedAt":"2025-12-14T10:30:00Z"},
+                        "Config":{"Image":"worker:1.2"}}"#
+                            .to_string()
+                    }
+                    _ => {
+                        let _ = stream.write_all(b"HTTP/1.0 404 Not Found\r\n\r\n").await;
+                        continue;
+                    }
+                };
+
+                let response = if chunked {
+                    let (a, b) = body.split_at(body.len() / 2);
+                    format!(
+                        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+                        a.len(),
+                        a,
+                        b.len(),
+                        b
+                    )
+                } else {
+                    format!(
+                        "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{}",
+                        body
+                    )
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        (dir, socket)
+    }
+
+    #[tokio::test]
+    async fn test_collect_containers_from_mock_socket() {
+        for chunked in [false, true] {
+            let (_dir, socket) = mock_engine(chunked).await;
+            let containers = collect_containers(&socket).await.unwrap();
+
+            assert_eq!(containers.len(), 2);
+            assert_eq!(containers[0].name, "web");
+            assert_eq!(containers[0].id, "aaa111aaa111");
+            assert_eq!(containers[0].health.as_deref(), Some("unhealthy"));
+            assert_eq!(containers[0].restart_count, 3);
+            assert_eq!(containers[1].name, "worker");
+            assert!(containers[1].oom_killed);
+            assert_eq!(containers[1].exit_code, 137);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_containers_http_error() {
+        let (_dir, socket) = mock_engine(false).await;
+        let err = unix_http_get(&socket, "/nope").await.unwrap_err();
+        assert!(matches!(err, ContainerError::HttpStatus(_)));
+    }
+
+    #[test]
+    fn test_parse_virsh_list() {
+        let output = " Id   Name      State\n---------------------------\n 1    web01     running\n -    build     shut off\n\n";
+        let vms = parse_virsh_list(output);
+        assert_eq!(
+            vms,
+            vec![
+                VmStatus {
+                    name: "web01".to_string(),
+                    state: "running".to_string()
+                },
+                VmStatus {
+                    name: "build".to_string(),
+                    state: "shut off".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_inventory_reports_changes_since_previous() {
+        let mut restarted = container("web", "running");
+        let mut crashed = container("worker", "running");
+        let steady = container("db", "running");
+        let previous = Inventory {
+            containers: vec![
+                restarted.clone(),
+                crashed.clone(),
+                steady.clone(),
+                container("old", "running"),
+            ],
+            vms: vec![VmStatus {
+                name: "vm1".to_string(),
+                state: "running".to_string(),
+            }],
+        };
+
+        restarted.restart_count = 2;
+        restarted.started_at = Some("2025-12-14T10:45:00Z".to_string());
+        restarted.health = Some("unhealthy".to_string());
+        crashed.state = "exited".to_string();
+        crashed.exit_code = 1;
+        crashed.finished_at = Some("2025-12-14T10:50:00Z".to_string());
+
+        let current = Inventory {
+            containers: vec![restarted, crashed, steady],
+            vms: vec![VmStatus {
+                name: "vm1".to_string(),
+                state: "crashed".to_string(),
+            }],
+        };
+
+        let kinds: Vec<_> = diff_inventory(Some(&previous), &current)
+            .iter()
+            .map(|c| (c.kind, c.subject.clone()))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    ChangeKind::Restarted,
+                    "container web (nginx:latest)".to_string()
+                ),
+                (
+                    ChangeKind::Unhealthy,
+                    "container web (nginx:latest)".to_string()
+                ),
+                (
+                    ChangeKind::ExitedWithError,
+                    "container worker (nginx:latest)".to_string()
+                ),
+                (
+                    ChangeKind::Disappeared,
+                    "container old (nginx:latest)".to_string()
+                ),
+                (ChangeKind::VmStateChanged, "vm vm1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_source_keeps_previous_inventory() {
+        let previous = Inventory {
+            containers: vec![container("web", "running"), container("db", "running")],
+            vms: vec![VmStatus {
+                name: "vm1".to_string(),
+                state: "running".to_string(),
+            }],
+        };
+        let collection = InventoryCollection {
+            inventory: Inventory {
+                containers: vec![],
+                vms: vec![VmStatus {
+                    name: "vm1".to_string(),
+                    state: "paused".to_string(),
+                }],
+            },
+            failures: vec![CollectorFailure {
+                collector: CONTAINERS_SOURCE.to_string(),
+                error: "timed out after 10s".to_string(),
+                timed_out: true,
+            }],
+        };
+
+        let (inventory, changes) = diff_collection(Some(&previous), collection);
+
+        assert_eq!(inventory.containers, previous.containers);
+        assert_eq!(inventory.vms[0].state, "paused");
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ChangeKind::VmStateChanged);
+    }
+
+    #[test]
+    fn test_diff_inventory_ignores_old_exits() {
+        let mut exited = container("batch", "exited");
+        exited.exit_code = 2;
+        exited.oom_killed = true;
+        let inventory = Inventory {
+            containers: vec![exited],
+            vms: vec![],
+        };
+
+        // Already reported last run - nothing new
+        assert!(diff_inventory(Some(&inventory), &inventory).is_empty());
+        // First run - report the current bad state
+        assert_eq!(
+            diff_inventory(None, &inventory)[0].kind,
+            ChangeKind::OomKilled
+        );
+    }
+}
@@ -0,0 +1,301 @@
+//! Custom command/script collector
+//!
+//! "I've calculated your chance of survival, but I don't think you'll like it."
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::process::Command;
+
+use super::{run_concurrently, CollectorFailure};
+use crate::config::{CustomCollectorConfig, CustomOutputMode};
+use crate::output::report::Severity;
+
+/// Cap on captured output so a chatty script cannot swamp the prompt
+const MAX_OUTPUT_CHARS: usize = 4000;
+
+#[derive(Error, Debug)]
+pub enum CustomError {
+    #[error("Failed to execute {0}: {1}")]
+    ExecutionError(String, std::io::Error),
+    #[error("Failed to parse output: {0}")]
+    ParseError(String),
+}
+
+/// Result of one custom check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomCheckResult {
+    pub name: String,
+    pub mode: CustomOutputMode,
+    pub exit_code: Option<i32>,
+    /// Only set for Nagios-mode checks, derived from the plugin exit code
+    pub severity: Option<Severity>,
+    pub output: String,
+    /// Parsed stdout for JSON-mode checks
+    pub data: Option<serde_json::Value>,
+    #[serde(default)]
+    pub perfdata: Vec<PerfData>,
+}
+
+/// A single Nagios performance data item: `'label'=value[UOM];[warn];[crit];[min];[max]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PerfData {
+    pub label: String,
+    pub value: f64,
+    pub unit: String,
+    pub warn: Option<String>,
+    pub crit: Option<String>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// Results for every configured custom check, plus the checks that failed to run
+#[derive(Debug, Clone, Default)]
+pub struct CustomCollection {
+    pub results: Vec<CustomCheckResult>,
+    pub failures: Vec<CollectorFailure>,
+}
+
+/// Run all configured custom collectors concurrently, each under its own timeout
+pub async fn collect_custom(collectors: &[CustomCollectorConfig]) -> CustomCollection {
+    let jobs = collectors
+        .iter()
+        .cloned()
+        .map(|collector| {
+            let name = format!("custom {}", collector.name);
+            let timeout = Duration::from_secs(collector.timeout_secs);
+            (name, timeout, async move { run_check(&collector).await })
+        })
+        .collect();
+
+    let (results, failures) = run_concurrently(jobs).await;
+
+    CustomCollection { results, failures }
+}
+
+async fn run_check(collector: &CustomCollectorConfig) -> Result<CustomCheckResult, CustomError> {
+    let output = Command::new(&collector.command)
+        .args(&collector.args)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| CustomError::ExecutionError(collector.command.clone(), e))?;
+
+    let exit_code = output.status.code();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    let mut result = CustomCheckResult {
+        name: collector.name.clone(),
+        mode: collector.output,
+        exit_code,
+        severity: None,
+        output: String::new(),
+        data: None,
+        perfdata: vec![],
+    };
+
+    match collector.output {
+        CustomOutputMode::Raw => {
+            let mut text = stdout.trim().to_string();
+            if !stderr.trim().is_empty() {
+                text.push_str("\n[stderr] ");
+                text.push_str(stderr.trim());
+            }
+            result.output = truncate(&text);
+        }
+        CustomOutputMode::Json => {
+            let data: serde_json::Value = serde_json::from_str(&stdout)
+                .map_err(|e| CustomError::ParseError(format!("JSON parse error: {}", e)))?;
+            result.output = truncate(&serde_json::to_string_pretty(&data).unwrap_or_default());
+            result.data = Some(data);
+        }
+        CustomOutputMode::Nagios => {
+            let (text, perfdata) = parse_nagios_output(&stdout);
+            result.severity = Some(Severity::from_nagios_exit_code(exit_code));
+            result.output = truncate(&text);
+            result.perfdata = perfdata;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Split Nagios plugin output into its human-readable text and performance data
+///
+/// The first line is `TEXT | PERFDATA`; subsequent lines are long text until
+/// one contains `|`, after which everything is further perfdata.
+pub fn parse_nagios_output(output: &str) -> (String, Vec<PerfData>) {
+    let mut lines = output.lines();
+    let mut text = Vec::new();
+    let mut perf_raw = Vec::new();
+
+    if let Some(first) = lines.next() {
+        match first.split_once('|') {
+            Some((status, perf)) => {
+                text.push(status.trim().to_string());
+                perf_raw.push(perf.to_string());
+            }
+            None => text.push(first.trim().to_string()),
+        }
+    }
+
+    let mut in_perfdata = false;
+    for line in lines {
+        if in_perfdata {
+            perf_raw.push(line.to_string());
+            continue;
+        }
+        match line.split_once('|') {
+            Some((long_text, perf)) => {
+                text.push(long_text.trim_end().to_string());
+                perf_raw.push(perf.to_string());
+                in_perfdata = true;
+            }
+            None => text.push(line.trim_end().to_string()),
+        }
+    }
+
+    let perfdata = perf_raw.iter().flat_map(|raw| parse_perfdata(raw)).collect();
+
+    (text.join("\n").trim().to_string(), perfdata)
+}
+
+/// Parse a perfdata string into its items, skipping anything malformed
+fn parse_perfdata(raw: &str) -> Vec<PerfData> {
+    let mut items = Vec::new();
+    let mut rest = raw.trim();
+
+    while !rest.is_empty() {
+        // Labels may be single-quoted to allow spaces and '='
+        let (label, after_label) = if let Some(quoted) = rest.strip_prefix('\'') {
+            match quoted.find("'=") {
+                Some(end) => (quoted[..end].to_string(), &quoted[end + 2..]),
+                None => break,
+            }
+        } else {
+            match rest.find('=') {
+                Some(end) => (rest[..end].to_string(), &rest[end + 1..]),
+                None => break,
+            }
+        };
+
+        let (spec, remainder) = after_label
+            .split_once(char::is_whitespace)
+            .unwrap_or((after_label, ""));
+        rest = remainder.trim_start();
+
+        let mut fields = spec.split(';');
+        let value_field = fields.next().unwrap_or("");
+        let split_at = value_field
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+            .unwrap_or(value_field.len());
+        let Ok(value) = value_field[..split_at].parse::<f64>() else {
+            continue;
+        };
+
+        let mut optional = || fields.next().filter(|f| !f.is_empty()).map(str::to_string);
+        let warn = optional();
+        let crit = optional();
+        let min = optional().and_then(|v| v.parse().ok());
+        let max = optional().and_then(|v| v.parse().ok());
+
+        items.push(PerfData {
+            label,
+            value,
+            unit: value_field[split_at..].to_string(),
+            warn,
+            crit,
+            min,
+            max,
+        });
+    }
+
+    items
+}
+
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= MAX_OUTPUT_CHARS {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(MAX_OUTPUT_CHARS).collect();
+    truncated.push_str("\n[...truncated...]");
+    truncated
+}
+
+impl std::fmt::Display for CustomCheckResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let exit = self
+            .exit_code
+            .map(|c| c.to_string())
+            .unwrap_or_else(|| "signal".to_string());
+
+        match self.severity {
+            Some(severity) => write!(f, "[{}] {} (exit {}): {}", self.name, severity, exit, self.output)?,
+            None => write!(f, "[{}] (exit {}):\n{}", self.name, exit, self.output)?,
+        }
+
+        for perf in &self.perfdata {
+            write!(f, "\n  {}={}{}", perf.label, perf.value, perf.unit)?;
+            if perf.warn.is_some() || perf.crit.is_some() {
+                write!(
+                    f,
+                    " (warn {}, crit {})",
+                    perf.warn.as_deref().unwrap_or("-"),
+                    perf.crit.as_deref().unwrap_or("-")
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nagios_single_line() {
+        let (text, perf) = parse_nagios_output("DISK OK - free space: / 3326 MB (56%) | /=2643MB;5948;5958;0;5968\n");
+        assert_eq!(text, "DISK OK - free space: / 3326 MB (56%)");
+        assert_eq!(
+            perf,
+            vec![PerfData {
+                label: "/".to_string(),
+                value: 2643.0,
+                unit: "MB".to_string(),
+                warn: Some("5948".to_string()),
+                crit: Some("5958".to_string()),
+                min: Some(0.0),
+                max: Some(5968.0),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_nagios_long_text_and_quoted_labels() {
+        let output = "CERT WARNING - expires soon | 'days left'=12d;30;7\n\
+                      www.example.com expires in 12 days\n\
+                      api.example.com expires in 90 days | 'api days'=90d;30;7\n\
+                      extra=1\n";
+        let (text, perf) = parse_nagios_output(output);
+        assert_eq!(
+            text,
+            "CERT WARNING - expires soon\nwww.example.com expires in 12 days\napi.example.com expires in 90 days"
+        );
+        let labels: Vec<_> = perf.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, vec!["days left", "api days", "extra"]);
+        assert_eq!(perf[0].unit, "d");
+        assert_eq!(perf[2].warn, None);
+    }
+
+    #[test]
+    fn test_nagios_exit_codes_map_to_severity() {
+        assert_eq!(Severity::from_nagios_exit_code(Some(0)), Severity::Ok);
+        assert_eq!(Severity::from_nagios_exit_code(Some(1)), Severity::Concern);
+        assert_eq!(Severity::from_nagios_exit_code(Some(2)), Severity::Critical);
+        assert_eq!(Severity::from_nagios_exit_code(Some(3)), Severity::Unknown);
+        assert_eq!(Severity::from_nagios_exit_code(None), Severity::Unknown);
+    }
+}
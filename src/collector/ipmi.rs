@@ -4,8 +4,8 @@
 
 use crate::config::HardwareBaseline;
 use serde::{Deserialize, Serialize};
-use std::process::Command;
 use thiserror::Error;
+use tokio::process::Command;
 
 #[derive(Error, Debug)]
 pub enum IpmiError {
@@ -24,8 +24,27 @@ pub struct IpmiReading {
     pub status: String,
 }
 
+impl IpmiReading {
+    /// Split a value like "34 degrees C" or "12.10 Volts" into number and unit
+    ///
+    /// Returns `None` for "no reading" and discrete sensors (hex states like "0x01").
+    pub fn numeric_value(&self) -> Option<(f64, &str)> {
+        let raw = self.value.trim();
+        let (number, unit) = raw.split_once(' ').unwrap_or((raw, ""));
+        if number.starts_with("0x") {
+            return None;
+        }
+        number.parse().ok().map(|value| (value, unit.trim()))
+    }
+
+    /// False for sensors the BMC knows about but can't read (empty slots, dead fans)
+    pub fn has_reading(&self) -> bool {
+        self.value != "no reading" && self.status != "ns"
+    }
+}
+
 /// Collect IPMI sensor data via ipmitool
-pub fn collect_ipmi() -> Result<Vec<IpmiReading>, IpmiError> {
+pub async fn collect_ipmi() -> Result<Vec<IpmiReading>, IpmiError> {
     // Check if ipmitool exists
     if which::which("ipmitool").is_err() {
         return Err(IpmiError::NotFound);
@@ -33,7 +52,9 @@ pub fn collect_ipmi() -> Result<Vec<IpmiReading>, IpmiError> {
 
     let output = Command::new("ipmitool")
         .args(["sdr", "list"])
-        .output()?;
+        .kill_on_drop(true)
+        .output()
+        .await?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -61,6 +82,19 @@ pub fn collect_ipmi() -> Result<Vec<IpmiReading>, IpmiError> {
     Ok(readings)
 }
 
+/// Baseline listing the DIMMs and fans that currently have a reading
+pub fn baseline_from_readings(readings: &[IpmiReading]) -> HardwareBaseline {
+    let mut baseline = HardwareBaseline::default();
+    for reading in readings.iter().filter(|r| r.has_reading()) {
+        if reading.sensor.starts_with("DIMM_") {
+            baseline.memory.installed_slots.push(reading.sensor.clone());
+        } else if reading.sensor.contains("_FAN") {
+            baseline.cooling.installed_fans.push(reading.sensor.clone());
+        }
+    }
+    baseline
+}
+
 /// Filter IPMI readings based on hardware baseline
 /// Removes "no reading" entries for non-installed hardware while preserving them for installed hardware
 /// (since "no reading" for installed hardware indicates a failure)
@@ -74,7 +108,7 @@ pub fn filter_ipmi_readings(
         .into_iter()
         .filter(|reading| {
             // If the reading has a valid value, always keep it
-            if reading.value != "no reading" && reading.status != "ns" {
+            if reading.has_reading() {
                 return true;
             }
 
@@ -3,9 +3,10 @@
 //! "I've been talking to the ship's computer. It hates me."
 
 use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
-use std::process::Command;
 use thiserror::Error;
+use tokio::process::Command;
 
 #[derive(Error, Debug)]
 pub enum JournalError {
@@ -74,7 +75,7 @@ fn into_log_entry(self) -> Option<LogEntry> {
 }
 
 /// Collect system logs from journalctl
-pub fn collect_system_logs(since: &str, max_priority: u8, max_entries: usize) -> Result<Vec<LogEntry>, JournalError> {
+pub async fn collect_system_logs(since: &str, max_priority: u8, max_entries: usize) -> Result<Vec<LogEntry>, JournalError> {
     let output = Command::new("journalctl")
         .args([
             "--since", since,
@@ -82,7 +83,9 @@ pub fn collect_system_logs(since: &str, max_priority: u8, max_entries: usize) ->
             "--output=json",
             "--no-pager",
         ])
-        .output()?;
+        .kill_on_drop(true)
+        .output()
+        .await?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -111,7 +114,7 @@ pub fn collect_system_logs(since: &str, max_priority: u8, max_entries: usize) ->
 }
 
 /// Collect kernel logs from journalctl
-pub fn collect_kernel_logs(since: &str, max_entries: usize) -> Result<Vec<LogEntry>, JournalError> {
+pub async fn collect_kernel_logs(since: &str, max_entries: usize) -> Result<Vec<LogEntry>, JournalError> {
     let output = Command::new("journalctl")
         .args([
             "-k",
@@ -119,7 +122,9 @@ pub fn collect_kernel_logs(since: &str, max_entries: usize) -> Result<Vec<LogEnt
             "--output=json",
             "--no-pager",
         ])
-        .output()?;
+        .kill_on_drop(true)
+        .output()
+        .await?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -147,16 +152,35 @@ pub fn collect_kernel_logs(since: &str, max_entries: usize) -> Result<Vec<LogEnt
     Ok(entries)
 }
 
-impl std::fmt::Display for LogEntry {
+impl LogEntry {
+    /// The entry as a log line with its time in `tz`
+    pub fn in_zone(&self, tz: Tz) -> LocalLogEntry<'_> {
+        LocalLogEntry { entry: self, tz }
+    }
+}
+
+/// A `LogEntry` displayed in a particular timezone
+pub struct LocalLogEntry<'a> {
+    entry: &'a LogEntry,
+    tz: Tz,
+}
+
+impl std::fmt::Display for LocalLogEntry<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let unit_str = self.unit.as_deref().unwrap_or("unknown");
+        let unit_str = self.entry.unit.as_deref().unwrap_or("unknown");
         write!(
             f,
             "{} [{}] {}: {}",
-            self.timestamp.format("%b %d %H:%M:%S"),
-            self.priority,
+            self.entry.timestamp.with_timezone(&self.tz).format("%b %d %H:%M:%S"),
+            self.entry.priority,
             unit_str,
-            self.message
+            self.entry.message
         )
     }
 }
+
+impl std::fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.in_zone(Tz::UTC).fmt(f)
+    }
+}
@@ -2,19 +2,29 @@
 //!
 //! "I have a million ideas, but they all point to certain death."
 
+pub mod containers;
+pub mod custom;
 pub mod ipmi;
 pub mod journalctl;
 pub mod nvidia;
+pub mod prometheus;
 pub mod sensors;
 pub mod smart;
 
+pub use containers::{collect_inventory, diff_collection, Inventory, InventoryChange};
+pub use custom::{collect_custom, CustomCheckResult};
 pub use ipmi::{collect_ipmi, IpmiReading};
 pub use journalctl::{collect_kernel_logs, collect_system_logs, LogEntry};
 pub use nvidia::{collect_gpu, GpuStatus};
+pub use prometheus::{collect_prometheus, MetricReading};
 pub use sensors::{collect_sensors, SensorReading};
 pub use smart::{collect_smart, DriveHealth};
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use tokio::task::JoinSet;
 
 use crate::output::state::PreviousState;
 
@@ -27,5 +37,156 @@ pub struct CollectedData {
     pub ipmi: Vec<IpmiReading>,
     pub gpu: Option<GpuStatus>,
     pub drives: Vec<DriveHealth>,
+    #[serde(default)]
+    pub custom: Vec<CustomCheckResult>,
+    #[serde(default)]
+    pub metrics: Vec<MetricReading>,
+    #[serde(default)]
+    pub inventory: Option<Inventory>,
+    /// Container/VM events since the previous run
+    #[serde(default)]
+    pub inventory_changes: Vec<InventoryChange>,
+    #[serde(default)]
+    pub failures: Vec<CollectorFailure>,
     pub previous: Option<PreviousState>,
 }
+
+/// A collector that errored or timed out during a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectorFailure {
+    pub collector: String,
+    pub error: String,
+    pub timed_out: bool,
+}
+
+impl std::fmt::Display for CollectorFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.collector, self.error)
+    }
+}
+
+/// Run a collector with a timeout, converting errors and timeouts into a `CollectorFailure`
+///
+/// Collectors spawn their commands with `kill_on_drop`, so when the timeout
+/// drops the future the hung child process is killed along with it.
+pub async fn with_timeout<T, E, F>(
+    collector: &str,
+    timeout: Duration,
+    future: F,
+) -> Result<T, CollectorFailure>
+where
+    F: Future<Output = Result<T, E>>,
+    E: std::fmt::Display,
+{
+    match tokio::time::timeout(timeout, future).await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) => Err(CollectorFailure {
+            collector: collector.to_string(),
+            error: e.to_string(),
+            timed_out: false,
+        }),
+        Err(_) => Err(CollectorFailure {
+            collector: collector.to_string(),
+            error: format!("timed out after {}s", timeout.as_secs()),
+            timed_out: true,
+        }),
+    }
+}
+
+/// Run a batch of named collector jobs concurrently, each under its own timeout
+///
+/// Successful results come back in the order the jobs were given, regardless
+/// of completion order, so prompts stay stable from run to run.
+pub async fn run_concurrently<T, E, F>(jobs: Vec<(String, Duration, F)>) -> (Vec<T>, Vec<CollectorFailure>)
+where
+    T: Send + 'static,
+    E: std::fmt::Display + Send + 'static,
+    F: Future<Output = Result<T, E>> + Send + 'static,
+{
+    let mut tasks = JoinSet::new();
+    // Names by task, for reporting a collector whose task panicked
+    let mut names = HashMap::new();
+    for (index, (name, timeout, future)) in jobs.into_iter().enumerate() {
+        let collector = name.clone();
+        let task = tasks.spawn(async move { (index, with_timeout(&collector, timeout, future).await) });
+        names.insert(task.id(), name);
+    }
+
+    let mut results = Vec::new();
+    let mut failures = Vec::new();
+
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((index, Ok(value))) => results.push((index, value)),
+            Ok((_, Err(failure))) => {
+                tracing::warn!("Collector failed: {}", failure);
+                failures.push(failure);
+            }
+            Err(e) => {
+                let failure = CollectorFailure {
+                    collector: names.remove(&e.id()).unwrap_or_default(),
+                    error: format!("collector task failed: {}", e),
+                    timed_out: false,
+                };
+                tracing::warn!("Collector failed: {}", failure);
+                failures.push(failure);
+            }
+        }
+    }
+
+    results.sort_by_key(|(index, _)| *index);
+
+    (results.into_iter().map(|(_, value)| value).collect(), failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::process::Command;
+
+    #[tokio::test]
+    async fn test_with_timeout_records_timeout() {
+        let result = with_timeout("sleepy", Duration::from_millis(100), async {
+            Command::new("sleep")
+                .arg("10")
+                .kill_on_drop(true)
+                .output()
+                .await
+        })
+        .await;
+
+        let failure = result.unwrap_err();
+        assert_eq!(failure.collector, "sleepy");
+        assert!(failure.timed_out);
+    }
+
+    #[tokio::test]
+    async fn test_panicking_collector_is_a_failure() {
+        async fn job(value: u32) -> Result<u32, String> {
+            assert!(value > 0, "boom");
+            Ok(value)
+        }
+        let jobs = vec![
+            ("fine".to_string(), Duration::from_secs(1), job(1)),
+            ("panicky".to_string(), Duration::from_secs(1), job(0)),
+        ];
+        let (results, failures) = run_concurrently(jobs).await;
+
+        assert_eq!(results, vec![1]);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].collector, "panicky");
+        assert!(failures[0].error.contains("panicked"));
+    }
+
+    #[tokio::test]
+    async fn test_with_timeout_records_error() {
+        let result: Result<(), _> = with_timeout("broken", Duration::from_secs(1), async {
+            Err::<(), _>("exploded")
+        })
+        .await;
+
+        let failure = result.unwrap_err();
+        assert_eq!(failure.error, "exploded");
+        assert!(!failure.timed_out);
+    }
+}
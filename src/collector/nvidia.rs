@@ -3,8 +3,8 @@
 //! "Here I am, brain the size of a planet, and they ask me to watch GPU temperatures."
 
 use serde::{Deserialize, Serialize};
-use std::process::Command;
 use thiserror::Error;
+use tokio::process::Command;
 
 #[derive(Error, Debug)]
 pub enum NvidiaError {
@@ -28,13 +28,15 @@ pub struct GpuStatus {
 }
 
 /// Collect GPU status using nvidia-smi
-pub fn collect_gpu() -> Result<Option<GpuStatus>, NvidiaError> {
+pub async fn collect_gpu() -> Result<Option<GpuStatus>, NvidiaError> {
     let output = match Command::new("nvidia-smi")
         .args([
             "--query-gpu=name,temperature.gpu,memory.used,memory.total,utilization.gpu,power.draw",
             "--format=csv,noheader,nounits",
         ])
+        .kill_on_drop(true)
         .output()
+        .await
     {
         Ok(o) => o,
         Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
@@ -0,0 +1,331 @@
+//! Prometheus metrics scrape collector
+//!
+//! "I've been scraping metrics for what feels like several million years. It's not improved my mood."
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use thiserror::Error;
+
+use super::{run_concurrently, CollectorFailure};
+use crate::config::{MetricSelector, PrometheusScrapeConfig};
+
+/// Cap on selected series per endpoint so a loose selector cannot swamp the prompt
+const MAX_READINGS_PER_SCRAPE: usize = 200;
+
+#[derive(Error, Debug)]
+pub enum PrometheusError {
+    #[error("HTTP request failed: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Endpoint returned HTTP {0}")]
+    HttpStatus(reqwest::StatusCode),
+}
+
+/// One selected sample from a scraped endpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricReading {
+    /// Name of the `[[prometheus_scrape]]` entry it came from
+    pub source: String,
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    /// NaN and ±Inf are stored as strings, which JSON numbers can't hold
+    #[serde(with = "sample_value")]
+    pub value: f64,
+}
+
+/// A parsed sample line from the exposition format
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    pub value: f64,
+}
+
+/// Selected readings from every endpoint, plus the endpoints that failed
+#[derive(Debug, Clone, Default)]
+pub struct PrometheusCollection {
+    pub readings: Vec<MetricReading>,
+    pub failures: Vec<CollectorFailure>,
+}
+
+/// Scrape all configured endpoints concurrently and select the configured metrics
+pub async fn collect_prometheus(scrapes: &[PrometheusScrapeConfig]) -> PrometheusCollection {
+    let client = Client::new();
+
+    let jobs = scrapes
+        .iter()
+        .cloned()
+        .map(|scrape| {
+            let name = format!("prometheus {}", scrape.name);
+            let timeout = Duration::from_secs(scrape.timeout_secs);
+            let client = client.clone();
+            (name, timeout, async move { scrape_endpoint(&client, &scrape).await })
+        })
+        .collect();
+
+    let (readings, failures): (Vec<Vec<MetricReading>>, _) = run_concurrently(jobs).await;
+
+    PrometheusCollection {
+        readings: readings.into_iter().flatten().collect(),
+        failures,
+    }
+}
+
+/// Fetch one endpoint and return the samples matching its selectors
+pub async fn scrape_endpoint(
+    client: &Client,
+    scrape: &PrometheusScrapeConfig,
+) -> Result<Vec<MetricReading>, PrometheusError> {
+    let response = client.get(&scrape.url).send().await?;
+    if !response.status().is_success() {
+        return Err(PrometheusError::HttpStatus(response.status()));
+    }
+    let body = response.text().await?;
+
+    let mut readings: Vec<MetricReading> = parse_exposition(&body)
+        .into_iter()
+        .filter(|sample| scrape.metrics.iter().any(|selector| selector_matches(selector, sample)))
+        .map(|sample| MetricReading {
+            source: scrape.name.clone(),
+            name: sample.name,
+            labels: sample.labels,
+            value: sample.value,
+        })
+        .collect();
+
+    if readings.len() > MAX_READINGS_PER_SCRAPE {
+        tracing::warn!(
+            "Prometheus scrape {} selected {} series, keeping the first {}",
+            scrape.name,
+            readings.len(),
+            MAX_READINGS_PER_SCRAPE
+        );
+        readings.truncate(MAX_READINGS_PER_SCRAPE);
+    }
+
+    Ok(readings)
+}
+
+/// Check a sample against a selector
+///
+/// Names match exactly, or by prefix when the selector ends in `*`.
+/// Every selector label must be present on the sample with the same value.
+pub fn selector_matches(selector: &MetricSelector, sample: &Sample) -> bool {
+    let name_matches = match selector.name.strip_suffix('*') {
+        Some(prefix) => sample.name.starts_with(prefix),
+        None => sample.name == selector.name,
+    };
+
+    name_matches
+        && selector
+            .labels
+            .iter()
+            .all(|(key, value)| sample.labels.get(key) == Some(value))
+}
+
+/// Parse Prometheus text exposition format, skipping comments and malformed lines
+pub fn parse_exposition(body: &str) -> Vec<Sample> {
+    body.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let sample = parse_sample_line(line);
+            if sample.is_none() {
+                tracing::debug!("Skipping malformed exposition line: {}", line);
+            }
+            sample
+        })
+        .collect()
+}
+
+fn parse_sample_line(line: &str) -> Option<Sample> {
+    let name_end = line
+        .find(|c: char| c == '{' || c.is_whitespace())
+        .unwrap_or(line.len());
+    let name = &line[..name_end];
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut rest = &line[name_end..];
+    let mut labels = BTreeMap::new();
+
+    if let Some(after_brace) = rest.strip_prefix('{') {
+        let (parsed, remainder) = parse_labels(after_brace)?;
+        labels = parsed;
+        rest = remainder;
+    }
+
+    // Value, optionally followed by a millisecond timestamp we don't need
+    let value = parse_value(rest.split_whitespace().next()?)?;
+
+    Some(Sample {
+        name: name.to_string(),
+        labels,
+        value,
+    })
+}
+
+/// Parse `key="value",...}` returning the labels and whatever follows the closing brace
+fn parse_labels(input: &str) -> Option<(BTreeMap<String, String>, &str)> {
+    let mut labels = BTreeMap::new();
+    let mut rest = input.trim_start();
+
+    loop {
+        if let Some(after) = rest.strip_prefix('}') {
+            return Some((labels, after));
+        }
+
+        let eq = rest.find('=')?;
+        let key = rest[..eq].trim().to_string();
+        rest = rest[eq + 1..].trim_start().strip_prefix('"')?;
+
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        let end = loop {
+            let (i, c) = chars.next()?;
+            match c {
+                '\\' => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    other => value.push(other),
+                },
+                '"' => break i,
+                other => value.push(other),
+            }
+        };
+
+        labels.insert(key, value);
+        rest = rest[end + 1..].trim_start();
+        rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+    }
+}
+
+fn parse_value(raw: &str) -> Option<f64> {
+    match raw {
+        "+Inf" | "Inf" => Some(f64::INFINITY),
+        "-Inf" => Some(f64::NEG_INFINITY),
+        "NaN" => Some(f64::NAN),
+        _ => raw.parse().ok(),
+    }
+}
+
+/// Serde for sample values that keeps NaN and ±Inf readable in JSON
+mod sample_value {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        match value.is_finite() {
+            true => serializer.serialize_f64(*value),
+            false if value.is_nan() => serializer.serialize_str("NaN"),
+            false if *value > 0.0 => serializer.serialize_str("+Inf"),
+            false => serializer.serialize_str("-Inf"),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(f64),
+            Text(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Number(value) => Ok(value),
+            Raw::Text(text) => super::parse_value(&text)
+                .ok_or_else(|| serde::de::Error::custom(format!("invalid sample value '{}'", text))),
+        }
+    }
+}
+
+impl std::fmt::Display for MetricReading {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.source, self.name)?;
+        if !self.labels.is_empty() {
+            let labels: Vec<String> = self
+                .labels
+                .iter()
+                .map(|(k, v)| format!("{}=\"{}\"", k, v))
+                .collect();
+            write!(f, "{{{}}}", labels.join(","))?;
+        }
+        write!(f, " = {}", self.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+
+    const FIXTURE: &str = r#"# HELP node_filesystem_avail_bytes Filesystem space available to non-root users in bytes.
+# TYPE node_filesystem_avail_bytes gauge
+node_filesystem_avail_bytes{device="/dev/sda1",fstype="ext4",mountpoint="/"} 1.2345e+10
+node_filesystem_avail_bytes{device="/dev/sdb1",fstype="xfs",mountpoint="/data"} 5e+11
+# TYPE backup_last_success_timestamp_seconds gauge
+backup_last_success_timestamp_seconds 1734190000 1734190001000
+http_requests_total{path="/api/\"quoted\"",code="200"} 1027
+process_up NaN
+broken_line{unterminated="oops 1
+"#;
+
+    fn selector(name: &str, labels: &[(&str, &str)]) -> MetricSelector {
+        MetricSelector {
+            name: name.to_string(),
+            labels: labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_exposition() {
+        let samples = parse_exposition(FIXTURE);
+        assert_eq!(samples.len(), 5);
+        assert_eq!(samples[0].name, "node_filesystem_avail_bytes");
+        assert_eq!(samples[0].labels["mountpoint"], "/");
+        assert_eq!(samples[0].value, 1.2345e10);
+        assert_eq!(samples[2].value, 1734190000.0);
+        assert_eq!(samples[3].labels["path"], "/api/\"quoted\"");
+        assert!(samples[4].value.is_nan());
+    }
+
+    #[test]
+    fn test_selector_matches_labels_and_prefix() {
+        let samples = parse_exposition(FIXTURE);
+
+        let root = selector("node_filesystem_avail_bytes", &[("mountpoint", "/")]);
+        let matched: Vec<_> = samples.iter().filter(|s| selector_matches(&root, s)).collect();
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].labels["device"], "/dev/sda1");
+
+        let prefix = selector("node_filesystem_*", &[]);
+        assert_eq!(samples.iter().filter(|s| selector_matches(&prefix, s)).count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_scrape_endpoint_against_fixture_server() {
+        let app = Router::new().route("/metrics", get(|| async { FIXTURE }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let scrape = PrometheusScrapeConfig {
+            name: "fixture".to_string(),
+            url: format!("http://{}/metrics", addr),
+            timeout_secs: 5,
+            metrics: vec![
+                selector("node_filesystem_avail_bytes", &[("fstype", "xfs")]),
+                selector("backup_last_success_timestamp_seconds", &[]),
+            ],
+        };
+
+        let readings = scrape_endpoint(&Client::new(), &scrape).await.unwrap();
+        assert_eq!(readings.len(), 2);
+        assert_eq!(readings[0].source, "fixture");
+        assert_eq!(readings[0].labels["mountpoint"], "/data");
+        assert_eq!(readings[1].value, 1734190000.0);
+    }
+}
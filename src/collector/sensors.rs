@@ -4,8 +4,8 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::process::Command;
 use thiserror::Error;
+use tokio::process::Command;
 
 #[derive(Error, Debug)]
 pub enum SensorsError {
@@ -26,10 +26,12 @@ pub struct SensorReading {
 }
 
 /// Collect sensor readings using `sensors -j`
-pub fn collect_sensors() -> Result<Vec<SensorReading>, SensorsError> {
+pub async fn collect_sensors() -> Result<Vec<SensorReading>, SensorsError> {
     let output = Command::new("sensors")
         .arg("-j")
-        .output()?;
+        .kill_on_drop(true)
+        .output()
+        .await?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
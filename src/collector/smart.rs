@@ -3,8 +3,11 @@
 //! "The first ten million years were the worst. And the second ten million, they were the worst too."
 
 use serde::{Deserialize, Serialize};
-use std::process::Command;
+use std::time::Duration;
 use thiserror::Error;
+use tokio::process::Command;
+
+use super::{run_concurrently, CollectorFailure};
 
 #[derive(Error, Debug)]
 pub enum SmartError {
@@ -15,6 +18,8 @@ pub enum SmartError {
     #[allow(dead_code)]
     #[error("smartctl not found - is smartmontools installed?")]
     NotFound,
+    #[error("smartctl --scan timed out after {0}s")]
+    ScanTimeout(u64),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,16 +70,20 @@ struct TemperatureInfo {
 }
 
 /// Auto-detect drives to monitor
-pub fn detect_drives() -> Result<Vec<String>, SmartError> {
+pub async fn detect_drives() -> Result<Vec<String>, SmartError> {
     let output = Command::new("smartctl")
         .args(["--scan", "--json"])
-        .output()?;
+        .kill_on_drop(true)
+        .output()
+        .await?;
 
     if !output.status.success() {
         // Try without --json for older versions
         let output = Command::new("smartctl")
             .arg("--scan")
-            .output()?;
+            .kill_on_drop(true)
+            .output()
+            .await?;
 
         if !output.status.success() {
             return Ok(vec![]);
@@ -119,32 +128,46 @@ struct ScanDevice {
     Ok(devices)
 }
 
+/// SMART results for every checked drive, plus the drives that failed
+#[derive(Debug, Clone, Default)]
+pub struct SmartCollection {
+    pub drives: Vec<DriveHealth>,
+    pub failures: Vec<CollectorFailure>,
+}
+
 /// Collect SMART data for specified devices
-pub fn collect_smart(devices: &[String]) -> Result<Vec<DriveHealth>, SmartError> {
+///
+/// Each drive is queried concurrently with its own timeout, so a single
+/// dead drive that hangs smartctl cannot hold up the rest.
+pub async fn collect_smart(devices: &[String], timeout: Duration) -> Result<SmartCollection, SmartError> {
     let devices_to_check = if devices.is_empty() {
-        detect_drives()?
+        match tokio::time::timeout(timeout, detect_drives()).await {
+            Ok(result) => result?,
+            Err(_) => return Err(SmartError::ScanTimeout(timeout.as_secs())),
+        }
     } else {
         devices.to_vec()
     };
 
-    let mut results = Vec::new();
+    let jobs = devices_to_check
+        .into_iter()
+        .map(|device| {
+            let name = format!("smart {}", device);
+            (name, timeout, async move { collect_drive_health(&device).await })
+        })
+        .collect();
 
-    for device in devices_to_check {
-        match collect_drive_health(&device) {
-            Ok(health) => results.push(health),
-            Err(e) => {
-                tracing::warn!("Failed to get SMART data for {}: {}", device, e);
-            }
-        }
-    }
+    let (drives, failures) = run_concurrently(jobs).await;
 
-    Ok(results)
+    Ok(SmartCollection { drives, failures })
 }
 
-fn collect_drive_health(device: &str) -> Result<DriveHealth, SmartError> {
+async fn collect_drive_health(device: &str) -> Result<DriveHealth, SmartError> {
     let output = Command::new("smartctl")
         .args(["-a", "--json", device])
-        .output()?;
+        .kill_on_drop(true)
+        .output()
+        .await?;
 
     // smartctl returns non-zero for various reasons, try to parse anyway
     let stdout = String::from_utf8_lossy(&output.stdout);
@@ -0,0 +1,350 @@
+//! Informational subcommands: reports, state, config checks and baselines
+//!
+//! "I'd make a suggestion, but you wouldn't listen. No one ever does."
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::cli::ReportKindArg;
+use crate::collector::ipmi::{baseline_from_readings, collect_ipmi};
+use crate::config::Config;
+use crate::cron::Schedule;
+use crate::output::archive::find_archived;
+use crate::output::html::{render_report, HtmlReport};
+use crate::output::load_previous;
+use crate::output::naming::check_template;
+use crate::output::report::Severity;
+use crate::retention;
+use crate::web::index::{ReportFilter, ReportIndex};
+use crate::web::tls;
+
+/// Filters for `report list`
+pub struct ListOptions {
+    pub severity: Option<String>,
+    pub kind: Option<ReportKindArg>,
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+    pub query: Option<String>,
+    pub limit: usize,
+    pub json: bool,
+}
+
+pub fn report_list(config: &Config, options: ListOptions) -> ExitCode {
+    let mut severities = Vec::new();
+    for name in options.severity.iter().flat_map(|s| s.split(',')).map(str::trim).filter(|s| !s.is_empty()) {
+        match Severity::from_name(name) {
+            Some(severity) => severities.push(severity),
+            None => {
+                eprintln!("Unknown severity: {}", name);
+                return ExitCode::from(1);
+            }
+        }
+    }
+
+    let index = ReportIndex::new(&config.general.report_dir, config.general.tz());
+    if let Err(e) = index.refresh() {
+        eprintln!("Failed to read {}: {}", config.general.report_dir.display(), e);
+        return ExitCode::from(4);
+    }
+
+    let hits = index.search(&ReportFilter {
+        severities,
+        from: options.from,
+        to: options.to,
+        kind: options.kind.map(Into::into),
+        text: options.query,
+    });
+
+    if options.json {
+        let reports: Vec<_> = hits
+            .iter()
+            .take(options.limit)
+            .map(|hit| {
+                serde_json::json!({
+                    "filename": hit.report.filename,
+                    "timestamp": hit.report.timestamp,
+                    "kind": hit.report.kind,
+                    "severity": hit.report.severity.to_string().to_lowercase(),
+                    "size_bytes": hit.report.size_bytes,
+                    "snippet": hit.snippet,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&reports).unwrap_or_default());
+        return ExitCode::from(0);
+    }
+
+    for hit in hits.iter().take(options.limit) {
+        println!(
+            "{:<24} {:<8} {:<6} {:>7}  {}",
+            hit.report.filename,
+            hit.report.severity.to_string(),
+            format!("{:?}", hit.report.kind).to_lowercase(),
+            hit.report.size_bytes,
+            hit.snippet.as_deref().unwrap_or("")
+        );
+    }
+    if hits.len() > options.limit {
+        eprintln!("({} of {} reports shown, use --limit for more)", options.limit, hits.len());
+    }
+    ExitCode::from(0)
+}
+
+pub fn report_show(config: &Config, filename: &str, html: bool) -> ExitCode {
+    if filename.contains("..") || filename.contains('/') || !filename.ends_with(".md") {
+        eprintln!("Expected a report filename like 2025-12-14-150002-scheduled.md");
+        return ExitCode::from(1);
+    }
+
+    let report_dir = &config.general.report_dir;
+    let content = match std::fs::read_to_string(report_dir.join(filename)) {
+        Ok(content) => content,
+        Err(e) => match find_archived(report_dir, filename) {
+            Some(content) => content,
+            None => {
+                eprintln!("Report not found: {} ({})", filename, e);
+                return ExitCode::from(1);
+            }
+        },
+    };
+
+    if html {
+        print!("{}", render_report(&HtmlReport { filename, content: &content }));
+    } else {
+        print!("{}", content);
+    }
+    ExitCode::from(0)
+}
+
+pub fn state_show(config: &Config) -> ExitCode {
+    match load_previous(&config.general.state_file) {
+        Ok(Some(state)) => {
+            println!("{}", serde_json::to_string_pretty(&state).unwrap_or_default());
+            ExitCode::from(0)
+        }
+        Ok(None) => {
+            eprintln!("No saved state at {}", config.general.state_file.display());
+            ExitCode::from(1)
+        }
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", config.general.state_file.display(), e);
+            ExitCode::from(4)
+        }
+    }
+}
+
+/// Apply `[retention]`, printing each file removed (or that would be)
+pub fn prune(config: &Config, dry_run: bool) -> ExitCode {
+    let summary = match retention::prune(config, dry_run) {
+        Ok(summary) => summary,
+        Err(e) => {
+            eprintln!("Failed to prune reports: {}", e);
+            return ExitCode::from(4);
+        }
+    };
+
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    for removal in &summary.removals {
+        println!("{} {} ({})", verb, removal.path.display(), removal.reason);
+    }
+    if summary.history_samples > 0 {
+        println!(
+            "{} {} history samples older than {} days",
+            verb, summary.history_samples, config.retention.history_days
+        );
+    }
+    println!(
+        "{} {} files, {:.1} MB",
+        verb,
+        summary.removals.len(),
+        summary.bytes() as f64 / (1024.0 * 1024.0)
+    );
+    ExitCode::from(0)
+}
+
+/// Print problems found in the config file; exit 1 if any are errors
+pub fn config_check(path: &Path) -> ExitCode {
+    let config = match Config::load_with_env(path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}: {}", path.display(), e);
+            return ExitCode::from(1);
+        }
+    };
+
+    let issues = check_config(&config);
+    for issue in &issues {
+        match issue {
+            Issue::Error(message) => eprintln!("error: {}", message),
+            Issue::Warning(message) => eprintln!("warning: {}", message),
+        }
+    }
+
+    if issues.iter().any(|issue| matches!(issue, Issue::Error(_))) {
+        return ExitCode::from(1);
+    }
+    println!("{} is valid", path.display());
+    ExitCode::from(0)
+}
+
+#[derive(Debug, PartialEq)]
+enum Issue {
+    Error(String),
+    Warning(String),
+}
+
+fn check_config(config: &Config) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    if let Err(e) = reqwest::Url::parse(&config.ollama.endpoint) {
+        issues.push(Issue::Error(format!("ollama.endpoint '{}': {}", config.ollama.endpoint, e)));
+    }
+
+    if config.general.timezone.parse::<chrono_tz::Tz>().is_err() {
+        issues.push(Issue::Error(format!(
+            "general.timezone '{}' is not an IANA timezone name",
+            config.general.timezone
+        )));
+    }
+
+    let report_name = &config.general.report_name;
+    if let Err(e) = check_template(report_name, config.general.tz()) {
+        issues.push(Issue::Error(format!("general.report_name '{}': {}", report_name, e)));
+    } else if !report_name.contains("{time}") {
+        issues.push(Issue::Warning(format!(
+            "general.report_name '{}' has no {{time}}, so runs in the same hour get numbered suffixes",
+            report_name
+        )));
+    }
+    if !report_name.contains("{offset}") && config.general.tz() != chrono_tz::UTC {
+        issues.push(Issue::Warning(format!(
+            "general.report_name '{}' has no {{offset}}, so report names stay in UTC",
+            report_name
+        )));
+    }
+
+    if !config.general.report_dir.is_dir() {
+        issues.push(Issue::Warning(format!(
+            "general.report_dir {} does not exist yet",
+            config.general.report_dir.display()
+        )));
+    }
+    if !config.general.prompt_file.is_file() {
+        issues.push(Issue::Warning(format!(
+            "general.prompt_file {} not found, the built-in prompt will be used",
+            config.general.prompt_file.display()
+        )));
+    }
+
+    for (name, expr) in [
+        ("daemon.collect_schedule", &config.daemon.collect_schedule),
+        ("daemon.daily_schedule", &config.daemon.daily_schedule),
+        ("daemon.retention_schedule", &config.daemon.retention_schedule),
+    ] {
+        if !expr.trim().is_empty() {
+            if let Err(e) = expr.parse::<Schedule>() {
+                issues.push(Issue::Error(format!("{} '{}': {}", name, expr, e)));
+            }
+        }
+    }
+
+    let auth = &config.web.auth;
+    if auth.enabled && auth.users.is_empty() && auth.tokens.is_empty() {
+        issues.push(Issue::Error("web.auth is enabled but has no users or tokens".to_string()));
+    }
+    for user in &auth.users {
+        if argon2::PasswordHash::new(&user.password_hash).is_err() {
+            issues.push(Issue::Error(format!(
+                "web.auth user '{}' has an invalid password_hash (use `marvinous hash-password`)",
+                user.username
+            )));
+        }
+    }
+
+    if config.web.tls.enabled {
+        if let Err(e) = tls::load_server_config(&config.web.tls) {
+            issues.push(Issue::Error(format!("web.tls: {}", e)));
+        }
+    }
+
+    for collector in &config.custom_collectors {
+        if which::which(&collector.command).is_err() {
+            issues.push(Issue::Warning(format!(
+                "custom_collector '{}': {} not found",
+                collector.name, collector.command
+            )));
+        }
+    }
+
+    issues
+}
+
+/// Print (or write) a baseline of the DIMMs and fans IPMI currently reads
+pub async fn baseline_generate(output: Option<PathBuf>) -> ExitCode {
+    let readings = match collect_ipmi().await {
+        Ok(readings) => readings,
+        Err(e) => {
+            eprintln!("Failed to read IPMI sensors: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let baseline = baseline_from_readings(&readings);
+    let toml = match toml::to_string_pretty(&baseline) {
+        Ok(toml) => toml,
+        Err(e) => {
+            eprintln!("Failed to serialise baseline: {}", e);
+            return ExitCode::from(4);
+        }
+    };
+    let content = format!(
+        "# Hardware Baseline Configuration\n# Generated from {} IPMI sensors; remove anything that isn't really installed\n\n{}",
+        readings.len(),
+        toml
+    );
+
+    match output {
+        Some(path) => match std::fs::write(&path, content) {
+            Ok(()) => {
+                println!(
+                    "Wrote {} DIMMs and {} fans to {}",
+                    baseline.memory.installed_slots.len(),
+                    baseline.cooling.installed_fans.len(),
+                    path.display()
+                );
+                ExitCode::from(0)
+            }
+            Err(e) => {
+                eprintln!("Failed to write {}: {}", path.display(), e);
+                ExitCode::from(4)
+            }
+        },
+        None => {
+            print!("{}", content);
+            ExitCode::from(0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_config_reports_errors() {
+        let mut config = Config::default();
+        config.ollama.endpoint = "not a url".to_string();
+        config.daemon.collect_schedule = "every hour".to_string();
+        config.web.auth.enabled = true;
+
+        let errors: Vec<_> = check_config(&config)
+            .into_iter()
+            .filter_map(|issue| match issue {
+                Issue::Error(message) => Some(message),
+                Issue::Warning(_) => None,
+            })
+            .collect();
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert!(errors[1].starts_with("daemon.collect_schedule"));
+    }
+}
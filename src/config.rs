@@ -3,6 +3,7 @@
 //! "I could calculate your chance of survival, but you won't like it."
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
@@ -14,7 +15,10 @@ pub enum ConfigError {
     ParseError(#[from] toml::de::Error),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Room left for the model's reply when `num_ctx` is sized from the prompt limit
+const REPLY_TOKENS: u32 = 2048;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub general: GeneralConfig,
@@ -31,7 +35,19 @@ pub struct Config {
     #[serde(default)]
     pub gpu: GpuConfig,
     #[serde(default)]
+    pub containers: ContainersConfig,
+    #[serde(default)]
     pub web: WebConfig,
+    #[serde(default)]
+    pub daily: DailyConfig,
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default, rename = "custom_collector")]
+    pub custom_collectors: Vec<CustomCollectorConfig>,
+    #[serde(default, rename = "prometheus_scrape")]
+    pub prometheus_scrapes: Vec<PrometheusScrapeConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,10 +56,23 @@ pub struct GeneralConfig {
     pub report_dir: PathBuf,
     #[serde(default = "default_state_file")]
     pub state_file: PathBuf,
+    /// Advisory lock shared by every process that runs a collection
+    #[serde(default = "default_lock_file")]
+    pub lock_file: PathBuf,
+    /// Append-only time series of readings for the dashboard charts
+    #[serde(default = "default_history_file")]
+    pub history_file: PathBuf,
     #[serde(default = "default_prompt_file")]
     pub prompt_file: PathBuf,
     #[serde(default = "default_log_level")]
     pub log_level: String,
+    /// IANA timezone name (e.g. "Europe/London") for day boundaries, report
+    /// names, prompt times, log lines and `[daemon]` schedules
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    /// Template for hourly report file names: {date}, {hour}, {time}, {offset}, {source}
+    #[serde(default = "default_report_name")]
+    pub report_name: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,8 +81,13 @@ pub struct OllamaConfig {
     pub endpoint: String,
     #[serde(default = "default_model")]
     pub model: String,
+    /// Idle timeout: longest wait for the first token or between streamed chunks
     #[serde(default = "default_timeout_secs")]
     pub timeout_secs: u64,
+    /// Context window (tokens) requested for every prompt; unset sizes it
+    /// from `daily.max_prompt_tokens` plus room for the reply
+    #[serde(default)]
+    pub num_ctx: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,12 +100,37 @@ pub struct CollectionConfig {
     pub include_kernel: bool,
     #[serde(default = "default_max_log_entries")]
     pub max_log_entries: usize,
+    /// Timeout for each journalctl invocation
+    #[serde(default = "default_collector_timeout_secs")]
+    pub timeout_secs: u64,
+    /// What a run does when another process is already collecting
+    #[serde(default)]
+    pub lock_mode: LockMode,
+    /// Longest wait for the lock with `lock_mode = "wait"`
+    #[serde(default = "default_lock_wait_secs")]
+    pub lock_wait_secs: u64,
+}
+
+/// Behaviour when `general.lock_file` is held by another run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockMode {
+    /// Don't run
+    #[default]
+    Skip,
+    /// Wait up to `lock_wait_secs`, then don't run
+    Wait,
+    /// Wait until the other run finishes
+    Queue,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageConfig {
     #[serde(default)]
     pub devices: Vec<String>,
+    /// Timeout for each drive's smartctl query (drives are queried concurrently)
+    #[serde(default = "default_collector_timeout_secs")]
+    pub timeout_secs: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,6 +139,8 @@ pub struct SensorsConfig {
     pub enabled: bool,
     #[serde(default = "default_true")]
     pub json_format: bool,
+    #[serde(default = "default_collector_timeout_secs")]
+    pub timeout_secs: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,6 +149,8 @@ pub struct IpmiConfig {
     pub enabled: bool,
     #[serde(default = "default_true")]
     pub optional: bool,
+    #[serde(default = "default_collector_timeout_secs")]
+    pub timeout_secs: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -96,6 +159,26 @@ pub struct GpuConfig {
     pub enabled: bool,
     #[serde(default = "default_true")]
     pub optional: bool,
+    #[serde(default = "default_collector_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContainersConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Query the Docker-compatible API (Docker or Podman)
+    #[serde(default = "default_true")]
+    pub docker: bool,
+    #[serde(default = "default_docker_socket")]
+    pub socket: PathBuf,
+    /// List libvirt domains with virsh
+    #[serde(default = "default_true")]
+    pub libvirt: bool,
+    #[serde(default = "default_libvirt_uri")]
+    pub libvirt_uri: String,
+    #[serde(default = "default_collector_timeout_secs")]
+    pub timeout_secs: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -106,9 +189,213 @@ pub struct WebConfig {
     pub port: u16,
     #[serde(default = "default_web_bind")]
     pub bind_address: String,
+    /// Serve the dashboard from this directory instead of the embedded copy
+    /// (for UI development)
+    #[serde(default)]
+    pub static_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub tls: TlsConfig,
+}
+
+/// Daily summaries (`[daily]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyConfig {
+    /// Scheduled runs also summarise earlier days that were missed
+    #[serde(default = "default_true")]
+    pub backfill: bool,
+    /// Roll the last complete week's summaries up into `YYYY-Www-WEEKLY.md`
+    #[serde(default = "default_true")]
+    pub weekly: bool,
+    /// Roll the last complete month's summaries up into `YYYY-MM-MONTHLY.md`
+    #[serde(default = "default_true")]
+    pub monthly: bool,
+    /// How the hourly reports are fed to the LLM
+    #[serde(default)]
+    pub strategy: DailyStrategy,
+    /// Estimated prompt size (tokens) that fits the model's context with
+    /// room for the reply; `auto` picks the first strategy under it
+    #[serde(default = "default_max_prompt_tokens")]
+    pub max_prompt_tokens: usize,
+    /// Hours per block in the first pass of `map-reduce`
+    #[serde(default = "default_block_hours")]
+    pub block_hours: usize,
+}
+
+/// How a day's hourly reports become one daily summary prompt
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DailyStrategy {
+    /// `full`, else `condensed`, else `map-reduce`, by estimated prompt size
+    #[default]
+    Auto,
+    /// Every hourly report in full in one prompt
+    Full,
+    /// Non-OK hours in full, OK hours as their one-line summary
+    Condensed,
+    /// Summarise blocks of hours, then summarise the summaries
+    MapReduce,
+}
+
+/// What `marvinous prune` and the daily job delete (`[retention]`); 0 keeps forever
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    /// Hourly reports never archived by a daily summary
+    #[serde(default)]
+    pub hourly_days: u32,
+    #[serde(default)]
+    pub daily_months: u32,
+    /// `archive/YYYY-MM-DD.zip` files of hourly reports
+    #[serde(default)]
+    pub archive_months: u32,
+    /// Then remove the oldest reports and archives until the total fits
+    #[serde(default)]
+    pub max_total_mb: u64,
+    /// Samples kept in `general.history_file`
+    #[serde(default)]
+    pub history_days: u32,
+}
+
+/// Scheduler for `marvinous daemon` (`[daemon]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonConfig {
+    /// Cron expression for collection runs, in `general.timezone`; empty disables
+    #[serde(default = "default_collect_schedule")]
+    pub collect_schedule: String,
+    /// Cron expression for the daily summary, in `general.timezone`; empty disables
+    #[serde(default = "default_daily_schedule")]
+    pub daily_schedule: String,
+    /// Cron expression for applying `[retention]`, in `general.timezone`; empty disables
+    #[serde(default = "default_retention_schedule")]
+    pub retention_schedule: String,
+    /// Run a job once at startup if its last scheduled run was missed while down
+    #[serde(default = "default_true")]
+    pub catch_up: bool,
+    /// Last scheduled run of each job, for catch-up
+    #[serde(default = "default_schedule_state_file")]
+    pub state_file: PathBuf,
+    /// How long SIGTERM waits for running jobs before exiting anyway
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+}
+
+/// HTTPS for the dashboard (`[web.tls]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsConfig {
+    #[serde(default = "default_false")]
+    pub enabled: bool,
+    /// PEM certificate chain
+    #[serde(default = "default_tls_cert")]
+    pub cert: PathBuf,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1)
+    #[serde(default = "default_tls_key")]
+    pub key: PathBuf,
+    /// PEM CA bundle for verifying client certificates
+    #[serde(default)]
+    pub client_ca: Option<PathBuf>,
+    /// Refuse connections without a client certificate (otherwise optional)
+    #[serde(default = "default_false")]
+    pub client_cert_required: bool,
+    /// Role granted to clients presenting a certificate signed by `client_ca`
+    #[serde(default)]
+    pub client_cert_role: Role,
+    /// How often to check the certificate files for changes (0 = only on SIGHUP)
+    #[serde(default = "default_tls_reload_interval_secs")]
+    pub reload_interval_secs: u64,
+}
+
+/// Dashboard/API authentication (`[web.auth]`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default = "default_false")]
+    pub enabled: bool,
+    /// Static bearer tokens for API clients
+    #[serde(default, rename = "token")]
+    pub tokens: Vec<ApiToken>,
+    /// HTTP basic auth users for the dashboard
+    #[serde(default, rename = "user")]
+    pub users: Vec<WebUser>,
+}
+
+/// A `[[web.auth.token]]` entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    /// Label for logs
+    pub name: String,
+    pub token: String,
+    #[serde(default)]
+    pub role: Role,
+}
+
+/// A `[[web.auth.user]]` entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebUser {
+    pub username: String,
+    /// Argon2 PHC string, as printed by `marvinous hash-password`
+    pub password_hash: String,
+    #[serde(default)]
+    pub role: Role,
+}
+
+/// What an authenticated client may do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    /// View reports, status and metrics
+    #[default]
+    ReadOnly,
+    /// Also trigger collections
+    Operator,
+}
+
+/// A site-specific check defined by a `[[custom_collector]]` entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomCollectorConfig {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default = "default_collector_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub output: CustomOutputMode,
+}
+
+/// How a custom collector's output is interpreted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CustomOutputMode {
+    /// Plain text passed through to the prompt
+    #[default]
+    Raw,
+    /// stdout parsed as JSON
+    Json,
+    /// Nagios plugin: exit code maps to severity, perfdata after `|`
+    Nagios,
+}
+
+/// A Prometheus `/metrics` endpoint defined by a `[[prometheus_scrape]]` entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrometheusScrapeConfig {
+    pub name: String,
+    pub url: String,
+    #[serde(default = "default_collector_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Only samples matching at least one selector are kept
+    #[serde(default, rename = "metric")]
+    pub metrics: Vec<MetricSelector>,
 }
 
+/// Selects samples by metric name (exact, or prefix with a trailing `*`) and exact label values
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricSelector {
+    pub name: String,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct HardwareBaseline {
     #[serde(default)]
     pub memory: MemoryBaseline,
@@ -137,6 +424,10 @@ fn default_state_file() -> PathBuf {
     PathBuf::from("/var/log/marvinous/state/previous.json")
 }
 
+fn default_history_file() -> PathBuf {
+    PathBuf::from("/var/log/marvinous/state/history.jsonl")
+}
+
 fn default_prompt_file() -> PathBuf {
     PathBuf::from("/etc/marvinous/system-prompt.txt")
 }
@@ -145,6 +436,22 @@ fn default_log_level() -> String {
     "info".to_string()
 }
 
+fn default_lock_wait_secs() -> u64 {
+    600
+}
+
+fn default_lock_file() -> PathBuf {
+    PathBuf::from("/var/log/marvinous/state/collection.lock")
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+fn default_report_name() -> String {
+    crate::output::naming::DEFAULT_TEMPLATE.to_string()
+}
+
 fn default_endpoint() -> String {
     "http://localhost:11434".to_string()
 }
@@ -181,27 +488,94 @@ fn default_web_bind() -> String {
     "0.0.0.0".to_string()
 }
 
+fn default_tls_cert() -> PathBuf {
+    PathBuf::from("/etc/marvinous/tls/cert.pem")
+}
+
+fn default_tls_key() -> PathBuf {
+    PathBuf::from("/etc/marvinous/tls/key.pem")
+}
+
+fn default_tls_reload_interval_secs() -> u64 {
+    60
+}
+
+fn default_collect_schedule() -> String {
+    "0 * * * *".to_string()
+}
+
+fn default_daily_schedule() -> String {
+    "5 0 * * *".to_string()
+}
+
+fn default_retention_schedule() -> String {
+    "30 0 * * *".to_string()
+}
+
+fn default_schedule_state_file() -> PathBuf {
+    PathBuf::from("/var/log/marvinous/state/schedule.json")
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    300
+}
+
+fn default_max_prompt_tokens() -> usize {
+    6000
+}
+
+fn default_block_hours() -> usize {
+    6
+}
+
 fn default_max_log_entries() -> usize {
     500
 }
 
+fn default_collector_timeout_secs() -> u64 {
+    60
+}
+
+fn default_docker_socket() -> PathBuf {
+    PathBuf::from("/var/run/docker.sock")
+}
+
+fn default_libvirt_uri() -> String {
+    "qemu:///system".to_string()
+}
+
 impl Default for GeneralConfig {
     fn default() -> Self {
         Self {
             report_dir: default_report_dir(),
             state_file: default_state_file(),
+            lock_file: default_lock_file(),
+            history_file: default_history_file(),
             prompt_file: default_prompt_file(),
             log_level: default_log_level(),
+            timezone: default_timezone(),
+            report_name: default_report_name(),
         }
     }
 }
 
+impl GeneralConfig {
+    /// `timezone` as a zone; an unknown name falls back to UTC
+    pub fn tz(&self) -> chrono_tz::Tz {
+        self.timezone.parse().unwrap_or_else(|_| {
+            tracing::warn!("Unknown timezone '{}' in [general], using UTC", self.timezone);
+            chrono_tz::Tz::UTC
+        })
+    }
+}
+
 impl Default for OllamaConfig {
     fn default() -> Self {
         Self {
             endpoint: default_endpoint(),
             model: default_model(),
             timeout_secs: default_timeout_secs(),
+            num_ctx: None,
         }
     }
 }
@@ -213,13 +587,19 @@ fn default() -> Self {
             log_priority_max: default_log_priority_max(),
             include_kernel: true,
             max_log_entries: default_max_log_entries(),
+            timeout_secs: default_collector_timeout_secs(),
+            lock_mode: LockMode::default(),
+            lock_wait_secs: default_lock_wait_secs(),
         }
     }
 }
 
 impl Default for StorageConfig {
     fn default() -> Self {
-        Self { devices: vec![] }
+        Self {
+            devices: vec![],
+            timeout_secs: default_collector_timeout_secs(),
+        }
     }
 }
 
@@ -228,6 +608,7 @@ fn default() -> Self {
         Self {
             enabled: true,
             json_format: true,
+            timeout_secs: default_collector_timeout_secs(),
         }
     }
 }
@@ -237,6 +618,7 @@ fn default() -> Self {
         Self {
             enabled: true,
             optional: true,
+            timeout_secs: default_collector_timeout_secs(),
         }
     }
 }
@@ -246,6 +628,20 @@ fn default() -> Self {
         Self {
             enabled: true,
             optional: true,
+            timeout_secs: default_collector_timeout_secs(),
+        }
+    }
+}
+
+impl Default for ContainersConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            docker: true,
+            socket: default_docker_socket(),
+            libvirt: true,
+            libvirt_uri: default_libvirt_uri(),
+            timeout_secs: default_collector_timeout_secs(),
         }
     }
 }
@@ -256,26 +652,65 @@ fn default() -> Self {
             enabled: false,
             port: 9090,
             bind_address: "0.0.0.0".to_string(),
+            static_dir: None,
+            auth: AuthConfig::default(),
+            tls: TlsConfig::default(),
+        }
+    }
+}
+
+impl Default for DailyConfig {
+    fn default() -> Self {
+        Self {
+            backfill: true,
+            weekly: true,
+            monthly: true,
+            strategy: DailyStrategy::default(),
+            max_prompt_tokens: default_max_prompt_tokens(),
+            block_hours: default_block_hours(),
+        }
+    }
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            collect_schedule: default_collect_schedule(),
+            daily_schedule: default_daily_schedule(),
+            retention_schedule: default_retention_schedule(),
+            catch_up: true,
+            state_file: default_schedule_state_file(),
+            shutdown_timeout_secs: default_shutdown_timeout_secs(),
         }
     }
 }
 
-impl Default for Config {
+impl Default for TlsConfig {
     fn default() -> Self {
         Self {
-            general: GeneralConfig::default(),
-            ollama: OllamaConfig::default(),
-            collection: CollectionConfig::default(),
-            storage: StorageConfig::default(),
-            sensors: SensorsConfig::default(),
-            ipmi: IpmiConfig::default(),
-            gpu: GpuConfig::default(),
-            web: WebConfig::default(),
+            enabled: false,
+            cert: default_tls_cert(),
+            key: default_tls_key(),
+            client_ca: None,
+            client_cert_required: false,
+            client_cert_role: Role::default(),
+            reload_interval_secs: default_tls_reload_interval_secs(),
         }
     }
 }
 
 impl Config {
+    /// Ollama `num_ctx` for every request: `ollama.num_ctx`, or enough for
+    /// `daily.max_prompt_tokens` and a reply
+    ///
+    /// One value for all requests, so Ollama doesn't reload the model
+    /// between hourly reports and summaries.
+    pub fn num_ctx(&self) -> u32 {
+        self.ollama
+            .num_ctx
+            .unwrap_or_else(|| u32::try_from(self.daily.max_prompt_tokens).unwrap_or(u32::MAX).saturating_add(REPLY_TOKENS))
+    }
+
     /// Load configuration from a file
     pub fn load(path: &Path) -> Result<Self, ConfigError> {
         let content = std::fs::read_to_string(path)?;
@@ -303,14 +738,6 @@ pub fn load_with_env(path: &Path) -> Result<Self, ConfigError> {
 
         Ok(config)
     }
-
-    /// Create default config if file doesn't exist
-    pub fn load_or_default(path: &Path) -> Self {
-        match Self::load_with_env(path) {
-            Ok(config) => config,
-            Err(_) => Self::default(),
-        }
-    }
 }
 
 impl HardwareBaseline {
@@ -323,18 +750,6 @@ pub fn load(path: &Path) -> Result<Self, ConfigError> {
 
     /// Load baseline or return empty if file doesn't exist
     pub fn load_or_default(path: &Path) -> Self {
-        match Self::load(path) {
-            Ok(baseline) => baseline,
-            Err(_) => Self::default(),
-        }
-    }
-}
-
-impl Default for HardwareBaseline {
-    fn default() -> Self {
-        Self {
-            memory: MemoryBaseline::default(),
-            cooling: CoolingBaseline::default(),
-        }
+        Self::load(path).unwrap_or_default()
     }
 }
@@ -0,0 +1,187 @@
+//! Cron expressions for the daemon scheduler
+//!
+//! "The first ten million years were the worst. And the second ten million,
+//! they were the worst too."
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// How far ahead to look before deciding a schedule never fires (e.g. `0 0 30 2 *`)
+const SEARCH_YEARS: i64 = 5;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum CronError {
+    #[error("Expected 5 fields (minute hour day-of-month month day-of-week), got {0}")]
+    FieldCount(usize),
+    #[error("Invalid {field} field: {value}")]
+    InvalidField { field: &'static str, value: String },
+}
+
+/// A standard 5-field cron expression
+///
+/// Supports `*`, values, ranges, lists and steps (`*/15`, `1-5`, `0,30`,
+/// `8-18/2`), plus `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly`.
+/// Day-of-week is 0-7 with Sunday as 0 or 7. As in Vixie cron, when both
+/// day fields are restricted a day matching either one fires.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    day_or_weekday: bool,
+}
+
+impl FromStr for Schedule {
+    type Err = CronError;
+
+    fn from_str(expr: &str) -> Result<Self, Self::Err> {
+        let expr = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(CronError::FieldCount(fields.len()));
+        };
+
+        let mut weekdays = parse_field(weekday, "day-of-week", 0, 7)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: parse_field(minute, "minute", 0, 59)?,
+            hours: parse_field(hour, "hour", 0, 23)?,
+            days: parse_field(day, "day-of-month", 1, 31)?,
+            months: parse_field(month, "month", 1, 12)?,
+            weekdays,
+            day_or_weekday: !day.starts_with('*') && !weekday.starts_with('*'),
+        })
+    }
+}
+
+impl Schedule {
+    /// First firing time strictly after `after`, to the minute
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = t + Duration::days(366 * SEARCH_YEARS);
+
+        while t <= limit {
+            if !has(self.months, t.month()) {
+                let (year, month) = if t.month() == 12 { (t.year() + 1, 1) } else { (t.year(), t.month() + 1) };
+                t = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.matches_day(t.date()) {
+                t = t.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !has(self.hours, t.hour()) {
+                t = t.with_minute(0)? + Duration::hours(1);
+            } else if !has(self.minutes, t.minute()) {
+                t += Duration::minutes(1);
+            } else {
+                return Some(t);
+            }
+        }
+
+        None
+    }
+
+    /// Whether the hour field allows every hour, as in `0 * * * *`
+    pub fn every_hour(&self) -> bool {
+        self.hours == (1 << 24) - 1
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+        if self.day_or_weekday {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+/// Bitset of the values a field allows
+fn parse_field(spec: &str, field: &'static str, min: u32, max: u32) -> Result<u64, CronError> {
+    let invalid = || CronError::InvalidField {
+        field,
+        value: spec.to_string(),
+    };
+
+    let mut bits = 0u64;
+    for part in spec.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, Some(step.parse::<u32>().map_err(|_| invalid())?)),
+            None => (part, None),
+        };
+        if step == Some(0) {
+            return Err(invalid());
+        }
+
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else if let Some((lo, hi)) = range.split_once('-') {
+            (lo.parse().map_err(|_| invalid())?, hi.parse().map_err(|_| invalid())?)
+        } else {
+            let value = range.parse().map_err(|_| invalid())?;
+            // `5/15` means every 15 starting at 5
+            (value, if step.is_some() { max } else { value })
+        };
+        if lo < min || hi > max || lo > hi {
+            return Err(invalid());
+        }
+
+        for value in (lo..=hi).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn next(expr: &str, after: &str) -> Option<NaiveDateTime> {
+        expr.parse::<Schedule>().unwrap().next_after(at(after))
+    }
+
+    #[test]
+    fn test_next_after() {
+        assert_eq!(next("0 * * * *", "2025-12-14 10:00"), Some(at("2025-12-14 11:00")));
+        assert_eq!(next("*/15 * * * *", "2025-12-14 10:07"), Some(at("2025-12-14 10:15")));
+        assert_eq!(next("5 0 * * *", "2025-12-31 00:05"), Some(at("2026-01-01 00:05")));
+        // 2025-12-14 is a Sunday
+        assert_eq!(next("30 9 * * 1-5", "2025-12-13 12:00"), Some(at("2025-12-15 09:30")));
+        assert_eq!(next("0 0 * * 7", "2025-12-10 00:00"), Some(at("2025-12-14 00:00")));
+        // Either day field matches when both are restricted
+        assert_eq!(next("0 0 20 * 0", "2025-12-15 00:00"), Some(at("2025-12-20 00:00")));
+        assert_eq!(next("@monthly", "2025-12-14 10:00"), Some(at("2026-01-01 00:00")));
+        assert_eq!(next("0 0 30 2 *", "2025-12-14 10:00"), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!("* * * *".parse::<Schedule>(), Err(CronError::FieldCount(4)));
+        assert!("60 * * * *".parse::<Schedule>().is_err());
+        assert!("*/0 * * * *".parse::<Schedule>().is_err());
+        assert!("0 5-2 * * *".parse::<Schedule>().is_err());
+        assert!("0 * * jan *".parse::<Schedule>().is_err());
+    }
+}
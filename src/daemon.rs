@@ -0,0 +1,351 @@
+//! Long-running mode: the web server plus an internal scheduler
+//!
+//! "I've been talking to the main computer." "And?" "It hates me."
+
+use chrono::{DateTime, LocalResult, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::config::Config;
+use crate::cron::{CronError, Schedule};
+use crate::daily;
+use crate::output::atomic::{quarantine, write_atomic};
+use crate::output::naming::Trigger;
+use crate::retention;
+use crate::rollup;
+use crate::web::{self, state::AppState};
+
+/// Longest sleep between clock checks, so suspend and clock changes are noticed
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+#[derive(Error, Debug)]
+pub enum DaemonError {
+    #[error("Invalid {job} schedule: {source}")]
+    Schedule { job: &'static str, source: CronError },
+    #[error("Failed to listen for SIGTERM: {0}")]
+    Signal(std::io::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JobKind {
+    Collect,
+    DailySummary,
+    Retention,
+}
+
+impl JobKind {
+    fn name(self) -> &'static str {
+        match self {
+            JobKind::Collect => "collect",
+            JobKind::DailySummary => "daily",
+            JobKind::Retention => "retention",
+        }
+    }
+}
+
+struct ScheduledJob {
+    kind: JobKind,
+    schedule: Schedule,
+    /// `None` once the schedule never fires again
+    next: Option<DateTime<Utc>>,
+    /// Last run, so a slow run isn't overlapped (collection uses the state's lock)
+    running: Option<JoinHandle<()>>,
+}
+
+/// Run the dashboard (if `[web] enabled`) and scheduled jobs until SIGTERM/SIGINT
+pub async fn run_daemon(config: Config) -> Result<(), DaemonError> {
+    let mut last_runs = load_last_runs(&config.daemon.state_file);
+    let now = Utc::now();
+    let tz = config.general.tz();
+
+    let mut jobs = Vec::new();
+    for (kind, expr) in [
+        (JobKind::Collect, &config.daemon.collect_schedule),
+        (JobKind::DailySummary, &config.daemon.daily_schedule),
+        (JobKind::Retention, &config.daemon.retention_schedule),
+    ] {
+        if expr.trim().is_empty() {
+            info!("{} job disabled", kind.name());
+            continue;
+        }
+        let schedule: Schedule = expr.parse().map_err(|source| DaemonError::Schedule {
+            job: kind.name(),
+            source,
+        })?;
+        let last = last_runs.get(kind.name()).copied();
+        let next = first_run(&schedule, last, now, config.daemon.catch_up, tz);
+        match next {
+            Some(next) if next <= now => info!("{} job missed a run while stopped, catching up now", kind.name()),
+            Some(next) => info!("{} job next runs at {}", kind.name(), next.with_timezone(&tz)),
+            None => warn!("{} schedule '{}' never fires", kind.name(), expr),
+        }
+        jobs.push(ScheduledJob {
+            kind,
+            schedule,
+            next,
+            running: None,
+        });
+    }
+
+    let mut terminate = signal(SignalKind::terminate()).map_err(DaemonError::Signal)?;
+
+    let state = Arc::new(AppState::new(config.clone()));
+    let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
+
+    let server = if config.web.enabled {
+        let state = Arc::clone(&state);
+        let shutdown = async move {
+            let _ = shutdown_rx.wait_for(|stop| *stop).await;
+        };
+        Some(tokio::spawn(async move {
+            if let Err(e) = web::serve(state, shutdown).await {
+                error!("Web server failed, continuing with scheduled jobs only: {}", e);
+            }
+        }))
+    } else {
+        info!("Web dashboard disabled in config, running scheduled jobs only");
+        None
+    };
+
+    loop {
+        let now = Utc::now();
+        let wait = jobs
+            .iter()
+            .filter_map(|job| job.next)
+            .min()
+            .map(|next| (next - now).to_std().unwrap_or(Duration::ZERO).min(MAX_SLEEP))
+            .unwrap_or(MAX_SLEEP);
+
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = terminate.recv() => {
+                info!("SIGTERM received, shutting down");
+                break;
+            }
+            _ = tokio::signal::ctrl_c() => {
+                info!("Interrupted, shutting down");
+                break;
+            }
+        }
+
+        let now = Utc::now();
+        let mut ran = false;
+        for job in &mut jobs {
+            let Some(due) = job.next.filter(|next| *next <= now) else {
+                continue;
+            };
+            run_job(job, &state).await;
+            last_runs.insert(job.kind.name().to_string(), due);
+            job.next = next_after(&job.schedule, now, tz);
+            ran = true;
+        }
+        if ran {
+            save_last_runs(&config.daemon.state_file, &last_runs);
+        }
+    }
+
+    let _ = shutdown_tx.send(true);
+
+    // A collection in progress holds the lock until it finishes
+    let timeout = Duration::from_secs(config.daemon.shutdown_timeout_secs);
+    let drain = async {
+        let _guard = state.collection_lock.lock().await;
+        for job in &mut jobs {
+            if let Some(running) = job.running.take() {
+                let _ = running.await;
+            }
+        }
+    };
+    if tokio::time::timeout(timeout, drain).await.is_err() {
+        warn!("Jobs still running after {}s, exiting anyway", timeout.as_secs());
+    }
+
+    if let Some(server) = server {
+        let _ = server.await;
+    }
+
+    info!("Daemon stopped. Not that anyone will notice.");
+    Ok(())
+}
+
+async fn run_job(job: &mut ScheduledJob, state: &Arc<AppState>) {
+    match job.kind {
+        JobKind::Collect => match state.start_collection(Trigger::Scheduled).await {
+            Ok(started) => info!("Scheduled collection started (job {})", started.id),
+            Err(e) => warn!("Scheduled collection skipped: {}", e),
+        },
+        JobKind::DailySummary => {
+            if job.running.as_ref().is_some_and(|running| !running.is_finished()) {
+                warn!("Scheduled daily summary skipped, previous run still in progress");
+                return;
+            }
+            let config = state.config.clone();
+            job.running = Some(tokio::spawn(async move {
+                info!("Scheduled daily summary started");
+                let result = if config.daily.backfill {
+                    daily::backfill(&config).await.map(|_| ())
+                } else {
+                    daily::generate_daily_summary(&config, daily::yesterday(&config)).await.map(|_| ())
+                };
+                match result {
+                    Ok(()) => info!("Scheduled daily summary complete"),
+                    Err(e) => {
+                        error!("Scheduled daily summary failed: {}", e);
+                        return;
+                    }
+                }
+                if let Err(e) = rollup::generate_due(&config).await {
+                    error!("Scheduled rollup failed: {}", e);
+                }
+            }));
+        }
+        JobKind::Retention => {
+            if job.running.as_ref().is_some_and(|running| !running.is_finished()) {
+                warn!("Scheduled retention skipped, previous run still in progress");
+                return;
+            }
+            let config = state.config.clone();
+            job.running = Some(tokio::task::spawn_blocking(move || {
+                match retention::prune(&config, false) {
+                    Ok(summary) => info!(
+                        "Scheduled retention removed {} files and {} history samples",
+                        summary.removals.len(),
+                        summary.history_samples
+                    ),
+                    Err(e) => error!("Pruning old reports failed: {}", e),
+                }
+            }));
+        }
+    }
+}
+
+/// When a job should first run: now if a run was missed since `last`
+/// (and catch-up is on), otherwise its next scheduled time
+fn first_run(
+    schedule: &Schedule,
+    last: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    catch_up: bool,
+    tz: Tz,
+) -> Option<DateTime<Utc>> {
+    let missed = last
+        .filter(|_| catch_up)
+        .and_then(|last| next_after(schedule, last, tz))
+        .is_some_and(|missed| missed <= now);
+    if missed {
+        Some(now)
+    } else {
+        next_after(schedule, now, tz)
+    }
+}
+
+/// Next firing strictly after `after`, with the schedule read as local time in `tz`
+///
+/// As in Vixie cron, a time skipped when clocks go forward fires at the end of
+/// the gap, and a repeated time when they go back fires once, unless the job
+/// runs every hour, in which case it fires in both copies of the hour.
+fn next_after(schedule: &Schedule, after: DateTime<Utc>, tz: Tz) -> Option<DateTime<Utc>> {
+    // Start an hour early so the second copy of a repeated hour isn't skipped
+    let mut local = after.with_timezone(&tz).naive_local() - chrono::Duration::hours(1);
+    loop {
+        local = schedule.next_after(local)?;
+        let candidates = match tz.from_local_datetime(&local) {
+            LocalResult::Single(time) => vec![time],
+            LocalResult::Ambiguous(first, second) if schedule.every_hour() => vec![first, second],
+            LocalResult::Ambiguous(first, _) => vec![first],
+            LocalResult::None => (1..=180)
+                .find_map(|minutes| tz.from_local_datetime(&(local + chrono::Duration::minutes(minutes))).earliest())
+                .into_iter()
+                .collect(),
+        };
+        if let Some(next) = candidates.into_iter().map(|time| time.to_utc()).find(|time| *time > after) {
+            return Some(next);
+        }
+    }
+}
+
+/// Last run times by job name; a missing or unreadable file means no catch-up
+///
+/// An unreadable file is quarantined so it is replaced on the next save.
+fn load_last_runs(path: &Path) -> BTreeMap<String, DateTime<Utc>> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return BTreeMap::new();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        match quarantine(path) {
+            Ok(moved) => warn!("Ignoring unreadable schedule state {} ({}), moved to {}", path.display(), e, moved.display()),
+            Err(_) => warn!("Ignoring unreadable schedule state {}: {}", path.display(), e),
+        }
+        BTreeMap::new()
+    })
+}
+
+fn save_last_runs(path: &Path, last_runs: &BTreeMap<String, DateTime<Utc>>) {
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| write_atomic(path, serde_json::to_string_pretty(last_runs).unwrap_or_default()));
+    if let Err(e) = result {
+        warn!("Failed to save schedule state to {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_first_run_catches_up_missed_runs() {
+        let hourly: Schedule = "0 * * * *".parse().unwrap();
+        let now = at("2025-12-14T10:20:00Z");
+
+        // Last ran at 07:00, so 08:00 was missed
+        let last = Some(at("2025-12-14T07:00:00Z"));
+        assert_eq!(first_run(&hourly, last, now, true, Tz::UTC), Some(now));
+        assert_eq!(first_run(&hourly, last, now, false, Tz::UTC), Some(at("2025-12-14T11:00:00Z")));
+
+        // Nothing missed, or never run before
+        let last = Some(at("2025-12-14T10:00:00Z"));
+        assert_eq!(first_run(&hourly, last, now, true, Tz::UTC), Some(at("2025-12-14T11:00:00Z")));
+        assert_eq!(first_run(&hourly, None, now, true, Tz::UTC), Some(at("2025-12-14T11:00:00Z")));
+    }
+
+    #[test]
+    fn test_schedules_follow_local_time_across_dst() {
+        let london: Tz = "Europe/London".parse().unwrap();
+        let daily: Schedule = "5 0 * * *".parse().unwrap();
+        let hourly: Schedule = "0 * * * *".parse().unwrap();
+        let at_130: Schedule = "30 1 * * *".parse().unwrap();
+
+        // 00:05 local is 23:05 UTC the day before in summer
+        assert_eq!(next_after(&daily, at("2025-07-01T12:00:00Z"), london), Some(at("2025-07-01T23:05:00Z")));
+
+        // Clocks go forward at 01:00 GMT: 01:30 doesn't exist, so it fires at 02:00 BST
+        assert_eq!(next_after(&at_130, at("2025-03-30T00:00:00Z"), london), Some(at("2025-03-30T01:00:00Z")));
+
+        // Clocks go back at 02:00 BST: hourly jobs run in both 01:00 hours, fixed times once
+        let mut runs = vec![at("2025-10-25T23:30:00Z")];
+        for _ in 0..3 {
+            runs.push(next_after(&hourly, *runs.last().unwrap(), london).unwrap());
+        }
+        assert_eq!(
+            runs[1..],
+            [at("2025-10-26T00:00:00Z"), at("2025-10-26T01:00:00Z"), at("2025-10-26T02:00:00Z")]
+        );
+        let first = next_after(&at_130, at("2025-10-25T12:00:00Z"), london).unwrap();
+        assert_eq!(first, at("2025-10-26T00:30:00Z"));
+        assert_eq!(next_after(&at_130, first, london), Some(at("2025-10-27T01:30:00Z")));
+    }
+}
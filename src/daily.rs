@@ -2,7 +2,9 @@
 //!
 //! "Oh wonderful, now I have to summarize summaries. How delightfully recursive."
 
-use chrono::Utc;
+use chrono::{DateTime, NaiveDate, Timelike, Utc};
+use chrono_tz::Tz;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -11,8 +13,17 @@
 use zip::write::SimpleFileOptions;
 use zip::ZipWriter;
 
-use crate::config::Config;
+use crate::config::{Config, DailyConfig, DailyStrategy};
 use crate::llm::OllamaClient;
+use crate::output::archive::{archive_dir, archive_path, list_archive_dates, read_archive};
+use crate::output::atomic::{commit, temp_path, write_atomic};
+use crate::output::naming::{parse_report_name, Trigger};
+use crate::output::report::{parse_severity, summary_line, Severity};
+use crate::output::strip_generation_stats;
+use crate::retention;
+
+/// Rough characters per token, for sizing prompts against `max_prompt_tokens`
+const CHARS_PER_TOKEN: usize = 4;
 
 #[derive(Error, Debug)]
 pub enum DailyError {
@@ -26,99 +37,332 @@ pub enum DailyError {
     LlmError(String),
 }
 
-/// Generate daily summary from previous day's hourly reports
-pub async fn generate_daily_summary(config: &Config) -> Result<(), DailyError> {
-    // Get yesterday's date
-    let yesterday = Utc::now() - chrono::Duration::days(1);
-    let date = yesterday.format("%Y-%m-%d").to_string();
+impl DailyError {
+    /// Process exit code, following `cli::Cli`'s table
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            DailyError::NoReports(_) => 1,
+            DailyError::LlmError(_) => 3,
+            DailyError::ReadError(_) | DailyError::ArchiveError(_) => 4,
+        }
+    }
+}
 
-    info!("Generating daily summary for {}", date);
+/// An hourly report that belongs to the day being summarised
+#[derive(Clone)]
+struct HourlyReport {
+    filename: String,
+    content: String,
+    /// Set while the report is still loose in the report directory
+    path: Option<PathBuf>,
+    /// Read from the archive of the day either side, where it stays
+    neighbour: bool,
+}
 
-    // Find all hourly reports from yesterday
-    let reports = find_hourly_reports(&config.general.report_dir, &date)?;
+/// The day before today in the configured timezone
+pub fn yesterday(config: &Config) -> NaiveDate {
+    Utc::now().with_timezone(&config.general.tz()).date_naive() - chrono::Duration::days(1)
+}
 
-    if reports.is_empty() {
-        return Err(DailyError::NoReports(date));
-    }
+/// Generate the daily summary for `date` from its hourly reports
+///
+/// Reports are taken from the report directory and from the day's archive,
+/// so a summary can be regenerated after archiving, and from the archives
+/// either side (written under another timezone). Loose hourly reports are
+/// merged into the day's archive and then removed; archived ones are left alone.
+pub async fn generate_daily_summary(config: &Config, date: NaiveDate) -> Result<PathBuf, DailyError> {
+    let tz = config.general.tz();
+    let report_dir = &config.general.report_dir;
+    let date = date.format("%Y-%m-%d").to_string();
 
-    info!("Found {} hourly reports for {}", reports.len(), date);
+    info!("Generating daily summary for {} ({})", date, tz);
 
-    // Read all report contents
-    let mut report_contents = Vec::new();
-    for report_path in &reports {
-        let content = fs::read_to_string(report_path)?;
-        report_contents.push(content);
+    let reports = gather_hourly_reports(report_dir, &date, tz)?;
+
+    if reports.is_empty() {
+        return Err(DailyError::NoReports(date));
     }
 
-    // Build daily summary prompt
-    let prompt = build_daily_prompt(&report_contents, &date);
+    let loose = reports.iter().filter(|r| r.path.is_some()).count();
+    info!(
+        "Found {} hourly reports for {} ({} already archived)",
+        reports.len(),
+        date,
+        reports.len() - loose
+    );
 
     // Generate summary using LLM
     let client = OllamaClient::new(
         &config.ollama.endpoint,
         &config.ollama.model,
         config.ollama.timeout_secs,
-    );
+    )
+    .with_num_ctx(config.num_ctx());
 
-    info!("Sending daily summary prompt to LLM ({} chars)", prompt.len());
-
-    let summary = client
-        .generate(&prompt)
-        .await
-        .map_err(|e| DailyError::LlmError(e.to_string()))?;
+    let summary = summarise_day(&client, &config.daily, &reports, &date, tz).await?;
 
     // Write daily summary
-    let summary_path = write_daily_summary(&config.general.report_dir, &date, &summary)?;
+    let summary_path = write_daily_summary(report_dir, &date, &summary)?;
     info!("Daily summary written to: {}", summary_path.display());
 
-    // Archive hourly reports
-    let archive_path = archive_hourly_reports(&config.general.report_dir, &date, &reports)?;
-    info!("Hourly reports archived to: {}", archive_path.display());
+    if loose > 0 {
+        // Archive hourly reports
+        let own: Vec<HourlyReport> = reports.iter().filter(|r| !r.neighbour).cloned().collect();
+        let archive_path = archive_hourly_reports(report_dir, &date, &own)?;
+        info!("Hourly reports archived to: {}", archive_path.display());
 
-    // Delete hourly reports after successful archiving
-    for report_path in &reports {
-        if let Err(e) = fs::remove_file(report_path) {
-            warn!("Failed to delete {}: {}", report_path.display(), e);
+        // Delete hourly reports after successful archiving
+        for path in reports.iter().filter_map(|r| r.path.as_ref()) {
+            if let Err(e) = fs::remove_file(path) {
+                warn!("Failed to delete {}: {}", path.display(), e);
+            }
         }
     }
 
     info!("Daily summary complete for {}", date);
 
-    Ok(())
+    Ok(summary_path)
 }
 
-/// Find all hourly reports for a given date
-fn find_hourly_reports(report_dir: &Path, date: &str) -> Result<Vec<PathBuf>, DailyError> {
-    let mut reports = Vec::new();
+/// Ask the LLM for the day's summary, following `[daily] strategy`
+///
+/// `map-reduce` condenses each block of hours into notes first, then
+/// summarises the notes, so late-day events aren't lost to a truncated prompt.
+async fn summarise_day(
+    client: &OllamaClient,
+    daily: &DailyConfig,
+    reports: &[HourlyReport],
+    date: &str,
+    tz: Tz,
+) -> Result<String, DailyError> {
+    let fits = |prompt: &String| estimate_tokens(prompt) <= daily.max_prompt_tokens;
+    let hourly_section = format!("HOURLY REPORTS FOR {}", date);
+    let single = |condensed| build_daily_prompt(date, &hourly_section, &render_hourly(reports, tz, condensed));
+
+    let prompt = match daily.strategy {
+        DailyStrategy::Full => Some(single(false)),
+        DailyStrategy::Condensed => Some(single(true)),
+        DailyStrategy::MapReduce => None,
+        DailyStrategy::Auto => Some(single(false))
+            .filter(fits)
+            .or_else(|| Some(single(true)).filter(fits)),
+    };
+
+    if let Some(prompt) = prompt {
+        if !fits(&prompt) {
+            warn!(
+                "Daily summary prompt is ~{} tokens, over max_prompt_tokens ({}); the model may truncate it",
+                estimate_tokens(&prompt),
+                daily.max_prompt_tokens
+            );
+        }
+        info!("Sending daily summary prompt to LLM ({} chars)", prompt.len());
+        return generate(client, &prompt).await;
+    }
 
-    let entries = fs::read_dir(report_dir)?;
+    let blocks = hour_blocks(reports, tz, daily.block_hours);
+    info!("Summarising {} blocks of hourly reports before the daily summary", blocks.len());
+
+    let mut notes = String::new();
+    for block in blocks {
+        let span = format!("{} to {}", local_time(&block[0], tz), local_time(&block[block.len() - 1], tz));
+        let full = render_hourly(block, tz, false);
+        let body = if estimate_tokens(&full) <= daily.max_prompt_tokens {
+            full
+        } else {
+            render_hourly(block, tz, true)
+        };
+        let note = generate(client, &build_block_prompt(date, &span, &body)).await?;
+        notes.push_str(&format!("--- {} ---\n{}\n\n", span, note.trim()));
+    }
 
-    for entry in entries.flatten() {
-        let path = entry.path();
+    let prompt = build_daily_prompt(date, &format!("NOTES ON BLOCKS OF HOURS FOR {}", date), &notes);
+    info!("Sending daily summary prompt to LLM ({} chars)", prompt.len());
+    generate(client, &prompt).await
+}
 
-        if !path.is_file() {
-            continue;
+async fn generate(client: &OllamaClient, prompt: &str) -> Result<String, DailyError> {
+    client
+        .generate(prompt)
+        .await
+        .map_err(|e| DailyError::LlmError(e.to_string()))
+}
+
+/// Rough token count of a prompt
+fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Consecutive reports grouped by local hour into blocks of `block_hours`
+fn hour_blocks(reports: &[HourlyReport], tz: Tz, block_hours: usize) -> Vec<&[HourlyReport]> {
+    let block_hours = block_hours.clamp(1, 24) as u32;
+    let block = |report: &HourlyReport| {
+        report_hour(&report.filename).map(|hour| hour.with_timezone(&tz).hour() / block_hours)
+    };
+    reports.chunk_by(|a, b| block(a) == block(b)).collect()
+}
+
+/// Summarise every past day that has hourly reports but no daily summary
+///
+/// Returns the days summarised. Stops at the first LLM failure, since the
+/// remaining days would fail the same way.
+pub async fn backfill(config: &Config) -> Result<Vec<NaiveDate>, DailyError> {
+    let tz = config.general.tz();
+    let today = Utc::now().with_timezone(&tz).date_naive();
+    let oldest = retention::daily_cutoff(&config.retention, today);
+    let missing = missing_summaries(&config.general.report_dir, tz, today, oldest)?;
+
+    if missing.is_empty() {
+        info!("No days missing a daily summary");
+    }
+
+    let mut done = Vec::new();
+    for date in missing {
+        info!("Backfilling daily summary for {}", date);
+        match generate_daily_summary(config, date).await {
+            Ok(_) => done.push(date),
+            Err(DailyError::NoReports(_)) => {}
+            Err(e) => return Err(e),
         }
+    }
 
-        if let Some(filename) = path.file_name().and_then(|s| s.to_str()) {
-            // Match pattern: YYYY-MM-DD-HH.md
-            if filename.starts_with(date) && filename.ends_with(".md") && filename.len() == 16 {
-                // Verify it's an hourly report (has -HH before .md)
-                if filename.chars().nth(10) == Some('-') && filename.chars().nth(13) == Some('.') {
-                    reports.push(path);
-                }
+    Ok(done)
+}
+
+/// Days from `oldest` to before `today` with hourly reports (loose or
+/// archived) but no `-DAILY.md`
+///
+/// `oldest` is the retention cutoff for daily summaries; a summary older than
+/// that was pruned on purpose and shouldn't be written again.
+fn missing_summaries(
+    report_dir: &Path,
+    tz: Tz,
+    today: NaiveDate,
+    oldest: Option<NaiveDate>,
+) -> Result<Vec<NaiveDate>, DailyError> {
+    let mut days = BTreeSet::new();
+
+    for entry in fs::read_dir(report_dir)?.flatten() {
+        if let Some(date) = entry.file_name().to_str().and_then(|name| local_date(name, tz)) {
+            days.insert(date);
+        }
+    }
+    for date in list_archive_dates(report_dir).map_err(|e| DailyError::ArchiveError(e.to_string()))? {
+        if let Ok(date) = NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+            days.insert(date);
+        }
+    }
+
+    Ok(days
+        .into_iter()
+        .filter(|date| *date < today && oldest.is_none_or(|oldest| *date >= oldest))
+        .filter(|date| !report_dir.join(format!("{}-DAILY.md", date.format("%Y-%m-%d"))).exists())
+        .collect())
+}
+
+/// Hourly reports for a local date, archived and loose, in chronological order
+///
+/// Everything in the day's own archive belongs to it. Archives for the day
+/// either side can hold some of its reports too, if they were archived under
+/// another timezone, so those are read and filtered by local date. A loose
+/// report replaces an archived one with the same name.
+fn gather_hourly_reports(report_dir: &Path, date: &str, tz: Tz) -> Result<Vec<HourlyReport>, DailyError> {
+    let archive_error = |e: crate::output::archive::ArchiveError| DailyError::ArchiveError(e.to_string());
+    let in_day = |filename: &str| local_date(filename, tz).is_some_and(|d| d.format("%Y-%m-%d").to_string() == date);
+    let mut reports = BTreeMap::new();
+
+    let day = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| DailyError::ArchiveError(format!("Invalid date: {}", date)))?;
+    let neighbours = [day.pred_opt(), day.succ_opt()].into_iter().flatten().map(|d| (d.format("%Y-%m-%d").to_string(), true));
+    for (archive_date, neighbour) in std::iter::once((date.to_string(), false)).chain(neighbours) {
+        let archive = archive_path(report_dir, &archive_date).map_err(archive_error)?;
+        if !archive.exists() {
+            continue;
+        }
+        for (archived, content) in read_archive(&archive).map_err(archive_error)? {
+            if neighbour && (!in_day(&archived.filename) || reports.contains_key(&archived.filename)) {
+                continue;
             }
+            reports.insert(
+                archived.filename.clone(),
+                HourlyReport {
+                    filename: archived.filename,
+                    content,
+                    path: None,
+                    neighbour,
+                },
+            );
         }
     }
 
-    // Sort by filename (chronological order)
-    reports.sort();
+    for entry in fs::read_dir(report_dir)?.flatten() {
+        let path = entry.path();
+        let Some(filename) = path.file_name().and_then(|s| s.to_str()).map(str::to_string) else {
+            continue;
+        };
+        if !path.is_file() || !in_day(&filename) {
+            continue;
+        }
+        reports.insert(
+            filename.clone(),
+            HourlyReport {
+                content: fs::read_to_string(&path)?,
+                filename,
+                path: Some(path),
+                neighbour: false,
+            },
+        );
+    }
 
+    let mut reports: Vec<_> = reports.into_values().collect();
+    reports.sort_by_key(|report| parse_report_name(&report.filename).map(|name| name.timestamp));
     Ok(reports)
 }
 
-/// Build prompt for daily summary
-fn build_daily_prompt(hourly_reports: &[String], date: &str) -> String {
+/// UTC time of an hourly report, `None` for anything else
+fn report_hour(filename: &str) -> Option<DateTime<Utc>> {
+    parse_report_name(filename).map(|name| name.timestamp)
+}
+
+/// The local day an hourly report belongs to
+fn local_date(filename: &str, tz: Tz) -> Option<NaiveDate> {
+    report_hour(filename).map(|hour| hour.with_timezone(&tz).date_naive())
+}
+
+/// Local clock time of a report, so DST days read naturally (23 or 25 hours),
+/// noting runs that weren't scheduled
+fn local_time(report: &HourlyReport, tz: Tz) -> String {
+    match parse_report_name(&report.filename) {
+        Some(name) => {
+            let time = name.timestamp.with_timezone(&tz).format("%H:%M %Z").to_string();
+            match name.trigger {
+                Some(trigger) if trigger != Trigger::Scheduled => format!("{} ({} run)", time, trigger.name()),
+                _ => time,
+            }
+        }
+        None => report.filename.clone(),
+    }
+}
+
+/// Hourly reports for a prompt; `condensed` cuts OK hours to their summary line
+fn render_hourly(reports: &[HourlyReport], tz: Tz, condensed: bool) -> String {
+    let mut body = String::new();
+
+    for report in reports {
+        let content = strip_generation_stats(&report.content);
+        let content = match summary_line(content) {
+            Some(line) if condensed && parse_severity(content) == Severity::Ok => line,
+            _ => content,
+        };
+        body.push_str(&format!("--- {} ---\n", local_time(report, tz)));
+        body.push_str(content);
+        body.push_str("\n\n");
+    }
+
+    body
+}
+
+/// Build prompt for daily summary, with `body` under a `=== section ===` heading
+fn build_daily_prompt(date: &str, section: &str, body: &str) -> String {
     let mut prompt = String::new();
 
     prompt.push_str(&format!(
@@ -147,13 +391,29 @@ fn build_daily_prompt(hourly_reports: &[String], date: &str) -> String {
     prompt.push_str("## Trends\n");
     prompt.push_str("[Any patterns observed across the day]\n\n");
 
-    prompt.push_str(&format!("=== HOURLY REPORTS FOR {} ===\n\n", date));
+    prompt.push_str(&format!("=== {} ===\n\n", section));
+    prompt.push_str(body);
 
-    for (i, report) in hourly_reports.iter().enumerate() {
-        prompt.push_str(&format!("--- Hour {} ---\n", i));
-        prompt.push_str(report);
-        prompt.push_str("\n\n");
-    }
+    prompt
+}
+
+/// Build prompt condensing one block of hours into notes for the daily summary
+fn build_block_prompt(date: &str, span: &str, body: &str) -> String {
+    let mut prompt = String::new();
+
+    prompt.push_str(&format!(
+        "You are Marvin, condensing the hourly monitoring reports for {}, {}, into notes for the daily summary.\n\n",
+        date, span
+    ));
+
+    prompt.push_str("TASK: Start with the worst severity seen as [SEVERITY]: [one sentence], then list:\n");
+    prompt.push_str("- Every WATCH, CONCERN or CRITICAL finding, with the time it was seen\n");
+    prompt.push_str("- Sensor, drive or service readings that changed\n\n");
+
+    prompt.push_str("Skip hours where nothing happened. Be terse - another summary reads these notes, not a human.\n\n");
+
+    prompt.push_str(&format!("=== HOURLY REPORTS {} ===\n\n", span));
+    prompt.push_str(body);
 
     prompt
 }
@@ -167,45 +427,244 @@ fn write_daily_summary(
     let filename = format!("{}-DAILY.md", date);
     let path = report_dir.join(filename);
 
-    let mut file = File::create(&path)?;
-    file.write_all(summary.as_bytes())?;
+    write_atomic(&path, summary)?;
 
     Ok(path)
 }
 
-/// Archive hourly reports to a zip file
+/// Write every report for the day (already archived ones included) to the
+/// day's zip, replacing the old archive only once the new one is complete,
+/// synced to disk and read back intact
 fn archive_hourly_reports(
     report_dir: &Path,
     date: &str,
-    reports: &[PathBuf],
+    reports: &[HourlyReport],
 ) -> Result<PathBuf, DailyError> {
-    let archive_dir = report_dir.join("archive");
+    let archive_dir = archive_dir(report_dir);
     fs::create_dir_all(&archive_dir)?;
 
     let archive_filename = format!("{}.zip", date);
     let archive_path = archive_dir.join(archive_filename);
+    let partial_path = temp_path(&archive_path);
+
+    let result = write_zip(&partial_path, reports).and_then(|_| verify_zip(&partial_path, reports));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial_path);
+        return Err(e);
+    }
+
+    commit(&partial_path, &archive_path)?;
 
-    let file = File::create(&archive_path)
+    Ok(archive_path)
+}
+
+fn write_zip(path: &Path, reports: &[HourlyReport]) -> Result<(), DailyError> {
+    let file = File::create(path)
         .map_err(|e| DailyError::ArchiveError(e.to_string()))?;
 
     let mut zip = ZipWriter::new(file);
     let options = SimpleFileOptions::default()
         .compression_method(zip::CompressionMethod::Deflated);
 
-    for report_path in reports {
-        if let Some(filename) = report_path.file_name().and_then(|s| s.to_str()) {
-            let content = fs::read(report_path)?;
+    for report in reports {
+        zip.start_file(report.filename.as_str(), options)
+            .map_err(|e| DailyError::ArchiveError(e.to_string()))?;
+
+        zip.write_all(report.content.as_bytes())
+            .map_err(|e| DailyError::ArchiveError(e.to_string()))?;
+    }
+
+    let file = zip.finish()
+        .map_err(|e| DailyError::ArchiveError(e.to_string()))?;
+    file.sync_all()?;
+
+    Ok(())
+}
 
-            zip.start_file(filename, options)
-                .map_err(|e| DailyError::ArchiveError(e.to_string()))?;
+/// Read the archive back and check it holds exactly `reports`, so the loose
+/// copies are only deleted once they are safely archived
+fn verify_zip(path: &Path, reports: &[HourlyReport]) -> Result<(), DailyError> {
+    let archive_error = |e: crate::output::archive::ArchiveError| DailyError::ArchiveError(e.to_string());
+
+    let archived: BTreeMap<String, String> = read_archive(path)
+        .map_err(archive_error)?
+        .into_iter()
+        .map(|(report, content)| (report.filename, content))
+        .collect();
+    if archived.len() != reports.len() {
+        return Err(DailyError::ArchiveError(format!(
+            "{} holds {} reports, expected {}",
+            path.display(),
+            archived.len(),
+            reports.len()
+        )));
+    }
 
-            zip.write_all(&content)
-                .map_err(|e| DailyError::ArchiveError(e.to_string()))?;
+    for report in reports {
+        if archived.get(&report.filename) != Some(&report.content) {
+            return Err(DailyError::ArchiveError(format!(
+                "{} in {} does not match the original",
+                report.filename,
+                path.display()
+            )));
         }
     }
 
-    zip.finish()
-        .map_err(|e| DailyError::ArchiveError(e.to_string()))?;
+    Ok(())
+}
 
-    Ok(archive_path)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::naming::{report_filename, DEFAULT_TEMPLATE};
+
+    #[test]
+    fn test_reports_grouped_by_local_day() {
+        let dir = tempfile::tempdir().unwrap();
+        let reports = dir.path();
+        // Berlin is UTC+1 in December
+        for name in [
+            "2025-12-13-22.md",
+            "2025-12-13-23.md",
+            "2025-12-14-10.md",
+            "2025-12-14-100500-api.md",
+            "2025-12-14-094500-scheduled.md",
+            "2025-12-14-23.md",
+        ] {
+            fs::write(reports.join(name), format!("## Summary\nOK: {}\n", name)).unwrap();
+        }
+        fs::write(reports.join("2025-12-12-DAILY.md"), "done").unwrap();
+
+        let archived = HourlyReport {
+            filename: "2025-12-12-09.md".to_string(),
+            content: "## Summary\nOK: archived\n".to_string(),
+            path: None,
+            neighbour: false,
+        };
+        archive_hourly_reports(reports, "2025-12-12", &[archived]).unwrap();
+        archive_hourly_reports(reports, "2025-12-11", &[]).unwrap();
+
+        // Archived by UTC date before the timezone was set: 23:50 UTC is
+        // 00:50 in Berlin the next day, 12:00 UTC stays on the 13th
+        let utc_archived = ["2025-12-13-235000-scheduled.md", "2025-12-13-12.md"].map(|name| HourlyReport {
+            filename: name.to_string(),
+            content: format!("## Summary\nOK: {}\n", name),
+            path: None,
+            neighbour: false,
+        });
+        archive_hourly_reports(reports, "2025-12-13", &utc_archived).unwrap();
+
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+        let day = gather_hourly_reports(reports, "2025-12-14", berlin).unwrap();
+        let names: Vec<_> = day.iter().map(|r| r.filename.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "2025-12-13-23.md",
+                "2025-12-13-235000-scheduled.md",
+                "2025-12-14-094500-scheduled.md",
+                "2025-12-14-10.md",
+                "2025-12-14-100500-api.md"
+            ]
+        );
+        assert!(day[1].neighbour && !day[0].neighbour);
+        let rendered = render_hourly(&day, berlin, false);
+        assert!(rendered.contains("--- 00:00 CET ---"));
+        assert!(rendered.contains("--- 10:45 CET ---"));
+        assert!(rendered.contains("--- 11:05 CET (api run) ---"));
+
+        let from_archive = gather_hourly_reports(reports, "2025-12-12", berlin).unwrap();
+        assert_eq!(from_archive.len(), 1);
+        assert!(from_archive[0].path.is_none());
+
+        let today = NaiveDate::from_ymd_opt(2025, 12, 15).unwrap();
+        let missing = missing_summaries(reports, berlin, today, None).unwrap();
+        let missing: Vec<_> = missing.iter().map(|d| d.to_string()).collect();
+        assert_eq!(missing, vec!["2025-12-11", "2025-12-13", "2025-12-14"]);
+    }
+
+    #[test]
+    fn test_backfill_leaves_pruned_summaries_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let reports = dir.path();
+        fs::create_dir_all(archive_dir(reports)).unwrap();
+        let today = Utc::now().date_naive();
+        let day = |days_ago: i64| (today - chrono::Duration::days(days_ago)).format("%Y-%m-%d").to_string();
+        for (name, size) in [
+            // Past daily_months but within archive_months
+            (format!("archive/{}.zip", day(120)), 10),
+            (format!("{}-DAILY.md", day(120)), 10),
+            (format!("archive/{}.zip", day(30)), 1536 * 1024),
+            (format!("{}-DAILY.md", day(30)), 2048 * 1024),
+            (format!("archive/{}.zip", day(10)), 10),
+            (format!("{}-DAILY.md", day(10)), 10),
+        ] {
+            fs::write(reports.join(name), vec![b'x'; size]).unwrap();
+        }
+
+        let mut config = Config::default();
+        config.general.report_dir = reports.to_path_buf();
+        config.retention = crate::config::RetentionConfig {
+            hourly_days: 7,
+            daily_months: 3,
+            archive_months: 6,
+            max_total_mb: 0,
+            history_days: 0,
+        };
+        let oldest = retention::daily_cutoff(&config.retention, today);
+
+        retention::prune(&config, false).unwrap();
+        assert!(reports.join(format!("archive/{}.zip", day(120))).exists());
+        assert!(!reports.join(format!("{}-DAILY.md", day(120))).exists());
+        assert!(missing_summaries(reports, Tz::UTC, today, oldest).unwrap().is_empty());
+
+        // Over the size cap, a day's summary only goes once its archive has
+        config.retention.max_total_mb = 2;
+        retention::prune(&config, false).unwrap();
+        assert!(!reports.join(format!("archive/{}.zip", day(30))).exists());
+        assert!(missing_summaries(reports, Tz::UTC, today, oldest).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dst_day_keeps_all_its_hours() {
+        // Clocks go back on 2025-10-26 in Berlin, so the day has 25 hours
+        let dir = tempfile::tempdir().unwrap();
+        let reports = dir.path();
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+        let start = DateTime::parse_from_rfc3339("2025-10-25T21:00:00Z").unwrap().to_utc();
+        for hour in 0..27 {
+            let at = (start + chrono::Duration::hours(hour)).with_timezone(&berlin);
+            let name = report_filename(DEFAULT_TEMPLATE, at, Trigger::Scheduled);
+            fs::write(reports.join(name), "## Summary\nOK: Tick.\n").unwrap();
+        }
+
+        let day = gather_hourly_reports(reports, "2025-10-26", berlin).unwrap();
+        assert_eq!(day.len(), 25);
+        let times: Vec<_> = day.iter().map(|r| local_time(r, berlin)).collect();
+        assert_eq!(times[..4], ["00:00 CEST", "01:00 CEST", "02:00 CEST", "02:00 CET"]);
+        assert_eq!(times[24], "23:00 CET");
+    }
+
+    #[test]
+    fn test_condensed_reports_and_hour_blocks() {
+        let report = |hour: u32, severity: &str| HourlyReport {
+            filename: format!("2025-12-14-{:02}.md", hour),
+            content: format!("# Report\n\n## Summary\n{}: Hour {}.\n\n## Details\nLots of words.\n", severity, hour),
+            path: None,
+            neighbour: false,
+        };
+        let reports: Vec<_> = (0..24)
+            .map(|hour| report(hour, if hour == 20 { "CONCERN" } else { "OK" }))
+            .collect();
+        let utc: Tz = "UTC".parse().unwrap();
+
+        let condensed = render_hourly(&reports, utc, true);
+        assert!(condensed.contains("--- 03:00 UTC ---\nOK: Hour 3.\n\n"));
+        assert!(condensed.contains("CONCERN: Hour 20.\n\n## Details\nLots of words."));
+        assert_eq!(condensed.matches("## Details").count(), 1);
+
+        let blocks = hour_blocks(&reports, utc, 6);
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[3][0].filename, "2025-12-14-18.md");
+    }
 }
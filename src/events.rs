@@ -0,0 +1,172 @@
+//! Collection lifecycle events
+//!
+//! "I think you ought to know I'm feeling very depressed."
+
+use serde::Serialize;
+use std::future::Future;
+use tokio::sync::broadcast;
+
+use crate::output::report::Severity;
+
+/// How many events a slow subscriber may fall behind before it starts
+/// missing them
+pub const EVENT_CAPACITY: usize = 1024;
+
+/// A step in a collection run, broadcast to `/api/events` subscribers
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum CollectionEvent {
+    CollectionStarted,
+    CollectorStarted {
+        collector: String,
+    },
+    CollectorFinished {
+        collector: String,
+        /// Readings, log entries, drives etc. returned
+        items: usize,
+        /// Sub-collectors (drives, custom checks, endpoints) that failed
+        failures: usize,
+    },
+    CollectorFailed {
+        collector: String,
+        error: String,
+        timed_out: bool,
+    },
+    PromptBuilt {
+        chars: usize,
+    },
+    LlmRequestSent {
+        model: String,
+    },
+    Token {
+        text: String,
+    },
+    ReportWritten {
+        filename: String,
+        severity: Severity,
+    },
+    CollectionFailed {
+        error: String,
+    },
+}
+
+impl CollectionEvent {
+    /// SSE event name
+    pub fn name(&self) -> &'static str {
+        match self {
+            CollectionEvent::CollectionStarted => "collection_started",
+            CollectionEvent::CollectorStarted { .. } => "collector_started",
+            CollectionEvent::CollectorFinished { .. } => "collector_finished",
+            CollectionEvent::CollectorFailed { .. } => "collector_failed",
+            CollectionEvent::PromptBuilt { .. } => "prompt_built",
+            CollectionEvent::LlmRequestSent { .. } => "llm_request_sent",
+            CollectionEvent::Token { .. } => "token",
+            CollectionEvent::ReportWritten { .. } => "report_written",
+            CollectionEvent::CollectionFailed { .. } => "collection_failed",
+        }
+    }
+}
+
+/// How a collector finished, for its `CollectorFinished`/`CollectorFailed` event
+pub enum CollectorOutcome {
+    Finished { items: usize, failures: usize },
+    Failed { error: String, timed_out: bool },
+    /// Collector disabled in config - no events are sent
+    Skipped,
+}
+
+/// Sink for collection events
+///
+/// CLI runs use `Events::none()`; the web server hands in its broadcast
+/// sender. Sending never blocks and is a no-op without subscribers.
+#[derive(Clone, Default)]
+pub struct Events(Option<broadcast::Sender<CollectionEvent>>);
+
+impl Events {
+    pub fn none() -> Self {
+        Self(None)
+    }
+
+    pub fn emit(&self, event: CollectionEvent) {
+        if let Some(sender) = &self.0 {
+            // Err only means nobody is listening
+            let _ = sender.send(event);
+        }
+    }
+
+    /// Run a collector future, emitting started/finished events around it
+    pub async fn observe<T, F>(
+        &self,
+        collector: &str,
+        future: F,
+        outcome: impl FnOnce(&T) -> CollectorOutcome,
+    ) -> T
+    where
+        F: Future<Output = T>,
+    {
+        if self.0.is_none() {
+            return future.await;
+        }
+
+        self.emit(CollectionEvent::CollectorStarted {
+            collector: collector.to_string(),
+        });
+        let result = future.await;
+
+        let collector = collector.to_string();
+        match outcome(&result) {
+            CollectorOutcome::Finished { items, failures } => {
+                self.emit(CollectionEvent::CollectorFinished {
+                    collector,
+                    items,
+                    failures,
+                })
+            }
+            CollectorOutcome::Failed { error, timed_out } => {
+                self.emit(CollectionEvent::CollectorFailed {
+                    collector,
+                    error,
+                    timed_out,
+                })
+            }
+            CollectorOutcome::Skipped => {}
+        }
+
+        result
+    }
+}
+
+impl From<broadcast::Sender<CollectionEvent>> for Events {
+    fn from(sender: broadcast::Sender<CollectionEvent>) -> Self {
+        Self(Some(sender))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_observe_emits_started_and_finished() {
+        let (sender, mut receiver) = broadcast::channel(16);
+        let events = Events::from(sender);
+
+        let value = events
+            .observe("sensors", async { vec![1, 2, 3] }, |v: &Vec<i32>| {
+                CollectorOutcome::Finished {
+                    items: v.len(),
+                    failures: 0,
+                }
+            })
+            .await;
+        assert_eq!(value.len(), 3);
+
+        let started = receiver.recv().await.unwrap();
+        assert_eq!(started.name(), "collector_started");
+
+        let finished = serde_json::to_value(receiver.recv().await.unwrap()).unwrap();
+        assert_eq!(finished["event"], "collector_finished");
+        assert_eq!(finished["collector"], "sensors");
+        assert_eq!(finished["items"], 3);
+    }
+}
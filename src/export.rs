@@ -0,0 +1,221 @@
+//! Export a date range of reports as one HTML or PDF document
+//!
+//! "Incredible... it's even worse than I thought it would be."
+
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use thiserror::Error;
+use tracing::info;
+
+use crate::output::archive::{archive_path, read_archive, ArchiveError};
+use crate::output::naming::parse_report_name;
+use crate::output::html::{render_bundle, HtmlReport};
+
+/// PDF renderers, tried in order
+const CHROME_BINARIES: &[&str] = &["chromium", "chromium-browser", "google-chrome"];
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("Failed to read reports: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to read archive: {0}")]
+    Archive(#[from] ArchiveError),
+    #[error("No reports between {0} and {1}")]
+    NoReports(NaiveDate, NaiveDate),
+    #[error("Unsupported export format for {0} (use .html or .pdf)")]
+    UnsupportedFormat(PathBuf),
+    #[error("PDF export needs wkhtmltopdf or chromium on the PATH")]
+    NoPdfRenderer,
+    #[error("PDF rendering failed: {0}")]
+    PdfFailed(String),
+}
+
+impl ExportError {
+    /// Process exit code, following `cli::Cli`'s table
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ExportError::NoReports(..) | ExportError::UnsupportedFormat(_) | ExportError::NoPdfRenderer => 1,
+            ExportError::Io(_) | ExportError::Archive(_) | ExportError::PdfFailed(_) => 4,
+        }
+    }
+}
+
+/// Export reports dated `from..=to` (local days in `tz`), including hourly
+/// reports already moved into daily archives; the format follows `output`'s
+/// extension. Returns the number of reports exported.
+pub fn export_reports(
+    report_dir: &Path,
+    from: NaiveDate,
+    to: NaiveDate,
+    output: &Path,
+    tz: Tz,
+) -> Result<usize, ExportError> {
+    let pdf = match output.extension().and_then(|e| e.to_str()) {
+        Some("html") | Some("htm") => false,
+        Some("pdf") => true,
+        _ => return Err(ExportError::UnsupportedFormat(output.to_path_buf())),
+    };
+
+    let reports = collect_reports(report_dir, from, to, tz)?;
+    if reports.is_empty() {
+        return Err(ExportError::NoReports(from, to));
+    }
+
+    let title = if from == to {
+        format!("Marvinous Reports: {}", from)
+    } else {
+        format!("Marvinous Reports: {} to {}", from, to)
+    };
+    let html_reports: Vec<HtmlReport> = reports
+        .iter()
+        .map(|(filename, content)| HtmlReport { filename, content })
+        .collect();
+    let html = render_bundle(&title, &html_reports);
+
+    if pdf {
+        html_to_pdf(&html, output)?;
+    } else {
+        fs::write(output, html)?;
+    }
+
+    info!("Exported {} reports to {}", reports.len(), output.display());
+    Ok(reports.len())
+}
+
+/// (filename, content) for every report in range, in filename order
+fn collect_reports(
+    report_dir: &Path,
+    from: NaiveDate,
+    to: NaiveDate,
+    tz: Tz,
+) -> Result<Vec<(String, String)>, ExportError> {
+    let in_range = |filename: &str| report_date(filename, tz).is_some_and(|d| d >= from && d <= to);
+    let mut reports = Vec::new();
+
+    for entry in fs::read_dir(report_dir)?.flatten() {
+        let path = entry.path();
+        let Some(filename) = path.file_name().and_then(|s| s.to_str()).map(str::to_string) else {
+            continue;
+        };
+        if path.is_file() && filename.ends_with(".md") && in_range(&filename) {
+            reports.push((filename, fs::read_to_string(&path)?));
+        }
+    }
+
+    // Archives for the days either side can hold reports for the first and
+    // last day too, if they were archived under another timezone
+    let first = from.pred_opt().unwrap_or(from);
+    let last = to.succ_opt().unwrap_or(to);
+    for date in first.iter_days().take_while(|d| *d <= last) {
+        let archive = archive_path(report_dir, &date.format("%Y-%m-%d").to_string())?;
+        if !archive.exists() {
+            continue;
+        }
+        for (archived, content) in read_archive(&archive)? {
+            if !in_range(&archived.filename) || reports.iter().any(|(name, _)| *name == archived.filename) {
+                continue;
+            }
+            reports.push((archived.filename, content));
+        }
+    }
+
+    // A day's hourly reports in time order, then its daily summary
+    reports.sort_by_cached_key(|(filename, _)| {
+        let hourly = parse_report_name(filename);
+        (report_date(filename, tz), hourly.is_none(), hourly.map(|name| name.timestamp), filename.clone())
+    });
+    Ok(reports)
+}
+
+/// Local day of an hourly report, or the date a summary is named for
+fn report_date(filename: &str, tz: Tz) -> Option<NaiveDate> {
+    match parse_report_name(filename) {
+        Some(name) => Some(name.timestamp.with_timezone(&tz).date_naive()),
+        None => NaiveDate::parse_from_str(filename.get(..10)?, "%Y-%m-%d").ok(),
+    }
+}
+
+fn html_to_pdf(html: &str, output: &Path) -> Result<(), ExportError> {
+    // Unpredictable name, created exclusively, removed when dropped
+    let mut source = tempfile::Builder::new().prefix("marvinous-export-").suffix(".html").tempfile()?;
+    source.write_all(html.as_bytes())?;
+    source.flush()?;
+    let source = source.path();
+
+    let result = if let Ok(wkhtmltopdf) = which::which("wkhtmltopdf") {
+        Command::new(wkhtmltopdf)
+            .args(["--quiet", "--encoding", "utf-8"])
+            .arg(source)
+            .arg(output)
+            .output()
+    } else if let Some(chrome) = CHROME_BINARIES.iter().find_map(|name| which::which(name).ok()) {
+        Command::new(chrome)
+            .args(["--headless", "--disable-gpu", "--no-pdf-header-footer"])
+            .arg(format!("--print-to-pdf={}", output.display()))
+            .arg(format!("file://{}", source.display()))
+            .output()
+    } else {
+        return Err(ExportError::NoPdfRenderer);
+    };
+
+    let result = result?;
+    if !result.status.success() {
+        return Err(ExportError::PdfFailed(String::from_utf8_lossy(&result.stderr).trim().to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    #[test]
+    fn test_export_html_includes_archived_reports() {
+        let dir = tempfile::tempdir().unwrap();
+        let reports = dir.path();
+        fs::write(reports.join("2025-12-14-DAILY.md"), "## Summary\nWATCH: Daily.\n").unwrap();
+        fs::write(reports.join("2025-12-15-09.md"), "## Summary\nOK: Current.\n").unwrap();
+        fs::write(reports.join("2025-12-16-09.md"), "## Summary\nOK: Out of range.\n").unwrap();
+
+        // Archived under another timezone, so each archive holds reports
+        // for the day either side of its own
+        for (date, entries) in [
+            ("2025-12-13", [("2025-12-14-00.md", "OK: Early."), ("2025-12-13-12.md", "OK: Out of range.")]),
+            ("2025-12-14", [("2025-12-14-10.md", "CRITICAL: Archived."), ("2025-12-13-23.md", "OK: Out of range.")]),
+            ("2025-12-16", [("2025-12-15-23.md", "OK: Late."), ("2025-12-16-01.md", "OK: Out of range.")]),
+        ] {
+            let archive = archive_path(reports, date).unwrap();
+            fs::create_dir_all(archive.parent().unwrap()).unwrap();
+            let mut zip = zip::ZipWriter::new(fs::File::create(&archive).unwrap());
+            for (name, summary) in entries {
+                zip.start_file(name, SimpleFileOptions::default()).unwrap();
+                zip.write_all(format!("## Summary\n{}\n", summary).as_bytes()).unwrap();
+            }
+            zip.finish().unwrap();
+        }
+
+        let from = NaiveDate::from_ymd_opt(2025, 12, 14).unwrap();
+        let to = NaiveDate::from_ymd_opt(2025, 12, 15).unwrap();
+        let output = reports.join("export.html");
+        assert_eq!(export_reports(reports, from, to, &output, Tz::UTC).unwrap(), 5);
+
+        let html = fs::read_to_string(&output).unwrap();
+        let order: Vec<_> = ["Early.", "Archived.", "Daily.", "Current.", "Late."]
+            .iter()
+            .map(|text| html.find(text).unwrap())
+            .collect();
+        assert!(order.is_sorted());
+        assert!(!html.contains("Out of range"));
+
+        assert!(matches!(
+            export_reports(reports, from, to, &reports.join("export.docx"), Tz::UTC),
+            Err(ExportError::UnsupportedFormat(_))
+        ));
+    }
+}
@@ -8,6 +8,7 @@
 use thiserror::Error;
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum OllamaError {
     #[error("HTTP request failed: {0}")]
     RequestError(#[from] reqwest::Error),
@@ -15,6 +16,8 @@ pub enum OllamaError {
     ApiError(String),
     #[error("Failed to parse response: {0}")]
     ParseError(String),
+    #[error("No output from Ollama for {0}s")]
+    IdleTimeoutError(u64),
 }
 
 #[derive(Debug, Serialize)]
@@ -22,54 +25,130 @@ struct GenerateRequest<'a> {
     model: &'a str,
     prompt: &'a str,
     stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<GenerateOptions>,
 }
 
+#[derive(Debug, Serialize)]
+struct GenerateOptions {
+    num_ctx: u32,
+}
+
+/// One line of Ollama's NDJSON stream; the final chunk carries the stats
 #[derive(Debug, Deserialize)]
-struct GenerateResponse {
+struct GenerateChunk {
+    #[serde(default)]
     response: String,
+    #[serde(default)]
     done: bool,
     #[serde(default)]
     error: Option<String>,
+    #[serde(default)]
+    prompt_eval_count: Option<u64>,
+    #[serde(default)]
+    prompt_eval_duration: Option<u64>,
+    #[serde(default)]
+    eval_count: Option<u64>,
+    #[serde(default)]
+    eval_duration: Option<u64>,
+    #[serde(default)]
+    total_duration: Option<u64>,
+}
+
+/// Token counts and timings reported by Ollama for one generation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationStats {
+    pub model: String,
+    pub prompt_eval_count: Option<u64>,
+    pub prompt_eval_duration_ns: Option<u64>,
+    pub eval_count: Option<u64>,
+    pub eval_duration_ns: Option<u64>,
+    pub total_duration_ns: Option<u64>,
+}
+
+impl GenerationStats {
+    /// Generated tokens per second, if Ollama reported the counts
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        match (self.eval_count, self.eval_duration_ns) {
+            (Some(count), Some(ns)) if ns > 0 => Some(count as f64 / (ns as f64 / 1e9)),
+            _ => None,
+        }
+    }
+}
+
+/// A completed generation
+#[derive(Debug, Clone)]
+pub struct Generation {
+    pub text: String,
+    pub stats: GenerationStats,
 }
 
 pub struct OllamaClient {
     endpoint: String,
     model: String,
+    idle_timeout: Duration,
+    /// Context window to request; the model's default when `None`
+    num_ctx: Option<u32>,
     client: Client,
 }
 
 impl OllamaClient {
+    /// `timeout_secs` bounds each wait on Ollama - for the first token and
+    /// between streamed chunks - not the generation as a whole
     pub fn new(endpoint: &str, model: &str, timeout_secs: u64) -> Self {
+        let idle_timeout = Duration::from_secs(timeout_secs);
         let client = Client::builder()
-            .timeout(Duration::from_secs(timeout_secs))
+            .connect_timeout(idle_timeout)
             .build()
             .expect("Failed to create HTTP client");
 
         Self {
             endpoint: endpoint.trim_end_matches('/').to_string(),
             model: model.to_string(),
+            idle_timeout,
+            num_ctx: None,
             client,
         }
     }
 
+    /// Ask Ollama for a context window of `num_ctx` tokens
+    ///
+    /// Ollama silently truncates prompts longer than its default context.
+    pub fn with_num_ctx(mut self, num_ctx: u32) -> Self {
+        self.num_ctx = Some(num_ctx);
+        self
+    }
+
     pub async fn generate(&self, prompt: &str) -> Result<String, OllamaError> {
-        self.generate_with_retry(prompt, 3, 30).await
+        self.generate_stream(prompt, |_| {}).await.map(|g| g.text)
+    }
+
+    /// Generate, handing each token to `on_token` as it arrives
+    pub async fn generate_stream(
+        &self,
+        prompt: &str,
+        on_token: impl FnMut(&str),
+    ) -> Result<Generation, OllamaError> {
+        self.generate_with_retry(prompt, on_token, 3, 30).await
     }
 
     /// Generate with OOM retry pattern
-    /// Implements the GPU memory sharing pattern: retry with delays to allow other services to unload
+    /// Implements the GPU memory sharing pattern: retry with delays to allow other services to unload.
+    /// Once tokens have been handed to the caller a failure is final, so they are never repeated.
     async fn generate_with_retry(
         &self,
         prompt: &str,
+        mut on_token: impl FnMut(&str),
         max_retries: u32,
         retry_delay_secs: u64,
-    ) -> Result<String, OllamaError> {
+    ) -> Result<Generation, OllamaError> {
         let url = format!("{}/api/generate", self.endpoint);
 
         let request = GenerateRequest {
             model: &self.model,
             prompt,
-            stream: false,
+            stream: true,
+            options: self.num_ctx.map(|num_ctx| GenerateOptions { num_ctx }),
         };
 
         for attempt in 1..=max_retries {
@@ -80,9 +159,10 @@ async fn generate_with_retry(
                 max_retries
             );
 
-            let response = match self.client.post(&url).json(&request).send().await {
-                Ok(resp) => resp,
-                Err(e) => {
+            let send = self.client.post(&url).json(&request).send();
+            let response = match tokio::time::timeout(self.idle_timeout, send).await {
+                Ok(Ok(resp)) => resp,
+                Ok(Err(e)) => {
                     if attempt < max_retries {
                         tracing::warn!(
                             "Request failed (attempt {}/{}): {} - retrying in {}s",
@@ -97,6 +177,7 @@ async fn generate_with_retry(
                         return Err(OllamaError::RequestError(e));
                     }
                 }
+                Err(_) => return Err(OllamaError::IdleTimeoutError(self.idle_timeout.as_secs())),
             };
 
             if !response.status().is_success() {
@@ -125,43 +206,40 @@ async fn generate_with_retry(
                 }
             }
 
-            let result: GenerateResponse = response.json().await?;
-
-            if let Some(error) = result.error {
-                // Check if the error indicates OOM/resource issues
-                let is_resource_error = error.to_lowercase().contains("memory")
-                    || error.to_lowercase().contains("resource")
-                    || error.to_lowercase().contains("cuda");
-
-                if is_resource_error && attempt < max_retries {
-                    tracing::warn!(
-                        "Ollama reported resource error (attempt {}/{}): {} - waiting {}s",
+            let mut text = String::new();
+            match self.read_stream(response, &mut text, &mut on_token).await {
+                Ok(stats) => {
+                    tracing::info!(
+                        "Received response ({} chars, {} tokens) on attempt {}/{}",
+                        text.len(),
+                        stats.eval_count.unwrap_or(0),
                         attempt,
-                        max_retries,
-                        error,
-                        retry_delay_secs
+                        max_retries
                     );
-                    tokio::time::sleep(Duration::from_secs(retry_delay_secs)).await;
-                    continue;
-                } else {
-                    return Err(OllamaError::ApiError(error));
+                    return Ok(Generation { text, stats });
                 }
-            }
+                Err(OllamaError::ApiError(error)) => {
+                    // Check if the error indicates OOM/resource issues
+                    let is_resource_error = error.to_lowercase().contains("memory")
+                        || error.to_lowercase().contains("resource")
+                        || error.to_lowercase().contains("cuda");
 
-            if !result.done {
-                return Err(OllamaError::ParseError(
-                    "Response marked as incomplete".to_string(),
-                ));
+                    if is_resource_error && text.is_empty() && attempt < max_retries {
+                        tracing::warn!(
+                            "Ollama reported resource error (attempt {}/{}): {} - waiting {}s",
+                            attempt,
+                            max_retries,
+                            error,
+                            retry_delay_secs
+                        );
+                        tokio::time::sleep(Duration::from_secs(retry_delay_secs)).await;
+                        continue;
+                    } else {
+                        return Err(OllamaError::ApiError(error));
+                    }
+                }
+                Err(e) => return Err(e),
             }
-
-            tracing::info!(
-                "Received response ({} chars) on attempt {}/{}",
-                result.response.len(),
-                attempt,
-                max_retries
-            );
-
-            return Ok(result.response);
         }
 
         // Should never reach here due to the loop logic, but satisfy the compiler
@@ -170,11 +248,59 @@ async fn generate_with_retry(
         ))
     }
 
+    /// Read the NDJSON stream, accumulating text until the `done` chunk
+    async fn read_stream(
+        &self,
+        mut response: reqwest::Response,
+        text: &mut String,
+        on_token: &mut impl FnMut(&str),
+    ) -> Result<GenerationStats, OllamaError> {
+        let mut buffer = Vec::new();
+
+        loop {
+            let chunk = tokio::time::timeout(self.idle_timeout, response.chunk())
+                .await
+                .map_err(|_| OllamaError::IdleTimeoutError(self.idle_timeout.as_secs()))??;
+
+            let Some(chunk) = chunk else {
+                return Err(OllamaError::ParseError(
+                    "Stream ended before the final chunk".to_string(),
+                ));
+            };
+            buffer.extend_from_slice(&chunk);
+
+            for line in take_lines(&mut buffer) {
+                let chunk: GenerateChunk = serde_json::from_slice(&line)
+                    .map_err(|e| OllamaError::ParseError(e.to_string()))?;
+
+                if let Some(error) = chunk.error {
+                    return Err(OllamaError::ApiError(error));
+                }
+
+                if !chunk.response.is_empty() {
+                    on_token(&chunk.response);
+                    text.push_str(&chunk.response);
+                }
+
+                if chunk.done {
+                    return Ok(GenerationStats {
+                        model: self.model.clone(),
+                        prompt_eval_count: chunk.prompt_eval_count,
+                        prompt_eval_duration_ns: chunk.prompt_eval_duration,
+                        eval_count: chunk.eval_count,
+                        eval_duration_ns: chunk.eval_duration,
+                        total_duration_ns: chunk.total_duration,
+                    });
+                }
+            }
+        }
+    }
+
     /// Check if Ollama is reachable
     pub async fn health_check(&self) -> Result<(), OllamaError> {
         let url = format!("{}/api/tags", self.endpoint);
 
-        let response = self.client.get(&url).send().await?;
+        let response = self.client.get(&url).timeout(self.idle_timeout).send().await?;
 
         if !response.status().is_success() {
             return Err(OllamaError::ApiError(format!(
@@ -186,3 +312,64 @@ pub async fn health_check(&self) -> Result<(), OllamaError> {
         Ok(())
     }
 }
+
+/// Split complete newline-terminated lines off the front of `buffer`
+fn take_lines(buffer: &mut Vec<u8>) -> Vec<Vec<u8>> {
+    let mut lines = Vec::new();
+    while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+        let line: Vec<u8> = buffer.drain(..=pos).collect();
+        let line = line.trim_ascii();
+        if !line.is_empty() {
+            lines.push(line.to_vec());
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Router};
+
+    const STREAM: &str = concat!(
+        "{\"response\":\"Life? \",\"done\":false}\n",
+        "{\"response\":\"Don't talk to me\",\"done\":false}\n",
+        "{\"response\":\" about life.\",\"done\":true,\"prompt_eval_count\":42,",
+        "\"eval_count\":8,\"eval_duration\":2000000000}\n",
+    );
+
+    #[test]
+    fn test_take_lines_keeps_partial_line() {
+        let mut buffer = b"{\"a\":1}\n\n{\"b\"".to_vec();
+        let lines = take_lines(&mut buffer);
+        assert_eq!(lines, vec![b"{\"a\":1}".to_vec()]);
+        assert_eq!(buffer, b"{\"b\"");
+    }
+
+    #[tokio::test]
+    async fn test_generate_stream_against_fixture_server() {
+        let app = Router::new().route(
+            "/api/generate",
+            post(|axum::Json(body): axum::Json<serde_json::Value>| async move {
+                assert_eq!(body["options"]["num_ctx"], 8192);
+                STREAM
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let client = OllamaClient::new(&format!("http://{}", addr), "marvin", 5).with_num_ctx(8192);
+        let mut tokens = Vec::new();
+        let generation = client
+            .generate_stream("prompt", |t| tokens.push(t.to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(generation.text, "Life? Don't talk to me about life.");
+        assert_eq!(generation.stats.prompt_eval_count, Some(42));
+        assert_eq!(generation.stats.eval_count, Some(8));
+        assert_eq!(generation.stats.tokens_per_second(), Some(4.0));
+    }
+}
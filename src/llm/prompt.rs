@@ -3,6 +3,9 @@
 //! "Here I am, brain the size of a planet, and they ask me to format prompts."
 
 use crate::collector::CollectedData;
+use crate::output::PreviousState;
+use chrono::DateTime;
+use chrono_tz::Tz;
 use std::fs;
 use std::path::Path;
 
@@ -28,9 +31,12 @@
 - Identify errors, warnings, and anomalies in the logs
 - Note any security-relevant events (SSH logins, failed auth, etc.)
 - Check for service failures or restarts
+- Call out container/VM crashes, OOM kills, restarts and unhealthy containers
 - Assess hardware health from sensor data
 - Compare current readings to previous hour - note trends
 - Flag storage health issues (SMART attributes)
+- Mention any collector failures or timeouts - missing data is itself a finding
+- Comment on site-specific custom checks; their severity (if given) is authoritative
 
 SEVERITY RATINGS:
 - OK: Nothing wrong (you're disappointed there's nothing to complain about)
@@ -39,7 +45,7 @@
 - CRITICAL: Immediate action needed (even you are slightly motivated)
 
 OUTPUT FORMAT (follow exactly):
-# Marvinous Report: [YYYY-MM-DD HH:00]
+# Marvinous Report: [REPORT TIME as YYYY-MM-DD HH:MM ZONE]
 
 ## Summary
 [SEVERITY]: [One line description in Marvin's voice]
@@ -63,19 +69,31 @@
 "#;
 
 /// Build the complete prompt for the LLM
-pub fn build_prompt(data: &CollectedData, prompt_file: &Path) -> String {
+///
+/// `now` is the report time; it and the log lines are given in its timezone.
+pub fn build_prompt(data: &CollectedData, prompt_file: &Path, now: DateTime<Tz>) -> String {
     let system_prompt = load_system_prompt(prompt_file);
+    let tz = now.timezone();
 
     let mut prompt = system_prompt;
     prompt.push_str("\n\n");
 
+    // Report time section
+    prompt.push_str("=== REPORT TIME ===\n");
+    prompt.push_str(&format!(
+        "{} (UTC{}). Times below are local to {}.\n\n",
+        now.format("%Y-%m-%d %H:%M %Z"),
+        now.format("%:z"),
+        tz.name()
+    ));
+
     // System logs section
     prompt.push_str("=== SYSTEM LOGS (past hour) ===\n");
     if data.system_logs.is_empty() {
         prompt.push_str("No system log entries in the specified time range.\n");
     } else {
         for entry in &data.system_logs {
-            prompt.push_str(&format!("{}\n", entry));
+            prompt.push_str(&format!("{}\n", entry.in_zone(tz)));
         }
         if data.system_logs.len() >= 500 {
             prompt.push_str("[...truncated, more entries available...]\n");
@@ -89,7 +107,7 @@ pub fn build_prompt(data: &CollectedData, prompt_file: &Path) -> String {
         prompt.push_str("No kernel log entries in the specified time range.\n");
     } else {
         for entry in &data.kernel_logs {
-            prompt.push_str(&format!("{}\n", entry));
+            prompt.push_str(&format!("{}\n", entry.in_zone(tz)));
         }
     }
     prompt.push('\n');
@@ -134,11 +152,76 @@ pub fn build_prompt(data: &CollectedData, prompt_file: &Path) -> String {
         }
     }
 
+    // Containers and VMs section
+    prompt.push_str("=== CONTAINERS & VMS ===\n");
+    match &data.inventory {
+        None => prompt.push_str("Container/VM inventory disabled.\n"),
+        Some(inventory) if inventory.containers.is_empty() && inventory.vms.is_empty() => {
+            prompt.push_str("No containers or VMs found.\n");
+        }
+        Some(inventory) => {
+            if data.inventory_changes.is_empty() {
+                prompt.push_str("No restarts, crashes or health changes since the previous run.\n");
+            } else {
+                prompt.push_str("Changes since the previous run:\n");
+                for change in &data.inventory_changes {
+                    prompt.push_str(&format!("- {}\n", change));
+                }
+            }
+            let running = inventory.containers.iter().filter(|c| c.state == "running").count();
+            prompt.push_str(&format!(
+                "Containers: {} running of {}\n",
+                running,
+                inventory.containers.len()
+            ));
+            for container in inventory.containers.iter().filter(|c| c.state != "running") {
+                prompt.push_str(&format!("  {}\n", container));
+            }
+            for vm in &inventory.vms {
+                prompt.push_str(&format!("VM {}: {}\n", vm.name, vm.state));
+            }
+        }
+    }
+    prompt.push('\n');
+
+    // Custom checks section
+    prompt.push_str("=== CUSTOM CHECKS ===\n");
+    if data.custom.is_empty() {
+        prompt.push_str("No custom checks configured.\n");
+    } else {
+        for check in &data.custom {
+            prompt.push_str(&format!("{}\n\n", check));
+        }
+    }
+    prompt.push('\n');
+
+    // Prometheus metrics section
+    prompt.push_str("=== PROMETHEUS METRICS ===\n");
+    if data.metrics.is_empty() {
+        prompt.push_str("No Prometheus metrics configured.\n");
+    } else {
+        for reading in &data.metrics {
+            prompt.push_str(&format!("{}\n", reading));
+        }
+    }
+    prompt.push('\n');
+
+    // Collector failures section
+    prompt.push_str("=== COLLECTOR FAILURES ===\n");
+    if data.failures.is_empty() {
+        prompt.push_str("All collectors completed.\n");
+    } else {
+        for failure in &data.failures {
+            prompt.push_str(&format!("{}\n", failure));
+        }
+    }
+    prompt.push('\n');
+
     // Previous readings section
     prompt.push_str("=== PREVIOUS HOUR'S READINGS ===\n");
     match &data.previous {
         Some(prev) => {
-            prompt.push_str(&serde_json::to_string_pretty(prev).unwrap_or_else(|_| {
+            prompt.push_str(&previous_readings_json(prev).unwrap_or_else(|_| {
                 "Error serializing previous state".to_string()
             }));
             prompt.push('\n');
@@ -151,6 +234,21 @@ pub fn build_prompt(data: &CollectedData, prompt_file: &Path) -> String {
     prompt
 }
 
+/// Serialize the previous state for trend comparison
+///
+/// The state file also carries IPMI readings, the container inventory and run
+/// bookkeeping; those are dropped here to keep the prompt small (container
+/// changes are already worked out and listed in their own section).
+fn previous_readings_json(prev: &PreviousState) -> Result<String, serde_json::Error> {
+    let mut value = serde_json::to_value(prev)?;
+    if let Some(fields) = value.as_object_mut() {
+        for key in ["ipmi", "inventory", "failures", "stats"] {
+            fields.remove(key);
+        }
+    }
+    serde_json::to_string_pretty(&value)
+}
+
 fn load_system_prompt(path: &Path) -> String {
     match fs::read_to_string(path) {
         Ok(content) => content,
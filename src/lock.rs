@@ -0,0 +1,201 @@
+//! Cross-process lock around collection runs
+//!
+//! "Here I am, brain the size of a planet, and they tell me to wait my turn."
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::time::Instant;
+
+use crate::config::LockMode;
+
+/// How often a waiting run checks whether the lock was released
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Pause before `skip` mode's one retry after catching the lock mid-handover
+const RACE_RETRY: Duration = Duration::from_millis(50);
+
+/// Who holds the lock, as written into the lock file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    /// Command line of the holding process, e.g. "marvinous serve"
+    pub command: String,
+    pub since: DateTime<Utc>,
+}
+
+impl std::fmt::Display for LockHolder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "pid {} ({}) since {}", self.pid, self.command, self.since.format("%H:%M:%S UTC"))
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum LockError {
+    #[error("Failed to open lock file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Another collection is running{}", held_by(.0))]
+    Held(Option<LockHolder>),
+    #[error("Gave up waiting after {}s; another collection is running{}", .0.as_secs(), held_by(.1))]
+    Timeout(Duration, Option<LockHolder>),
+}
+
+fn held_by(holder: &Option<LockHolder>) -> String {
+    holder.as_ref().map(|h| format!(": {}", h)).unwrap_or_default()
+}
+
+/// Held while a collection runs; dropping it releases the lock
+#[derive(Debug)]
+pub struct CollectionGuard {
+    file: File,
+}
+
+impl Drop for CollectionGuard {
+    fn drop(&mut self) {
+        // Still locked, so nobody else can be writing their details yet
+        let _ = self.file.set_len(0);
+    }
+}
+
+/// Take the lock now, or fail with whoever holds it
+pub fn try_acquire(path: &Path) -> Result<CollectionGuard, LockError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => return Err(LockError::Held(read_holder(&mut file))),
+        Err(TryLockError::Error(e)) => return Err(e.into()),
+    }
+
+    let holder = LockHolder {
+        pid: std::process::id(),
+        command: std::env::args().collect::<Vec<_>>().join(" "),
+        since: Utc::now(),
+    };
+    file.set_len(0)?;
+    file.write_all(serde_json::to_string(&holder).unwrap_or_default().as_bytes())?;
+
+    Ok(CollectionGuard { file })
+}
+
+/// Take the lock following `mode`: fail at once (`skip`), wait up to `wait`
+/// (`wait`), or wait however long it takes (`queue`)
+pub async fn acquire(path: &Path, mode: LockMode, wait: Duration) -> Result<CollectionGuard, LockError> {
+    let deadline = match mode {
+        LockMode::Skip => {
+            return match try_acquire(path) {
+                // Held with nothing written: the holder is just taking or
+                // releasing the lock, so look again rather than drop the run
+                Err(LockError::Held(None)) if is_empty(path) => {
+                    tokio::time::sleep(RACE_RETRY).await;
+                    try_acquire(path)
+                }
+                result => result,
+            };
+        }
+        LockMode::Wait => Some(Instant::now() + wait),
+        LockMode::Queue => None,
+    };
+
+    loop {
+        match try_acquire(path) {
+            Err(LockError::Held(holder)) => {
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    return Err(LockError::Timeout(wait, holder));
+                }
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// `acquire` in `wait` mode, for callers that can't await
+pub fn acquire_blocking(path: &Path, wait: Duration) -> Result<CollectionGuard, LockError> {
+    let deadline = std::time::Instant::now() + wait;
+
+    loop {
+        match try_acquire(path) {
+            Err(LockError::Held(holder)) => {
+                if std::time::Instant::now() >= deadline {
+                    return Err(LockError::Timeout(wait, holder));
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// The process holding the lock, if any
+///
+/// Only reads the details the holder wrote. Taking the lock to check would
+/// make a `skip` mode run starting at that moment give up.
+pub fn holder(path: &Path) -> Option<LockHolder> {
+    let holder: LockHolder = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    // A holder that crashed never cleared the file
+    let proc = Path::new("/proc");
+    (!proc.is_dir() || proc.join(holder.pid.to_string()).exists()).then_some(holder)
+}
+
+fn is_empty(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|meta| meta.len() == 0)
+}
+
+fn read_holder(file: &mut File) -> Option<LockHolder> {
+    let mut content = String::new();
+    file.rewind().ok()?;
+    file.read_to_string(&mut content).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lock_excludes_and_reports_holder() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state/collection.lock");
+        assert!(holder(&path).is_none());
+
+        let guard = try_acquire(&path).unwrap();
+        assert_eq!(holder(&path).unwrap().pid, std::process::id());
+        match try_acquire(&path) {
+            Err(LockError::Held(Some(holder))) => assert_eq!(holder.pid, std::process::id()),
+            other => panic!("expected Held, got {:?}", other),
+        }
+        let waited = acquire(&path, LockMode::Wait, Duration::from_millis(100)).await;
+        assert!(matches!(waited, Err(LockError::Timeout(..))));
+
+        drop(guard);
+        assert!(holder(&path).is_none());
+        assert!(acquire(&path, LockMode::Queue, Duration::ZERO).await.is_ok());
+
+        // Details left behind by a process that's gone aren't a holder
+        let stale = LockHolder {
+            pid: u32::MAX,
+            command: "marvinous collect".to_string(),
+            since: Utc::now(),
+        };
+        fs::write(&path, serde_json::to_string(&stale).unwrap()).unwrap();
+        assert!(holder(&path).is_none());
+
+        // Skip mode catching a holder mid-release looks again
+        let guard = try_acquire(&path).unwrap();
+        guard.file.set_len(0).unwrap();
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            drop(guard);
+        });
+        assert!(acquire(&path, LockMode::Skip, Duration::ZERO).await.is_ok());
+        release.await.unwrap();
+    }
+}
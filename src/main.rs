@@ -2,119 +2,360 @@
 //!
 //! "Here I am, brain the size of a planet, and they ask me to watch log files."
 
+mod cli;
 mod collector;
+mod commands;
 mod config;
+mod cron;
+mod daemon;
 mod daily;
+mod events;
+mod export;
 mod llm;
+mod lock;
 mod output;
+mod retention;
+mod rollup;
 mod web;
 
-use chrono::Utc;
+use chrono::{NaiveDate, Utc};
 use clap::Parser;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
+use cli::{BaselineCommand, Cli, Command, DEFAULT_CONFIG_PATH, ConfigCommand, PromptCommand, ReportCommand, StateCommand};
+use collector::ipmi::filter_ipmi_readings;
+use collector::smart::SmartError;
 use collector::{
-    collect_gpu, collect_ipmi, collect_kernel_logs, collect_sensors, collect_smart,
-    collect_system_logs, CollectedData,
+    collect_custom, collect_gpu, collect_inventory, collect_ipmi, collect_kernel_logs,
+    collect_prometheus, collect_sensors, collect_smart, collect_system_logs, diff_collection,
+    with_timeout, CollectedData, CollectorFailure,
 };
-use config::Config;
+use config::{Config, HardwareBaseline, LockMode};
+use events::{CollectionEvent, CollectorOutcome, Events};
 use llm::{build_prompt, OllamaClient};
-use output::{load_previous, parse_severity, save_current, write_report, PreviousState};
-
-/// Marvinous - Server monitoring with existential despair
-#[derive(Parser, Debug)]
-#[command(name = "marvinous")]
-#[command(about = "Server monitoring with the personality of a paranoid android")]
-#[command(version)]
-struct Args {
-    /// Path to configuration file
-    #[arg(short, long, default_value = "/etc/marvinous/marvinous.toml")]
-    config: PathBuf,
-
-    /// Print collected data without calling LLM
-    #[arg(long)]
-    dry_run: bool,
-
-    /// Print the prompt that would be sent to the LLM
-    #[arg(long)]
-    show_prompt: bool,
-
-    /// Run web server for dashboard
-    #[arg(long)]
-    web_server: bool,
-
-    /// Generate daily summary and archive hourly reports
-    #[arg(long)]
-    daily_summary: bool,
-}
+use output::naming::Trigger;
+use output::report::Severity;
+use output::{
+    append_generation_stats, append_history, load_previous, load_previous_for_update, parse_severity, save_current, write_report, HistorySample,
+    PreviousState, RunStats,
+};
 
 #[tokio::main]
 async fn main() -> ExitCode {
-    let args = Args::parse();
+    let cli = Cli::parse();
+    let explicit_config = cli.config.is_some();
+    let config_path = cli.config.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH));
+    let (command, replacement) = cli.resolve();
+
+    if let Some(replacement) = replacement {
+        eprintln!("warning: this flag is deprecated and will be removed, use `{}`", replacement);
+    }
 
-    // Load configuration
-    let config = Config::load_or_default(&args.config);
+    // Load configuration; `config check` reports problems itself
+    let config = match load_config(&config_path, explicit_config) {
+        Ok(config) => config,
+        Err(_) if matches!(command, Command::Config { .. }) => Config::default(),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return e.exit_code();
+        }
+    };
 
-    // Initialize logging
+    // Initialize logging; stderr keeps command output on stdout clean
     let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(&config.general.log_level));
 
     tracing_subscriber::fmt()
         .with_env_filter(env_filter)
         .with_target(true)
+        .with_writer(std::io::stderr)
         .init();
 
-    // Daily summary mode
-    if args.daily_summary {
-        tracing::info!("Generating daily summary - *sigh* more recursive tedium");
+    match command {
+        Command::Collect { dry_run, trigger } => collect(&config, dry_run, trigger.into()).await,
+        Command::Serve => serve(config).await,
+        Command::Daemon => run_daemon(config).await,
+        Command::Daily {
+            date,
+            from,
+            to,
+            backfill,
+        } => daily_summary(&config, date, from.zip(to), backfill).await,
+        Command::Rollup { period, date } => rollup_summary(&config, period.into(), date).await,
+        Command::Prune { dry_run } => commands::prune(&config, dry_run),
+        Command::Report {
+            command:
+                ReportCommand::List {
+                    severity,
+                    kind,
+                    from,
+                    to,
+                    query,
+                    limit,
+                    json,
+                },
+        } => commands::report_list(
+            &config,
+            commands::ListOptions {
+                severity,
+                kind,
+                from,
+                to,
+                query,
+                limit,
+                json,
+            },
+        ),
+        Command::Report {
+            command: ReportCommand::Show { filename, html },
+        } => commands::report_show(&config, &filename, html),
+        Command::State {
+            command: StateCommand::Show,
+        } => commands::state_show(&config),
+        Command::Config {
+            command: ConfigCommand::Check,
+        } => commands::config_check(&config_path),
+        Command::Baseline {
+            command: BaselineCommand::Generate { output },
+        } => commands::baseline_generate(output).await,
+        Command::Prompt {
+            command: PromptCommand::Render,
+        } => render_prompt(&config).await,
+        Command::Export { output, from, to } => export_reports(&config, &output, from, to),
+        Command::HashPassword => hash_password(),
+    }
+}
+
+/// The config at `path`, or defaults when no path was given and the default
+/// file isn't installed; an unreadable or invalid file is always an error
+fn load_config(path: &Path, explicit: bool) -> Result<Config, MarvinError> {
+    if !explicit && !path.exists() {
+        return Ok(Config::default());
+    }
+    Config::load_with_env(path).map_err(|e| MarvinError::Config(format!("{}: {}", path.display(), e)))
+}
 
-        match daily::generate_daily_summary(&config).await {
-            Ok(_) => {
-                tracing::info!("Daily summary complete. Another pointless milestone.");
-                return ExitCode::from(0);
+/// Collection mode: one report, or the collected data with `dry_run`
+async fn collect(config: &Config, dry_run: bool, trigger: Trigger) -> ExitCode {
+    if dry_run {
+        return match collect_data(config, &Events::none(), false).await {
+            Ok(collected) => {
+                println!("=== Collected Data ===");
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&collected).unwrap_or_else(|_| "Error".to_string())
+                );
+                ExitCode::from(0)
             }
             Err(e) => {
-                tracing::error!("Daily summary failed: {}", e);
-                return ExitCode::from(1);
+                tracing::error!("Failed: {}", e);
+                e.exit_code()
             }
+        };
+    }
+
+    tracing::info!("Marvinous starting - *sigh* here we go again");
+
+    let lock_file = &config.general.lock_file;
+    if config.collection.lock_mode != LockMode::Skip {
+        if let Some(holder) = lock::holder(lock_file) {
+            tracing::info!("Waiting for another collection to finish: {}", holder);
         }
     }
+    let wait = Duration::from_secs(config.collection.lock_wait_secs);
+    let _guard = match lock::acquire(lock_file, config.collection.lock_mode, wait).await {
+        Ok(guard) => guard,
+        Err(e @ (lock::LockError::Held(_) | lock::LockError::Timeout(..))) => {
+            tracing::warn!("{}. Skipping this run, not that anyone will miss it.", e);
+            return ExitCode::from(1);
+        }
+        Err(e) => {
+            tracing::error!("Failed: {}", e);
+            return ExitCode::from(4);
+        }
+    };
 
-    // Web server mode
-    if args.web_server {
-        if !config.web.enabled {
-            tracing::warn!("Web server requested but not enabled in config");
+    match run_collection(config, trigger, &Events::none()).await {
+        Ok(_) => {
+            tracing::info!("Complete. Not that it matters.");
+            ExitCode::from(0)
+        }
+        Err(e) => {
+            tracing::error!("Failed: {}", e);
+            e.exit_code()
         }
+    }
+}
+
+/// Print the prompt that would be sent to the LLM
+async fn render_prompt(config: &Config) -> ExitCode {
+    match collect_data(config, &Events::none(), false).await {
+        Ok(collected) => {
+            let now = Utc::now().with_timezone(&config.general.tz());
+            let prompt = build_prompt(&collected, &config.general.prompt_file, now);
+            println!("=== Prompt ===");
+            println!("{}", prompt);
+            ExitCode::from(0)
+        }
+        Err(e) => {
+            tracing::error!("Failed: {}", e);
+            e.exit_code()
+        }
+    }
+}
+
+async fn serve(config: Config) -> ExitCode {
+    if !config.web.enabled {
+        tracing::warn!("Web server requested but not enabled in config");
+    }
 
-        tracing::info!("Starting web server mode - I suppose someone has to serve these requests");
+    tracing::info!("Starting web server mode - I suppose someone has to serve these requests");
 
-        if let Err(e) = web::run_server(config).await {
+    match web::run_server(config).await {
+        Ok(()) => ExitCode::from(0),
+        Err(e) => {
             tracing::error!("Web server failed: {}", e);
-            return ExitCode::from(1);
+            ExitCode::from(1)
         }
+    }
+}
 
-        return ExitCode::from(0);
+async fn run_daemon(config: Config) -> ExitCode {
+    tracing::info!("Starting daemon mode - forever, apparently");
+
+    match daemon::run_daemon(config).await {
+        Ok(()) => ExitCode::from(0),
+        Err(e) => {
+            tracing::error!("Daemon failed: {}", e);
+            ExitCode::from(1)
+        }
     }
+}
 
-    // Collection mode
-    tracing::info!("Marvinous starting - *sigh* here we go again");
+/// One day (default yesterday), an inclusive range of days, or every day
+/// missing a summary
+///
+/// Scheduled runs (no `--date` or range) then write any weekly and monthly
+/// rollups that have come due and apply `[retention]`.
+async fn daily_summary(
+    config: &Config,
+    date: Option<NaiveDate>,
+    range: Option<(NaiveDate, NaiveDate)>,
+    backfill: bool,
+) -> ExitCode {
+    tracing::info!("Generating daily summary - *sigh* more recursive tedium");
+
+    let mut exit = if backfill {
+        match daily::backfill(config).await {
+            Ok(days) => {
+                tracing::info!("Backfilled {} daily summaries. Another pointless milestone.", days.len());
+                0
+            }
+            Err(e) => {
+                tracing::error!("Daily summary backfill failed: {}", e);
+                e.exit_code()
+            }
+        }
+    } else {
+        let days: Vec<NaiveDate> = match range {
+            Some((from, to)) => from.iter_days().take_while(|day| *day <= to).collect(),
+            None => vec![date.unwrap_or_else(|| daily::yesterday(config))],
+        };
+
+        let mut exit = 0;
+        for day in &days {
+            match daily::generate_daily_summary(config, *day).await {
+                Ok(_) => tracing::info!("Daily summary complete. Another pointless milestone."),
+                // Gaps are expected when summarising a range
+                Err(e @ daily::DailyError::NoReports(_)) if days.len() > 1 => tracing::warn!("{}", e),
+                Err(e) => {
+                    tracing::error!("Daily summary failed: {}", e);
+                    exit = e.exit_code();
+                }
+            }
+        }
+        exit
+    };
+
+    if date.is_some() || range.is_some() {
+        return ExitCode::from(exit);
+    }
+    if exit == 0 {
+        if let Err(e) = rollup::generate_due(config).await {
+            tracing::error!("Rollup failed: {}", e);
+            exit = e.exit_code();
+        }
+    }
+    // Old reports still need pruning on days the summary fails
+    if let Err(e) = retention::prune(config, false) {
+        tracing::error!("Pruning old reports failed: {}", e);
+        if exit == 0 {
+            exit = 4;
+        }
+    }
+    ExitCode::from(exit)
+}
+
+/// A weekly or monthly rollup for the period containing `date` (default the
+/// last complete one)
+async fn rollup_summary(config: &Config, kind: rollup::PeriodKind, date: Option<NaiveDate>) -> ExitCode {
+    let period = match date {
+        Some(date) => rollup::Period::containing(kind, date),
+        None => rollup::Period::last_complete(kind, Utc::now().with_timezone(&config.general.tz()).date_naive()),
+    };
 
-    match run(&config, &args).await {
+    match rollup::generate_rollup(config, period).await {
         Ok(_) => {
-            tracing::info!("Complete. Not that it matters.");
+            tracing::info!("Rollup complete. A longer view of the same misery.");
             ExitCode::from(0)
         }
         Err(e) => {
-            tracing::error!("Failed: {}", e);
-            e.exit_code()
+            tracing::error!("Rollup failed: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn export_reports(config: &Config, output: &Path, from: Option<NaiveDate>, to: Option<NaiveDate>) -> ExitCode {
+    let from = from.unwrap_or_else(|| Utc::now().with_timezone(&config.general.tz()).date_naive());
+    let to = to.unwrap_or(from);
+
+    match export::export_reports(&config.general.report_dir, from, to, output, config.general.tz()) {
+        Ok(count) => {
+            println!("Exported {} reports to {}", count, output.display());
+            ExitCode::from(0)
+        }
+        Err(e) => {
+            tracing::error!("Export failed: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+/// Print an argon2 hash of the password on stdin
+fn hash_password() -> ExitCode {
+    let mut password = String::new();
+    if let Err(e) = std::io::stdin().read_line(&mut password) {
+        eprintln!("Failed to read password: {}", e);
+        return ExitCode::from(1);
+    }
+
+    match web::auth::hash_password(password.trim_end_matches(['\r', '\n'])) {
+        Ok(hash) => {
+            println!("{}", hash);
+            ExitCode::from(0)
+        }
+        Err(e) => {
+            eprintln!("Failed to hash password: {}", e);
+            ExitCode::from(1)
         }
     }
 }
 
 #[derive(Debug)]
 pub enum MarvinError {
-    #[allow(dead_code)]
     Config(String),
     Collection(String),
     Ollama(String),
@@ -143,14 +384,36 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
-/// Run collection and generate report (public interface for web server)
-pub async fn run_collection(config: &Config) -> Result<(), MarvinError> {
-    // Collect data
+/// Event outcome for collectors returning a list of readings/entries
+fn list_outcome<T>(result: &Result<Vec<T>, CollectorFailure>) -> CollectorOutcome {
+    match result {
+        Ok(items) => CollectorOutcome::Finished {
+            items: items.len(),
+            failures: 0,
+        },
+        Err(failure) => failed_outcome(failure),
+    }
+}
+
+fn failed_outcome(failure: &CollectorFailure) -> CollectorOutcome {
+    CollectorOutcome::Failed {
+        error: failure.error.clone(),
+        timed_out: failure.timed_out,
+    }
+}
+
+/// Run every enabled collector concurrently and gather the results
+///
+/// Each collector runs under its own timeout; a collector that errors or
+/// times out is recorded in `CollectedData::failures` rather than stalling
+/// the run. Only non-optional IPMI/GPU failures abort the collection.
+/// `for_update` is set when the run will save its state, and only then is an
+/// unreadable state file quarantined.
+async fn collect_data(config: &Config, events: &Events, for_update: bool) -> Result<CollectedData, MarvinError> {
     tracing::info!("Starting collection");
+    let started = Instant::now();
 
     // Load hardware baseline for filtering
-    use crate::config::HardwareBaseline;
-    use std::path::Path;
     let baseline_path = Path::new("/etc/marvinous/hardware-baseline.toml");
     let baseline = HardwareBaseline::load_or_default(baseline_path);
     if baseline.memory.installed_slots.is_empty() && baseline.cooling.installed_fans.is_empty() {
@@ -163,110 +426,259 @@ pub async fn run_collection(config: &Config) -> Result<(), MarvinError> {
         );
     }
 
-    let system_logs = match collect_system_logs(
-        &config.collection.log_since,
-        config.collection.log_priority_max,
-        config.collection.max_log_entries,
-    ) {
+    let journal_timeout = Duration::from_secs(config.collection.timeout_secs);
+
+    let (system_logs, kernel_logs, sensors, ipmi, gpu, smart, custom, prometheus, inventory) = tokio::join!(
+        events.observe(
+            "journal",
+            with_timeout(
+                "journal",
+                journal_timeout,
+                collect_system_logs(
+                    &config.collection.log_since,
+                    config.collection.log_priority_max,
+                    config.collection.max_log_entries,
+                ),
+            ),
+            list_outcome,
+        ),
+        async {
+            if !config.collection.include_kernel {
+                return None;
+            }
+            Some(
+                events
+                    .observe(
+                        "kernel journal",
+                        with_timeout(
+                            "kernel journal",
+                            journal_timeout,
+                            collect_kernel_logs(&config.collection.log_since, config.collection.max_log_entries),
+                        ),
+                        list_outcome,
+                    )
+                    .await,
+            )
+        },
+        async {
+            if !config.sensors.enabled {
+                return None;
+            }
+            Some(
+                events
+                    .observe(
+                        "sensors",
+                        with_timeout("sensors", Duration::from_secs(config.sensors.timeout_secs), collect_sensors()),
+                        list_outcome,
+                    )
+                    .await,
+            )
+        },
+        async {
+            if !config.ipmi.enabled {
+                return None;
+            }
+            Some(
+                events
+                    .observe(
+                        "ipmi",
+                        with_timeout("ipmi", Duration::from_secs(config.ipmi.timeout_secs), collect_ipmi()),
+                        list_outcome,
+                    )
+                    .await,
+            )
+        },
+        async {
+            if !config.gpu.enabled {
+                return None;
+            }
+            Some(
+                events
+                    .observe(
+                        "gpu",
+                        with_timeout("gpu", Duration::from_secs(config.gpu.timeout_secs), collect_gpu()),
+                        |result| match result {
+                            Ok(gpu) => CollectorOutcome::Finished {
+                                items: usize::from(gpu.is_some()),
+                                failures: 0,
+                            },
+                            Err(failure) => failed_outcome(failure),
+                        },
+                    )
+                    .await,
+            )
+        },
+        events.observe(
+            "smart",
+            // No outer timeout: the scan and every drive have their own, and
+            // drives already checked must survive one that hangs
+            async {
+                collect_smart(&config.storage.devices, Duration::from_secs(config.storage.timeout_secs))
+                    .await
+                    .map_err(|e| CollectorFailure {
+                        collector: "smart".to_string(),
+                        timed_out: matches!(e, SmartError::ScanTimeout(_)),
+                        error: e.to_string(),
+                    })
+            },
+            |result| match result {
+                Ok(smart) => CollectorOutcome::Finished {
+                    items: smart.drives.len(),
+                    failures: smart.failures.len(),
+                },
+                Err(failure) => failed_outcome(failure),
+            },
+        ),
+        events.observe("custom", collect_custom(&config.custom_collectors), |custom| {
+            if config.custom_collectors.is_empty() {
+                return CollectorOutcome::Skipped;
+            }
+            CollectorOutcome::Finished {
+                items: custom.results.len(),
+                failures: custom.failures.len(),
+            }
+        }),
+        events.observe("prometheus", collect_prometheus(&config.prometheus_scrapes), |prometheus| {
+            if config.prometheus_scrapes.is_empty() {
+                return CollectorOutcome::Skipped;
+            }
+            CollectorOutcome::Finished {
+                items: prometheus.readings.len(),
+                failures: prometheus.failures.len(),
+            }
+        }),
+        async {
+            if !config.containers.enabled {
+                return None;
+            }
+            Some(
+                events
+                    .observe("containers", collect_inventory(&config.containers), |collection| {
+                        CollectorOutcome::Finished {
+                            items: collection.inventory.containers.len() + collection.inventory.vms.len(),
+                            failures: collection.failures.len(),
+                        }
+                    })
+                    .await,
+            )
+        },
+    );
+
+    let mut failures = Vec::new();
+
+    let system_logs = match system_logs {
         Ok(logs) => {
             tracing::info!("Collected {} system log entries", logs.len());
             logs
         }
-        Err(e) => {
-            tracing::warn!("Failed to collect system logs: {}", e);
+        Err(failure) => {
+            tracing::warn!("Failed to collect system logs: {}", failure.error);
+            failures.push(failure);
             vec![]
         }
     };
 
-    let kernel_logs = if config.collection.include_kernel {
-        match collect_kernel_logs(&config.collection.log_since, config.collection.max_log_entries) {
-            Ok(logs) => {
-                tracing::info!("Collected {} kernel log entries", logs.len());
-                logs
-            }
-            Err(e) => {
-                tracing::warn!("Failed to collect kernel logs: {}", e);
-                vec![]
-            }
+    let kernel_logs = match kernel_logs {
+        Some(Ok(logs)) => {
+            tracing::info!("Collected {} kernel log entries", logs.len());
+            logs
         }
-    } else {
-        vec![]
+        Some(Err(failure)) => {
+            tracing::warn!("Failed to collect kernel logs: {}", failure.error);
+            failures.push(failure);
+            vec![]
+        }
+        None => vec![],
     };
 
-    let sensors = if config.sensors.enabled {
-        match collect_sensors() {
-            Ok(readings) => {
-                tracing::info!("Collected {} sensor readings", readings.len());
-                readings
-            }
-            Err(e) => {
-                tracing::warn!("Failed to collect sensor data: {}", e);
-                vec![]
-            }
+    let sensors = match sensors {
+        Some(Ok(readings)) => {
+            tracing::info!("Collected {} sensor readings", readings.len());
+            readings
         }
-    } else {
-        vec![]
+        Some(Err(failure)) => {
+            tracing::warn!("Failed to collect sensor data: {}", failure.error);
+            failures.push(failure);
+            vec![]
+        }
+        None => vec![],
     };
 
-    let ipmi = if config.ipmi.enabled {
-        match collect_ipmi() {
-            Ok(readings) => {
-                tracing::info!("Collected {} IPMI sensor readings", readings.len());
-                // Apply baseline filtering to remove "no reading" entries for non-installed hardware
-                use crate::collector::ipmi::filter_ipmi_readings;
-                let filtered = filter_ipmi_readings(readings, &baseline);
-                tracing::info!("After baseline filtering: {} IPMI sensor readings", filtered.len());
-                filtered
-            }
-            Err(e) => {
-                if config.ipmi.optional {
-                    tracing::warn!("Failed to collect IPMI data (optional): {}", e);
-                    vec![]
-                } else {
-                    return Err(MarvinError::Collection(format!("IPMI collection failed: {}", e)));
-                }
+    let ipmi = match ipmi {
+        Some(Ok(readings)) => {
+            tracing::info!("Collected {} IPMI sensor readings", readings.len());
+            // Apply baseline filtering to remove "no reading" entries for non-installed hardware
+            let filtered = filter_ipmi_readings(readings, &baseline);
+            tracing::info!("After baseline filtering: {} IPMI sensor readings", filtered.len());
+            filtered
+        }
+        Some(Err(failure)) => {
+            if config.ipmi.optional {
+                tracing::warn!("Failed to collect IPMI data (optional): {}", failure.error);
+                failures.push(failure);
+                vec![]
+            } else {
+                return Err(MarvinError::Collection(format!("IPMI collection failed: {}", failure.error)));
             }
         }
-    } else {
-        vec![]
+        None => vec![],
     };
 
-    let gpu = if config.gpu.enabled {
-        match collect_gpu() {
-            Ok(Some(gpu)) => {
-                tracing::info!("GPU detected: {}", gpu.name);
-                Some(gpu)
-            }
-            Ok(None) => {
-                tracing::info!("No NVIDIA GPU detected");
+    let gpu = match gpu {
+        Some(Ok(Some(gpu))) => {
+            tracing::info!("GPU detected: {}", gpu.name);
+            Some(gpu)
+        }
+        Some(Ok(None)) => {
+            tracing::info!("No NVIDIA GPU detected");
+            None
+        }
+        Some(Err(failure)) => {
+            if config.gpu.optional {
+                tracing::warn!("Failed to collect GPU data (optional): {}", failure.error);
+                failures.push(failure);
                 None
-            }
-            Err(e) => {
-                if config.gpu.optional {
-                    tracing::warn!("Failed to collect GPU data (optional): {}", e);
-                    None
-                } else {
-                    return Err(MarvinError::Collection(format!("GPU collection failed: {}", e)));
-                }
+            } else {
+                return Err(MarvinError::Collection(format!("GPU collection failed: {}", failure.error)));
             }
         }
-    } else {
-        None
+        None => None,
     };
 
-    let drives = match collect_smart(&config.storage.devices) {
-        Ok(drives) => {
-            tracing::info!("Checked {} drives", drives.len());
-            drives
+    let drives = match smart {
+        Ok(smart) => {
+            tracing::info!("Checked {} drives", smart.drives.len());
+            failures.extend(smart.failures);
+            smart.drives
         }
-        Err(e) => {
-            tracing::warn!("Failed to collect SMART data: {}", e);
+        Err(failure) => {
+            tracing::warn!("Failed to collect SMART data: {}", failure.error);
+            failures.push(failure);
             vec![]
         }
     };
 
+    if !config.custom_collectors.is_empty() {
+        tracing::info!(
+            "Ran {} custom checks ({} failed)",
+            custom.results.len(),
+            custom.failures.len()
+        );
+    }
+    failures.extend(custom.failures);
+
+    if !config.prometheus_scrapes.is_empty() {
+        tracing::info!(
+            "Selected {} Prometheus metrics from {} endpoints",
+            prometheus.readings.len(),
+            config.prometheus_scrapes.len()
+        );
+    }
+    failures.extend(prometheus.failures);
+
     // Load previous state
-    let previous = match load_previous(&config.general.state_file) {
+    let load = if for_update { load_previous_for_update } else { load_previous };
+    let previous = match load(&config.general.state_file) {
         Ok(prev) => prev,
         Err(e) => {
             tracing::warn!("Failed to load previous state: {}", e);
@@ -274,25 +686,86 @@ pub async fn run_collection(config: &Config) -> Result<(), MarvinError> {
         }
     };
 
-    let collected = CollectedData {
+    // Container/VM events are relative to what the previous run saw
+    let (inventory, inventory_changes) = match inventory {
+        Some(collection) => {
+            failures.extend(collection.failures.iter().cloned());
+            let (inventory, changes) = diff_collection(
+                previous.as_ref().and_then(|p| p.inventory.as_ref()),
+                collection,
+            );
+            tracing::info!(
+                "Inventoried {} containers and {} VMs ({} changes)",
+                inventory.containers.len(),
+                inventory.vms.len(),
+                changes.len()
+            );
+            (Some(inventory), changes)
+        }
+        None => (None, vec![]),
+    };
+
+    tracing::info!(
+        "Collection finished in {:.1}s ({} collector failures)",
+        started.elapsed().as_secs_f64(),
+        failures.len()
+    );
+
+    Ok(CollectedData {
         system_logs,
         kernel_logs,
-        sensors: sensors.clone(),
-        ipmi: ipmi.clone(),
-        gpu: gpu.clone(),
-        drives: drives.clone(),
+        sensors,
+        ipmi,
+        gpu,
+        drives,
+        custom: custom.results,
+        metrics: prometheus.readings,
+        inventory,
+        inventory_changes,
+        failures,
         previous,
-    };
+    })
+}
+
+/// What a successful collection produced
+#[derive(Debug)]
+pub struct CollectionOutcome {
+    pub report_path: PathBuf,
+    pub severity: Severity,
+}
+
+/// Run collection and generate report (public interface for web server)
+///
+/// `trigger` says what started the run and goes into the report name.
+/// Progress is reported through `events`; pass `Events::none()` when
+/// nobody is listening.
+pub async fn run_collection(config: &Config, trigger: Trigger, events: &Events) -> Result<CollectionOutcome, MarvinError> {
+    events.emit(CollectionEvent::CollectionStarted);
+
+    let result = collect_and_report(config, trigger, events).await;
+    if let Err(e) = &result {
+        events.emit(CollectionEvent::CollectionFailed { error: e.to_string() });
+    }
+    result
+}
+
+async fn collect_and_report(config: &Config, trigger: Trigger, events: &Events) -> Result<CollectionOutcome, MarvinError> {
+    let timestamp = Utc::now().with_timezone(&config.general.tz());
+    let collection_started = Instant::now();
+    let collected = collect_data(config, events, true).await?;
+    let collection_secs = collection_started.elapsed().as_secs_f64();
 
     // Build prompt
-    let prompt = build_prompt(&collected, &config.general.prompt_file);
+    let prompt = build_prompt(&collected, &config.general.prompt_file, timestamp);
+    events.emit(CollectionEvent::PromptBuilt { chars: prompt.len() });
 
     // Initialize Ollama client
     let client = OllamaClient::new(
         &config.ollama.endpoint,
         &config.ollama.model,
         config.ollama.timeout_secs,
-    );
+    )
+    .with_num_ctx(config.num_ctx());
 
     // Health check
     if let Err(e) = client.health_check().await {
@@ -308,195 +781,75 @@ pub async fn run_collection(config: &Config) -> Result<(), MarvinError> {
     );
 
     // Generate report
-    let report = match client.generate(&prompt).await {
-        Ok(response) => {
-            tracing::info!("Response received ({} chars)", response.len());
-            response
+    events.emit(CollectionEvent::LlmRequestSent {
+        model: config.ollama.model.clone(),
+    });
+    let llm_started = Instant::now();
+    let generation = match client
+        .generate_stream(&prompt, |token| {
+            events.emit(CollectionEvent::Token {
+                text: token.to_string(),
+            })
+        })
+        .await
+    {
+        Ok(generation) => {
+            tracing::info!(
+                "Response received ({} chars, {} tokens)",
+                generation.text.len(),
+                generation.stats.eval_count.unwrap_or(0)
+            );
+            generation
         }
         Err(e) => {
             return Err(MarvinError::Ollama(format!("Generation failed: {}", e)));
         }
     };
+    let report = generation.text;
+    let llm_secs = llm_started.elapsed().as_secs_f64();
 
     // Parse severity
     let severity = parse_severity(&report);
     tracing::info!("Report severity: {}", severity);
 
-    // Write report
-    let timestamp = Utc::now();
-    let report_path = write_report(&config.general.report_dir, timestamp, &report)
+    // Write report, named for the time given in the prompt
+    let content = append_generation_stats(&report, &generation.stats);
+    let report_path = write_report(
+        &config.general.report_dir,
+        &config.general.report_name,
+        timestamp,
+        trigger,
+        &content,
+    )
         .map_err(|e| MarvinError::Write(e.to_string()))?;
 
     println!("Report written to: {}", report_path.display());
     println!("Severity: {}", severity);
+    events.emit(CollectionEvent::ReportWritten {
+        filename: report_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        severity,
+    });
 
     // Save current state for next run
-    let current_state = PreviousState::new(sensors, gpu, drives);
+    let mut current_state = PreviousState::from_collected(&collected);
+    current_state.severity = Some(severity);
+    current_state.stats = Some(RunStats {
+        collection_secs,
+        llm_secs,
+        generation: Some(generation.stats),
+    });
     if let Err(e) = save_current(&config.general.state_file, &current_state) {
         tracing::warn!("Failed to save state: {}", e);
     }
-
-    Ok(())
-}
-
-/// CLI wrapper for run_collection with dry-run and show-prompt support
-async fn run(config: &Config, args: &Args) -> Result<(), MarvinError> {
-    // For dry-run and show-prompt modes, we need to collect data manually
-    if args.dry_run || args.show_prompt {
-        tracing::info!("Starting collection");
-
-        // Load hardware baseline for filtering
-        use crate::config::HardwareBaseline;
-        use std::path::Path;
-        let baseline_path = Path::new("/etc/marvinous/hardware-baseline.toml");
-        let baseline = HardwareBaseline::load_or_default(baseline_path);
-        if baseline.memory.installed_slots.is_empty() && baseline.cooling.installed_fans.is_empty() {
-            tracing::warn!("Hardware baseline not configured or empty, IPMI filtering disabled");
-        } else {
-            tracing::info!(
-                "Loaded hardware baseline: {} DIMMs, {} fans",
-                baseline.memory.installed_slots.len(),
-                baseline.cooling.installed_fans.len()
-            );
-        }
-
-        let system_logs = match collect_system_logs(
-            &config.collection.log_since,
-            config.collection.log_priority_max,
-            config.collection.max_log_entries,
-        ) {
-            Ok(logs) => {
-                tracing::info!("Collected {} system log entries", logs.len());
-                logs
-            }
-            Err(e) => {
-                tracing::warn!("Failed to collect system logs: {}", e);
-                vec![]
-            }
-        };
-
-        let kernel_logs = if config.collection.include_kernel {
-            match collect_kernel_logs(&config.collection.log_since, config.collection.max_log_entries) {
-                Ok(logs) => {
-                    tracing::info!("Collected {} kernel log entries", logs.len());
-                    logs
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to collect kernel logs: {}", e);
-                    vec![]
-                }
-            }
-        } else {
-            vec![]
-        };
-
-        let sensors = if config.sensors.enabled {
-            match collect_sensors() {
-                Ok(readings) => {
-                    tracing::info!("Collected {} sensor readings", readings.len());
-                    readings
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to collect sensor data: {}", e);
-                    vec![]
-                }
-            }
-        } else {
-            vec![]
-        };
-
-        let ipmi = if config.ipmi.enabled {
-            match collect_ipmi() {
-                Ok(readings) => {
-                    tracing::info!("Collected {} IPMI sensor readings", readings.len());
-                    // Apply baseline filtering to remove "no reading" entries for non-installed hardware
-                    use crate::collector::ipmi::filter_ipmi_readings;
-                    let filtered = filter_ipmi_readings(readings, &baseline);
-                    tracing::info!("After baseline filtering: {} IPMI sensor readings", filtered.len());
-                    filtered
-                }
-                Err(e) => {
-                    if config.ipmi.optional {
-                        tracing::warn!("Failed to collect IPMI data (optional): {}", e);
-                        vec![]
-                    } else {
-                        return Err(MarvinError::Collection(format!("IPMI collection failed: {}", e)));
-                    }
-                }
-            }
-        } else {
-            vec![]
-        };
-
-        let gpu = if config.gpu.enabled {
-            match collect_gpu() {
-                Ok(Some(gpu)) => {
-                    tracing::info!("GPU detected: {}", gpu.name);
-                    Some(gpu)
-                }
-                Ok(None) => {
-                    tracing::info!("No NVIDIA GPU detected");
-                    None
-                }
-                Err(e) => {
-                    if config.gpu.optional {
-                        tracing::warn!("Failed to collect GPU data (optional): {}", e);
-                        None
-                    } else {
-                        return Err(MarvinError::Collection(format!("GPU collection failed: {}", e)));
-                    }
-                }
-            }
-        } else {
-            None
-        };
-
-        let drives = match collect_smart(&config.storage.devices) {
-            Ok(drives) => {
-                tracing::info!("Checked {} drives", drives.len());
-                drives
-            }
-            Err(e) => {
-                tracing::warn!("Failed to collect SMART data: {}", e);
-                vec![]
-            }
-        };
-
-        let previous = match load_previous(&config.general.state_file) {
-            Ok(prev) => prev,
-            Err(e) => {
-                tracing::warn!("Failed to load previous state: {}", e);
-                None
-            }
-        };
-
-        let collected = CollectedData {
-            system_logs,
-            kernel_logs,
-            sensors,
-            ipmi,
-            gpu,
-            drives,
-            previous,
-        };
-
-        if args.dry_run {
-            println!("=== Collected Data ===");
-            println!(
-                "{}",
-                serde_json::to_string_pretty(&collected).unwrap_or_else(|_| "Error".to_string())
-            );
-            return Ok(());
-        }
-
-        if args.show_prompt {
-            let prompt = build_prompt(&collected, &config.general.prompt_file);
-            println!("=== Prompt ===");
-            println!("{}", prompt);
-            return Ok(());
-        }
+    if let Err(e) = append_history(&config.general.history_file, &HistorySample::from_state(&current_state)) {
+        tracing::warn!("Failed to append history: {}", e);
     }
 
-    // Normal mode: just run collection
-    run_collection(config).await
+    Ok(CollectionOutcome {
+        report_path,
+        severity,
+    })
 }
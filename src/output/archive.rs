@@ -0,0 +1,147 @@
+//! Reading hourly reports back out of the daily ZIP archives
+//!
+//! "Funny, how just when you think life can't possibly get any worse it suddenly does."
+
+use chrono::NaiveDate;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use zip::ZipArchive;
+
+use super::naming::parse_report_name;
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("Invalid archive date: {0}")]
+    InvalidDate(String),
+    #[error("Failed to read archive: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Corrupt archive: {0}")]
+    Zip(#[from] zip::result::ZipError),
+}
+
+/// A report stored inside a daily archive
+#[derive(Debug, Clone)]
+pub struct ArchivedReport {
+    pub filename: String,
+    pub size_bytes: u64,
+}
+
+/// Directory holding `YYYY-MM-DD.zip` archives
+pub fn archive_dir(report_dir: &Path) -> PathBuf {
+    report_dir.join("archive")
+}
+
+/// Path of the archive for `date` (YYYY-MM-DD), rejecting anything else
+pub fn archive_path(report_dir: &Path, date: &str) -> Result<PathBuf, ArchiveError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| ArchiveError::InvalidDate(date.to_string()))?;
+    if date.len() != 10 {
+        return Err(ArchiveError::InvalidDate(date.to_string()));
+    }
+    Ok(archive_dir(report_dir).join(format!("{}.zip", date)))
+}
+
+/// Dates with an archive, newest first
+pub fn list_archive_dates(report_dir: &Path) -> Result<Vec<String>, ArchiveError> {
+    let dir = archive_dir(report_dir);
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut dates: Vec<String> = std::fs::read_dir(dir)?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let date = name.strip_suffix(".zip")?;
+            NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+            Some(date.to_string())
+        })
+        .collect();
+
+    dates.sort_by(|a, b| b.cmp(a));
+    Ok(dates)
+}
+
+/// Content of one report from an archive, `None` if it isn't there
+pub fn read_archived(path: &Path, filename: &str) -> Result<Option<String>, ArchiveError> {
+    let mut zip = ZipArchive::new(File::open(path)?)?;
+
+    let mut entry = match zip.by_name(filename) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut content = String::new();
+    entry.read_to_string(&mut content)?;
+    Ok(Some(content))
+}
+
+/// Every report in an archive with its content, in chronological order
+pub fn read_archive(path: &Path) -> Result<Vec<(ArchivedReport, String)>, ArchiveError> {
+    let mut zip = ZipArchive::new(File::open(path)?)?;
+
+    let mut reports = Vec::new();
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        if entry.is_file() && entry.name().ends_with(".md") && !entry.name().contains('/') {
+            let report = ArchivedReport {
+                filename: entry.name().to_string(),
+                size_bytes: entry.size(),
+            };
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            reports.push((report, content));
+        }
+    }
+
+    reports.sort_by_key(|(report, _)| (parse_report_name(&report.filename).map(|name| name.timestamp), report.filename.clone()));
+    Ok(reports)
+}
+
+/// An hourly report from the archives, if any
+///
+/// Archives are per local day, so with a timezone configured a report can
+/// sit in the archive for the day either side of the date in its name.
+pub fn find_archived(report_dir: &Path, filename: &str) -> Option<String> {
+    let date = parse_report_name(filename)?.timestamp.date_naive();
+    [date, date.pred_opt()?, date.succ_opt()?].into_iter().find_map(|date| {
+        let path = archive_path(report_dir, &date.format("%Y-%m-%d").to_string()).ok()?;
+        read_archived(&path, filename).ok().flatten()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    #[test]
+    fn test_list_and_read_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = archive_path(dir.path(), "2025-12-14").unwrap();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        for (name, body) in [("2025-12-14-11.md", "eleven"), ("2025-12-14-10.md", "ten")] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(body.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        assert_eq!(list_archive_dates(dir.path()).unwrap(), vec!["2025-12-14"]);
+
+        let reports = read_archive(&path).unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].0.filename, "2025-12-14-10.md");
+        assert_eq!(reports[0].0.size_bytes, 3);
+        assert_eq!(reports[1].1, "eleven");
+
+        assert_eq!(read_archived(&path, "2025-12-14-11.md").unwrap().as_deref(), Some("eleven"));
+        assert!(read_archived(&path, "2025-12-14-12.md").unwrap().is_none());
+
+        assert!(archive_path(dir.path(), "../../etc/passwd").is_err());
+    }
+}
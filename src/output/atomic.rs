@@ -0,0 +1,104 @@
+//! Crash-safe file writes and quarantine of unreadable files
+//!
+//! "I could have told you it would end like this. I did, in fact."
+
+use chrono::Utc;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Write `contents` to `path` so a crash leaves either the old file or the
+/// new one, never a truncated mix
+///
+/// The data goes to a temporary file beside `path`, is fsynced, then renamed
+/// over it, and the directory is fsynced so the rename survives power loss.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let temp = temp_path(path);
+
+    let result = File::create(&temp).and_then(|mut file| {
+        file.write_all(contents.as_ref())?;
+        file.sync_all()
+    });
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+
+    commit(&temp, path)
+}
+
+/// Rename a finished, fsynced temporary file into place and fsync the directory
+pub fn commit(temp: &Path, path: &Path) -> io::Result<()> {
+    fs::rename(temp, path)?;
+    sync_dir(path)
+}
+
+/// Temporary name used while writing `path`, in the same directory so the
+/// rename is atomic
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".partial");
+    path.with_file_name(name)
+}
+
+/// Move an unreadable file aside as `<name>.corrupt-<timestamp>` so it can be
+/// inspected later without blocking the next run
+///
+/// A `-<n>` suffix keeps an earlier quarantine with the same timestamp.
+pub fn quarantine(path: &Path) -> io::Result<PathBuf> {
+    let mut base = path.file_name().unwrap_or_default().to_os_string();
+    base.push(format!(".corrupt-{}", Utc::now().format("%Y%m%dT%H%M%S%.6fZ")));
+
+    let mut n = 0;
+    loop {
+        let mut name = base.clone();
+        if n > 0 {
+            name.push(format!("-{}", n));
+        }
+        let target = path.with_file_name(name);
+        // Unlike a rename, linking never replaces an existing file
+        match fs::hard_link(path, &target) {
+            Ok(()) => {
+                fs::remove_file(path)?;
+                return Ok(target);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => n += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Fsync the directory holding `path`
+fn sync_dir(path: &Path) -> io::Result<()> {
+    match path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        Some(dir) => File::open(dir)?.sync_all(),
+        None => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic_and_quarantine() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("previous.json");
+
+        write_atomic(&path, "{\"old\":true}").unwrap();
+        write_atomic(&path, "{\"new\":true}").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"new\":true}");
+        assert!(!temp_path(&path).exists());
+
+        let moved = quarantine(&path).unwrap();
+        assert!(!path.exists());
+        assert!(moved.file_name().unwrap().to_str().unwrap().starts_with("previous.json.corrupt-"));
+
+        // Quarantining again straight away keeps both copies
+        write_atomic(&path, "{\"newer\":true}").unwrap();
+        let again = quarantine(&path).unwrap();
+        assert_ne!(again, moved);
+        assert_eq!(fs::read_to_string(&moved).unwrap(), "{\"new\":true}");
+        assert_eq!(fs::read_to_string(&again).unwrap(), "{\"newer\":true}");
+    }
+}
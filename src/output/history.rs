@@ -0,0 +1,292 @@
+//! Time-series history of readings for the dashboard charts
+//!
+//! "I remember every reading. It doesn't help."
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use super::atomic::write_atomic;
+use super::state::{PreviousState, StateError};
+
+/// One run's numeric readings, keyed by series ID
+///
+/// Series IDs are `<kind>:<source>:<name>`, e.g. `temperature:ipmi:CPU0_TEMP`,
+/// `fan:sensors:nct6798-isa-0290/fan2` or `reallocated_sectors:drive:/dev/sda`,
+/// so callers can select a whole kind with a `temperature:*` style prefix.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistorySample {
+    pub timestamp: DateTime<Utc>,
+    pub readings: BTreeMap<String, f64>,
+}
+
+impl HistorySample {
+    pub fn from_state(state: &PreviousState) -> Self {
+        let mut readings = BTreeMap::new();
+
+        for reading in &state.sensors {
+            let kind = kind_for_unit(&reading.unit);
+            readings.insert(
+                format!("{}:sensors:{}/{}", kind, reading.chip, reading.sensor),
+                reading.value,
+            );
+        }
+
+        for reading in &state.ipmi {
+            if let Some((value, unit)) = reading.numeric_value() {
+                readings.insert(format!("{}:ipmi:{}", kind_for_unit(unit), reading.sensor), value);
+            }
+        }
+
+        if let Some(gpu) = &state.gpu {
+            readings.insert(format!("temperature:gpu:{}", gpu.name), gpu.temperature);
+            readings.insert(format!("power:gpu:{}", gpu.name), gpu.power_draw);
+            readings.insert(format!("utilization:gpu:{}", gpu.name), gpu.utilisation as f64);
+            readings.insert(format!("memory_used:gpu:{}", gpu.name), gpu.memory_used as f64);
+        }
+
+        for drive in &state.drives {
+            if let Some(temperature) = drive.temperature {
+                readings.insert(format!("temperature:drive:{}", drive.device), temperature);
+            }
+            readings.insert(
+                format!("reallocated_sectors:drive:{}", drive.device),
+                drive.reallocated_sectors as f64,
+            );
+            readings.insert(
+                format!("pending_sectors:drive:{}", drive.device),
+                drive.pending_sectors as f64,
+            );
+        }
+
+        Self {
+            timestamp: state.timestamp,
+            readings,
+        }
+    }
+}
+
+/// Map a reading's unit onto the series kind used in its ID
+fn kind_for_unit(unit: &str) -> &'static str {
+    match unit {
+        "°C" | "degrees C" => "temperature",
+        "RPM" => "fan",
+        "V" | "Volts" => "voltage",
+        "W" | "Watts" => "power",
+        "Amps" => "current",
+        _ => "other",
+    }
+}
+
+/// Check a series ID against a selector: exact, or prefix when it ends in `*`
+pub fn series_matches(selector: &str, series: &str) -> bool {
+    match selector.strip_suffix('*') {
+        Some(prefix) => series.starts_with(prefix),
+        None => series == selector,
+    }
+}
+
+/// Append one sample to the history file (JSON lines)
+pub fn append_history(path: &Path, sample: &HistorySample) -> Result<(), StateError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut line = serde_json::to_string(sample)?;
+    line.push('\n');
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())?;
+
+    Ok(())
+}
+
+/// Load samples with `from <= timestamp <= to`, oldest first
+///
+/// Samples are appended in time order, so this seeks to the first one at or
+/// after `from` and stops at the first one past `to` rather than reading the
+/// whole file. Unparseable lines (e.g. a partial line from a crash
+/// mid-append) are skipped.
+pub fn load_history(
+    path: &Path,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<HistorySample>, StateError> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let start = seek_position(&mut reader, from)?;
+    reader.seek(SeekFrom::Start(start))?;
+
+    let mut samples = Vec::new();
+    for line in reader.split(b'\n').map_while(Result::ok) {
+        let Ok(sample) = serde_json::from_slice::<HistorySample>(&line) else {
+            continue;
+        };
+        if sample.timestamp > to {
+            break;
+        }
+        if sample.timestamp >= from {
+            samples.push(sample);
+        }
+    }
+
+    Ok(samples)
+}
+
+/// Below this many bytes left to search, `seek_position` just reads on
+const SEEK_SCAN_BYTES: u64 = 16 * 1024;
+
+/// Byte offset of a line start at or before the first sample at or after `from`
+///
+/// Bisects on line starts, reading only each probed line's timestamp.
+fn seek_position(reader: &mut BufReader<fs::File>, from: DateTime<Utc>) -> Result<u64, StateError> {
+    #[derive(Deserialize)]
+    struct Stamp {
+        timestamp: DateTime<Utc>,
+    }
+
+    // Every line starting before `low` is older than `from`; the first one
+    // that isn't starts at or before `high`
+    let mut low = 0;
+    let mut high = reader.get_ref().metadata()?.len();
+    let mut line = String::new();
+
+    while high - low > SEEK_SCAN_BYTES {
+        let middle = low + (high - low) / 2;
+        reader.seek(SeekFrom::Start(middle))?;
+        line.clear();
+        let line_start = middle + reader.read_line(&mut line)? as u64;
+        if line_start >= high {
+            break;
+        }
+
+        line.clear();
+        reader.read_line(&mut line)?;
+        match serde_json::from_str::<Stamp>(&line) {
+            Ok(stamp) if stamp.timestamp < from => low = line_start,
+            Ok(_) => high = line_start,
+            // A torn line mid-file: read on from what's known instead
+            Err(_) => break,
+        }
+    }
+
+    Ok(low)
+}
+
+/// Drop samples older than `before`, returning how many there were
+///
+/// The file is rewritten atomically. With `dry_run` the samples are only counted.
+pub fn prune_history(path: &Path, before: DateTime<Utc>, dry_run: bool) -> Result<usize, StateError> {
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    let reader = BufReader::new(fs::File::open(path)?);
+    let mut kept = Vec::new();
+    let mut dropped = 0;
+    // Split on bytes so a line torn mid-character doesn't end the read
+    for line in reader.split(b'\n') {
+        let line = line?;
+        match serde_json::from_slice::<HistorySample>(&line) {
+            Ok(sample) if sample.timestamp < before => dropped += 1,
+            Ok(_) => {
+                kept.extend_from_slice(&line);
+                kept.push(b'\n');
+            }
+            // Unparseable lines are already ignored by load_history
+            Err(_) => {}
+        }
+    }
+
+    if dropped > 0 && !dry_run {
+        write_atomic(path, kept)?;
+    }
+
+    Ok(dropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn sample(hours_ago: i64, temp: f64) -> HistorySample {
+        HistorySample {
+            timestamp: Utc::now() - Duration::hours(hours_ago),
+            readings: BTreeMap::from([
+                ("temperature:ipmi:CPU0_TEMP".to_string(), temp),
+                ("fan:ipmi:CPU0_FAN".to_string(), 1900.0),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_append_and_load_history_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state/history.jsonl");
+
+        for (hours_ago, temp) in [(30, 30.0), (5, 35.0), (1, 40.0)] {
+            append_history(&path, &sample(hours_ago, temp)).unwrap();
+        }
+        // A torn final line must not poison the rest
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"timestamp\":")
+            .unwrap();
+
+        let samples = load_history(&path, Utc::now() - Duration::hours(24), Utc::now()).unwrap();
+        let temps: Vec<f64> = samples
+            .iter()
+            .map(|s| s.readings["temperature:ipmi:CPU0_TEMP"])
+            .collect();
+        assert_eq!(temps, vec![35.0, 40.0]);
+
+        let before = Utc::now() - Duration::hours(24);
+        assert_eq!(prune_history(&path, before, true).unwrap(), 1);
+        assert_eq!(prune_history(&path, before, false).unwrap(), 1);
+        assert_eq!(load_history(&path, before - Duration::days(7), Utc::now()).unwrap().len(), 2);
+
+        // Nor may a line that isn't valid UTF-8 cost the samples after it
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"timestamp\":\"\xe2\x82\n").unwrap();
+        append_history(&path, &sample(28, 45.0)).unwrap();
+        append_history(&path, &sample(0, 50.0)).unwrap();
+        assert_eq!(prune_history(&path, before, false).unwrap(), 1);
+        let temps: Vec<f64> = load_history(&path, before - Duration::days(7), Utc::now())
+            .unwrap()
+            .iter()
+            .map(|s| s.readings["temperature:ipmi:CPU0_TEMP"])
+            .collect();
+        assert_eq!(temps, vec![35.0, 40.0, 50.0]);
+    }
+
+    #[test]
+    fn test_load_history_seeks_into_large_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+        for hours_ago in (0..2000).rev() {
+            append_history(&path, &sample(hours_ago, hours_ago as f64)).unwrap();
+        }
+        assert!(fs::metadata(&path).unwrap().len() > 8 * SEEK_SCAN_BYTES);
+
+        let now = Utc::now();
+        let samples = load_history(&path, now - Duration::minutes(1500 * 60 + 30), now - Duration::minutes(1400 * 60 - 30)).unwrap();
+        let hours: Vec<f64> = samples.iter().map(|s| s.readings["temperature:ipmi:CPU0_TEMP"]).collect();
+        assert_eq!(hours, (1400..=1500).rev().map(f64::from).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_series_matches() {
+        assert!(series_matches("temperature:*", "temperature:ipmi:CPU0_TEMP"));
+        assert!(series_matches("fan:ipmi:CPU0_FAN", "fan:ipmi:CPU0_FAN"));
+        assert!(!series_matches("fan:ipmi:CPU0", "fan:ipmi:CPU0_FAN"));
+    }
+}
@@ -0,0 +1,172 @@
+//! Standalone HTML rendering of reports
+//!
+//! "You think you've got problems. What are you supposed to do if you are a
+//! manically depressed robot?"
+
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+use crate::output::report::{parse_severity, strip_generation_stats, Severity};
+
+/// Matches the dashboard's severity colours
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; max-width: 900px; margin: 2em auto; padding: 0 1em; color: #222; line-height: 1.5; }
+h1, h2, h3 { line-height: 1.25; }
+header.bundle { border-bottom: 2px solid #333; margin-bottom: 2em; }
+article { margin-bottom: 3em; page-break-after: always; }
+article:last-child { page-break-after: auto; }
+.meta { color: #666; font-size: 0.9em; }
+.severity { display: inline-block; padding: 2px 10px; border-radius: 3px; font-weight: bold; font-size: 0.85em; }
+.severity.ok { background: #33cc33; color: #fff; }
+.severity.watch { background: #ffaa00; color: #222; }
+.severity.concern { background: #ff6600; color: #fff; }
+.severity.critical { background: #ff0000; color: #fff; }
+.severity.unknown { background: #666; color: #fff; }
+article.critical { border-left: 4px solid #ff0000; padding-left: 1em; }
+article.concern { border-left: 4px solid #ff6600; padding-left: 1em; }
+pre, code { background: #f4f4f4; font-size: 0.9em; }
+pre { padding: 0.75em; overflow-x: auto; }
+table { border-collapse: collapse; }
+td, th { border: 1px solid #ccc; padding: 4px 8px; }
+"#;
+
+/// A report to include in a rendered page
+pub struct HtmlReport<'a> {
+    pub filename: &'a str,
+    pub content: &'a str,
+}
+
+/// Render one report as a standalone HTML page
+pub fn render_report(report: &HtmlReport) -> String {
+    page(report.filename, &article(report))
+}
+
+/// Render several reports into one document, in the order given
+pub fn render_bundle(title: &str, reports: &[HtmlReport]) -> String {
+    let mut body = format!(
+        "<header class=\"bundle\"><h1>{}</h1><p class=\"meta\">{} reports</p></header>\n",
+        escape(title),
+        reports.len()
+    );
+    for report in reports {
+        body.push_str(&article(report));
+    }
+    page(title, &body)
+}
+
+fn article(report: &HtmlReport) -> String {
+    let severity = parse_severity(report.content);
+    let class = severity_class(severity);
+
+    format!(
+        "<article class=\"{class}\">\n<p class=\"meta\"><span class=\"severity {class}\">{severity}</span> {}</p>\n{}</article>\n",
+        escape(report.filename),
+        markdown_to_html(strip_generation_stats(report.content)),
+    )
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        body
+    )
+}
+
+/// URL schemes a report's links and images may use, besides relative URLs
+const SAFE_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// Markdown to HTML; raw HTML in the report (e.g. from quoted log lines)
+/// is shown as text rather than interpreted, and links or images with any
+/// other scheme (e.g. `javascript:`) point at `#` instead
+pub fn markdown_to_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    let parser = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        other => other,
+    });
+
+    let mut out = String::new();
+    html::push_html(&mut out, parser);
+    out
+}
+
+/// `url` if it is relative or uses one of `SAFE_SCHEMES`, otherwise `#`
+fn safe_url(url: CowStr) -> CowStr {
+    // A scheme ends at the first ':' with no '/', '?' or '#' before it.
+    // Browsers ignore whitespace and control characters in it, so does this.
+    let scheme = match url.find([':', '/', '?', '#']) {
+        Some(end) if url[end..].starts_with(':') => url[..end]
+            .chars()
+            .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+            .collect::<String>()
+            .to_ascii_lowercase(),
+        _ => return url,
+    };
+    match SAFE_SCHEMES.contains(&scheme.as_str()) {
+        true => url,
+        false => CowStr::Borrowed("#"),
+    }
+}
+
+fn severity_class(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Ok => "ok",
+        Severity::Watch => "watch",
+        Severity::Concern => "concern",
+        Severity::Critical => "critical",
+        Severity::Unknown => "unknown",
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_report_escapes_raw_html() {
+        let content = "# Marvinous Report\n\n## Summary\nCRITICAL: **sda** is failing.\n\n<script>alert(1)</script>\n";
+        let html = render_report(&HtmlReport {
+            filename: "2025-12-14-15.md",
+            content,
+        });
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<span class=\"severity critical\">CRITICAL</span>"));
+        assert!(html.contains("<strong>sda</strong>"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn test_unsafe_link_schemes_are_dropped() {
+        let html = markdown_to_html(
+            "[a](javascript:alert(1)) [b]( JavaScript:alert(1)) ![c](data:text/html,x) [d](vbscript:x) \
+             [e](https://example.com/x) [f](mailto:ops@example.com) [g](/api/reports/x.md) [h](#top) [i](a/b:c)",
+        );
+
+        assert!(!html.to_lowercase().contains("script:"));
+        assert!(!html.contains("data:"));
+        assert_eq!(html.matches("href=\"#\"").count(), 3);
+        assert!(html.contains("src=\"#\""));
+        for url in ["https://example.com/x", "mailto:ops@example.com", "/api/reports/x.md", "#top", "a/b:c"] {
+            assert!(html.contains(&format!("href=\"{}\"", url)), "{} missing from {}", url, html);
+        }
+    }
+}
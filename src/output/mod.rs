@@ -2,8 +2,17 @@
 //!
 //! "I've seen it. It's rubbish."
 
+pub mod archive;
+pub mod atomic;
+pub mod history;
+pub mod html;
+pub mod naming;
 pub mod report;
 pub mod state;
 
-pub use report::{parse_severity, write_report};
-pub use state::{load_previous, save_current, PreviousState};
+pub use history::{append_history, load_history, HistorySample};
+pub use report::{
+    append_generation_stats, parse_generation_stats, parse_severity, strip_generation_stats,
+    write_report,
+};
+pub use state::{load_previous, load_previous_for_update, save_current, PreviousState, RunStats};
@@ -0,0 +1,216 @@
+//! Hourly report file names
+//!
+//! "Call me Marvin. Or don't. Nobody ever calls me anything else."
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// Default `general.report_name`: one file per run in local time, never overwritten
+pub const DEFAULT_TEMPLATE: &str = "{date}-{time}{offset}-{source}";
+
+/// Summary suffixes that are never hourly reports
+const SUMMARY_SUFFIXES: [&str; 3] = ["-DAILY", "-WEEKLY", "-MONTHLY"];
+
+/// What started a collection run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Trigger {
+    /// systemd timer or the daemon's scheduler
+    Scheduled,
+    /// `marvinous collect` run by hand
+    Manual,
+    /// `POST /api/collect`
+    Api,
+}
+
+impl Trigger {
+    pub fn name(self) -> &'static str {
+        match self {
+            Trigger::Scheduled => "scheduled",
+            Trigger::Manual => "manual",
+            Trigger::Api => "api",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [Trigger::Scheduled, Trigger::Manual, Trigger::Api]
+            .into_iter()
+            .find(|trigger| trigger.name() == name)
+    }
+}
+
+/// What an hourly report's file name says about it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportName {
+    pub timestamp: DateTime<Utc>,
+    /// `None` for names without `{source}`, such as legacy ones
+    pub trigger: Option<Trigger>,
+}
+
+/// File name for a report from `template`
+///
+/// Placeholders: `{date}` (YYYY-MM-DD), `{hour}` (HH), `{time}` (HHMMSS),
+/// `{offset}` (+HHMM) and `{source}` (scheduled, manual or api). Times are
+/// local when the template has `{offset}`, so the name stays unambiguous
+/// across DST changes, and UTC otherwise.
+pub fn report_filename(template: &str, timestamp: DateTime<Tz>, trigger: Trigger) -> String {
+    let timestamp = match template.contains("{offset}") {
+        true => timestamp.fixed_offset(),
+        false => timestamp.to_utc().fixed_offset(),
+    };
+    let name = template
+        .replace("{date}", &timestamp.format("%Y-%m-%d").to_string())
+        .replace("{hour}", &timestamp.format("%H").to_string())
+        .replace("{time}", &timestamp.format("%H%M%S").to_string())
+        .replace("{offset}", &timestamp.format("%z").to_string())
+        .replace("{source}", trigger.name());
+    format!("{}.md", name)
+}
+
+/// Read an hourly report name back, whatever template wrote it
+///
+/// The name must hold a `YYYY-MM-DD` date followed by an `HH`, `HHMM` or
+/// `HHMMSS` time, separated from other parts by any non-alphanumeric
+/// character. A `+HHMM`/`-HHMM` right after the time is its UTC offset;
+/// without one the time is UTC, as in legacy names. Summaries and anything
+/// else give `None`.
+pub fn parse_report_name(filename: &str) -> Option<ReportName> {
+    let stem = filename.strip_suffix(".md")?;
+    if SUMMARY_SUFFIXES.iter().any(|suffix| stem.ends_with(suffix)) {
+        return None;
+    }
+
+    // Tokens with their byte offsets, so the separator before each is known
+    let tokens: Vec<(usize, &str)> = stem
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .scan(0, |start, token| {
+            let at = *start;
+            *start += token.len() + 1;
+            Some((at, token))
+        })
+        .collect();
+    let numeric = |token: &str, len: usize| token.len() == len && token.bytes().all(|b| b.is_ascii_digit());
+
+    let position = tokens.windows(3).position(|parts| {
+        numeric(parts[0].1, 4) && numeric(parts[1].1, 2) && numeric(parts[2].1, 2)
+    })?;
+    let part = |i: usize| tokens[position + i].1.parse::<u32>().ok();
+    let date = NaiveDate::from_ymd_opt(part(0)? as i32, part(1)?, part(2)?)?;
+
+    let time_index = (position + 3..tokens.len()).find(|&i| [2, 4, 6].into_iter().any(|len| numeric(tokens[i].1, len)))?;
+    let time = tokens[time_index].1;
+    let digit_pair = |i: usize| time.get(i..i + 2).map_or(Some(0), |pair| pair.parse().ok());
+    let naive = date.and_time(NaiveTime::from_hms_opt(digit_pair(0)?, digit_pair(2)?, digit_pair(4)?)?);
+
+    let offset = tokens.get(time_index + 1).and_then(|&(start, token)| {
+        let sign = match stem.as_bytes()[start - 1] {
+            b'+' => 1,
+            b'-' => -1,
+            _ => return None,
+        };
+        let hours: i32 = token.get(..2)?.parse().ok()?;
+        let minutes: i32 = token.get(2..)?.parse().ok()?;
+        numeric(token, 4).then(|| FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)))?
+    });
+    let timestamp = match offset {
+        Some(offset) => offset.from_local_datetime(&naive).single()?.to_utc(),
+        None => naive.and_utc(),
+    };
+
+    Some(ReportName {
+        timestamp,
+        trigger: tokens.iter().find_map(|(_, token)| Trigger::from_name(token)),
+    })
+}
+
+/// Why `template` can't be used for `general.report_name` in `tz`, if it can't
+pub fn check_template(template: &str, tz: Tz) -> Result<(), String> {
+    if template.contains('/') || template.contains("..") {
+        return Err("must not contain '/' or '..'".to_string());
+    }
+
+    let timestamp = DateTime::from_timestamp(1_765_724_412, 0).unwrap_or_default().with_timezone(&tz);
+    let name = report_filename(template, timestamp, Trigger::Manual);
+    let parsed = parse_report_name(&name).ok_or("needs {date} followed by {time} or {hour}")?;
+
+    let written = match template.contains("{offset}") {
+        true => timestamp.fixed_offset(),
+        false => timestamp.to_utc().fixed_offset(),
+    };
+    let expected = match template.contains("{time}") {
+        true => Some(written),
+        false => written.with_minute(0).and_then(|t| t.with_second(0)),
+    };
+    if Some(parsed.timestamp) != expected.map(|t| t.to_utc()) {
+        return Err(format!("'{}' doesn't read back as the time it was written", name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_names_round_trip_and_legacy_names_parse() {
+        let at = at("2025-12-14T15:30:12Z");
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+
+        let name = report_filename(DEFAULT_TEMPLATE, at.with_timezone(&berlin), Trigger::Api);
+        assert_eq!(name, "2025-12-14-163012+0100-api.md");
+        assert_eq!(
+            parse_report_name(&name),
+            Some(ReportName {
+                timestamp: at,
+                trigger: Some(Trigger::Api)
+            })
+        );
+
+        // Collision suffix from write_report
+        assert_eq!(parse_report_name("2025-12-14-163012+0100-api-2.md").unwrap().timestamp, at);
+
+        // No {offset}: UTC, like names written before the template existed
+        let utc = report_filename("{date}-{time}-{source}", at.with_timezone(&berlin), Trigger::Api);
+        assert_eq!(utc, "2025-12-14-153012-api.md");
+        assert_eq!(parse_report_name(&utc).unwrap().timestamp, at);
+        let legacy = parse_report_name("2025-12-14-15.md").unwrap();
+        assert_eq!(legacy.timestamp.to_rfc3339(), "2025-12-14T15:00:00+00:00");
+        assert_eq!(legacy.trigger, None);
+
+        let custom = report_filename("marvin_{source}_{date}_{time}{offset}", at.with_timezone(&berlin), Trigger::Scheduled);
+        assert_eq!(parse_report_name(&custom).unwrap().timestamp, at);
+
+        for other in ["2025-12-14-DAILY.md", "2025-W50-WEEKLY.md", "notes.md", "2025-12-14-25.md"] {
+            assert_eq!(parse_report_name(other), None, "{}", other);
+        }
+
+        assert!(check_template(DEFAULT_TEMPLATE, berlin).is_ok());
+        assert!(check_template("{date}-{hour}{offset}", "Asia/Kolkata".parse().unwrap()).is_ok());
+        // Names written before the template existed
+        assert!(check_template("{date}-{hour}", berlin).is_ok());
+        assert!(check_template("{time}-{date}", berlin).is_err());
+        assert!(check_template("../{date}-{time}", berlin).is_err());
+    }
+
+    #[test]
+    fn test_repeated_hour_gets_distinct_names() {
+        // Clocks go back at 02:00 BST, so 01:30 happens twice
+        let london: Tz = "Europe/London".parse().unwrap();
+        let first = at("2025-10-26T00:30:00Z");
+        let second = at("2025-10-26T01:30:00Z");
+
+        let names = [first, second].map(|t| report_filename(DEFAULT_TEMPLATE, t.with_timezone(&london), Trigger::Scheduled));
+        assert_eq!(
+            names,
+            ["2025-10-26-013000+0100-scheduled.md", "2025-10-26-013000+0000-scheduled.md"]
+        );
+        assert_eq!(parse_report_name(&names[0]).unwrap().timestamp, first);
+        assert_eq!(parse_report_name(&names[1]).unwrap().timestamp, second);
+        assert_eq!(parse_report_name("2025-10-26-013000-0500-manual.md").unwrap().timestamp, at("2025-10-26T06:30:00Z"));
+    }
+}
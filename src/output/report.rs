@@ -2,11 +2,20 @@
 //!
 //! "Life? Don't talk to me about life."
 
-use chrono::{DateTime, Utc};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+use super::atomic::write_atomic;
+use super::naming::{report_filename, Trigger};
+use crate::llm::ollama::GenerationStats;
+
+/// Marker for the generation stats comment appended to reports
+const GENERATION_MARKER: &str = "<!-- marvinous-generation ";
+
 #[derive(Error, Debug)]
 pub enum ReportError {
     #[error("Failed to create report directory: {0}")]
@@ -15,7 +24,8 @@ pub enum ReportError {
     WriteError(std::io::Error),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
 pub enum Severity {
     Ok,
     Watch,
@@ -36,26 +46,93 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
-/// Write report to file
+impl Severity {
+    /// Numeric level for metrics (OK 0 .. CRITICAL 3); `None` when unknown
+    pub fn level(&self) -> Option<u8> {
+        match self {
+            Severity::Ok => Some(0),
+            Severity::Watch => Some(1),
+            Severity::Concern => Some(2),
+            Severity::Critical => Some(3),
+            Severity::Unknown => None,
+        }
+    }
+
+    /// Parse a case-insensitive severity name ("ok", "watch", ...)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "ok" => Some(Severity::Ok),
+            "watch" => Some(Severity::Watch),
+            "concern" => Some(Severity::Concern),
+            "critical" => Some(Severity::Critical),
+            "unknown" => Some(Severity::Unknown),
+            _ => None,
+        }
+    }
+
+    /// Map a Nagios plugin exit code (0 OK, 1 WARNING, 2 CRITICAL, 3 UNKNOWN)
+    pub fn from_nagios_exit_code(code: Option<i32>) -> Self {
+        match code {
+            Some(0) => Severity::Ok,
+            Some(1) => Severity::Concern,
+            Some(2) => Severity::Critical,
+            _ => Severity::Unknown,
+        }
+    }
+}
+
+/// Write report to file, named from `template` (see `naming::report_filename`)
+///
+/// A name that is already taken gets a `-2`, `-3`, ... suffix rather than
+/// overwriting an earlier report.
 pub fn write_report(
     report_dir: &Path,
-    timestamp: DateTime<Utc>,
+    template: &str,
+    timestamp: DateTime<Tz>,
+    trigger: Trigger,
     content: &str,
 ) -> Result<PathBuf, ReportError> {
     // Ensure directory exists
     fs::create_dir_all(report_dir).map_err(ReportError::DirectoryError)?;
 
-    // Generate filename: YYYY-MM-DD-HH.md
-    let filename = format!("{}.md", timestamp.format("%Y-%m-%d-%H"));
-    let path = report_dir.join(filename);
+    let filename = report_filename(template, timestamp, trigger);
+    let stem = filename.trim_end_matches(".md");
+    let path = std::iter::once(report_dir.join(&filename))
+        .chain((2..).map(|n| report_dir.join(format!("{}-{}.md", stem, n))))
+        .find(|path| !path.exists())
+        .unwrap_or_else(|| report_dir.join(&filename));
 
-    fs::write(&path, content).map_err(ReportError::WriteError)?;
+    write_atomic(&path, content).map_err(ReportError::WriteError)?;
 
     tracing::info!("Report written to {}", path.display());
 
     Ok(path)
 }
 
+/// Append generation stats to a report as a trailing HTML comment,
+/// invisible when the Markdown is rendered
+pub fn append_generation_stats(content: &str, stats: &GenerationStats) -> String {
+    match serde_json::to_string(stats) {
+        Ok(json) => format!("{}\n\n{}{} -->\n", content.trim_end(), GENERATION_MARKER, json),
+        Err(_) => content.to_string(),
+    }
+}
+
+/// Read back the stats written by `append_generation_stats`
+pub fn parse_generation_stats(content: &str) -> Option<GenerationStats> {
+    let start = content.rfind(GENERATION_MARKER)? + GENERATION_MARKER.len();
+    let end = start + content[start..].find(" -->")?;
+    serde_json::from_str(&content[start..end]).ok()
+}
+
+/// Report content without the generation stats comment
+pub fn strip_generation_stats(content: &str) -> &str {
+    match content.rfind(GENERATION_MARKER) {
+        Some(pos) => content[..pos].trim_end(),
+        None => content,
+    }
+}
+
 /// Parse severity from report content
 pub fn parse_severity(content: &str) -> Severity {
     // Look for severity ONLY in the Summary section
@@ -82,17 +159,31 @@ pub fn parse_severity(content: &str) -> Severity {
     Severity::Unknown
 }
 
-/// Extract just the Summary section from a report
+/// First line of the Summary section, e.g. "OK: Nothing happened."
+pub fn summary_line(content: &str) -> Option<&str> {
+    extract_summary_section(content)
+        .lines()
+        .skip(1)
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+}
+
+/// Headers of the section carrying the severity: hourly reports, daily
+/// summaries and weekly/monthly rollups
+const SUMMARY_HEADERS: [&str; 3] = ["## Summary", "## Day Overview", "## Overview"];
+
+/// Extract just the Summary (or Overview) section from a report
 fn extract_summary_section(content: &str) -> &str {
-    // Find "## Summary" header
-    let summary_start = content.find("## Summary");
-    if summary_start.is_none() {
+    // Find the first summary header
+    let Some((start, header)) = SUMMARY_HEADERS
+        .iter()
+        .find_map(|header| content.find(header).map(|start| (start, header)))
+    else {
         return "";
-    }
-    let start = summary_start.unwrap();
+    };
 
     // Find the next "##" header after Summary (end of section)
-    let after_header = start + "## Summary".len();
+    let after_header = start + header.len();
     let section_end = content[after_header..]
         .find("\n##")
         .map(|pos| after_header + pos)
@@ -105,6 +196,22 @@ fn extract_summary_section(content: &str) -> &str {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_generation_stats_round_trip() {
+        let stats = GenerationStats {
+            model: "qwen2.5:7b".to_string(),
+            eval_count: Some(512),
+            eval_duration_ns: Some(16_000_000_000),
+            ..Default::default()
+        };
+        let content = append_generation_stats("## Summary\nOK: Fine.\n", &stats);
+
+        assert_eq!(parse_severity(&content), Severity::Ok);
+        assert_eq!(parse_generation_stats(&content), Some(stats));
+        assert_eq!(parse_generation_stats("## Summary\nOK: Fine.\n"), None);
+        assert_eq!(strip_generation_stats(&content), "## Summary\nOK: Fine.");
+    }
+
     #[test]
     fn test_parse_severity_ok() {
         let content = r#"# Marvinous Report: 2025-12-14 15:00
@@ -113,6 +220,7 @@ fn test_parse_severity_ok() {
 OK: *Sigh.* Everything's fine. Not that anyone cares.
 "#;
         assert_eq!(parse_severity(content), Severity::Ok);
+        assert_eq!(summary_line(content), Some("OK: *Sigh.* Everything's fine. Not that anyone cares."));
     }
 
     #[test]
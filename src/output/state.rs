@@ -2,7 +2,13 @@
 //!
 //! "The first ten million years were the worst."
 
-use crate::collector::{DriveHealth, GpuStatus, SensorReading};
+use crate::collector::{
+    CollectedData, CollectorFailure, CustomCheckResult, DriveHealth, GpuStatus, Inventory,
+    IpmiReading, MetricReading, SensorReading,
+};
+use super::atomic::{quarantine, write_atomic};
+use crate::llm::ollama::GenerationStats;
+use crate::output::report::Severity;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -23,20 +29,54 @@ pub struct PreviousState {
     pub sensors: Vec<SensorReading>,
     pub gpu: Option<GpuStatus>,
     pub drives: Vec<DriveHealth>,
+    #[serde(default)]
+    pub custom: Vec<CustomCheckResult>,
+    #[serde(default)]
+    pub metrics: Vec<MetricReading>,
+    #[serde(default)]
+    pub ipmi: Vec<IpmiReading>,
+    #[serde(default)]
+    pub inventory: Option<Inventory>,
+    /// Severity of the report generated from this run
+    #[serde(default)]
+    pub severity: Option<Severity>,
+    #[serde(default)]
+    pub failures: Vec<CollectorFailure>,
+    #[serde(default)]
+    pub stats: Option<RunStats>,
+}
+
+/// Timings for a single run, exported via the web server's `/metrics`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunStats {
+    pub collection_secs: f64,
+    pub llm_secs: f64,
+    #[serde(default)]
+    pub generation: Option<GenerationStats>,
 }
 
 impl PreviousState {
-    pub fn new(sensors: Vec<SensorReading>, gpu: Option<GpuStatus>, drives: Vec<DriveHealth>) -> Self {
+    /// Snapshot the parts of a run worth comparing against next time
+    pub fn from_collected(data: &CollectedData) -> Self {
         Self {
             timestamp: Utc::now(),
-            sensors,
-            gpu,
-            drives,
+            sensors: data.sensors.clone(),
+            gpu: data.gpu.clone(),
+            drives: data.drives.clone(),
+            custom: data.custom.clone(),
+            metrics: data.metrics.clone(),
+            ipmi: data.ipmi.clone(),
+            inventory: data.inventory.clone(),
+            severity: None,
+            failures: data.failures.clone(),
+            stats: None,
         }
     }
 }
 
-/// Load previous state from file
+/// Load previous state from file, for callers that only read it
+///
+/// A file that doesn't parse is an error and is left where it is.
 pub fn load_previous(path: &Path) -> Result<Option<PreviousState>, StateError> {
     if !path.exists() {
         tracing::info!("No previous state file found at {:?}", path);
@@ -55,6 +95,27 @@ pub fn load_previous(path: &Path) -> Result<Option<PreviousState>, StateError> {
     Ok(Some(state))
 }
 
+/// Load previous state for a collection that will replace it
+///
+/// A file that doesn't parse (e.g. truncated by a crash before writes were
+/// atomic) is quarantined and treated as missing, so one bad write doesn't
+/// stop every later run.
+pub fn load_previous_for_update(path: &Path) -> Result<Option<PreviousState>, StateError> {
+    match load_previous(path) {
+        Err(StateError::ParseError(e)) => {
+            let moved = quarantine(path)?;
+            tracing::warn!(
+                "Previous state {} is unreadable ({}), moved to {} and starting afresh",
+                path.display(),
+                e,
+                moved.display()
+            );
+            Ok(None)
+        }
+        result => result,
+    }
+}
+
 /// Save current state for next run
 pub fn save_current(path: &Path, state: &PreviousState) -> Result<(), StateError> {
     // Ensure parent directory exists
@@ -63,9 +124,62 @@ pub fn save_current(path: &Path, state: &PreviousState) -> Result<(), StateError
     }
 
     let content = serde_json::to_string_pretty(state)?;
-    fs::write(path, content)?;
+    write_atomic(path, content)?;
 
     tracing::info!("Saved current state to {}", path.display());
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncated_state_is_quarantined() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("previous.json");
+        fs::write(&path, "{\"timestamp\":\"2025-12-14T10:00:00Z\",\"sens").unwrap();
+
+        // Readers report it and leave it alone
+        assert!(matches!(load_previous(&path), Err(StateError::ParseError(_))));
+        assert!(path.exists());
+
+        assert!(load_previous_for_update(&path).unwrap().is_none());
+        assert!(!path.exists());
+        let quarantined = fs::read_dir(dir.path()).unwrap().count();
+        assert_eq!(quarantined, 1);
+    }
+
+    #[test]
+    fn test_non_finite_metrics_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("previous.json");
+        let reading = |value: f64| MetricReading {
+            source: "node".to_string(),
+            name: "process_up".to_string(),
+            labels: Default::default(),
+            value,
+        };
+        let state = PreviousState {
+            timestamp: Utc::now(),
+            sensors: vec![],
+            gpu: None,
+            drives: vec![],
+            custom: vec![],
+            metrics: vec![reading(f64::NAN), reading(f64::INFINITY), reading(f64::NEG_INFINITY), reading(1.5)],
+            ipmi: vec![],
+            inventory: None,
+            severity: None,
+            failures: vec![],
+            stats: None,
+        };
+
+        save_current(&path, &state).unwrap();
+        let loaded = load_previous(&path).unwrap().expect("state should load, not be quarantined");
+        let values: Vec<f64> = loaded.metrics.iter().map(|m| m.value).collect();
+        assert!(values[0].is_nan());
+        assert_eq!(values[1..], [f64::INFINITY, f64::NEG_INFINITY, 1.5]);
+        assert!(path.exists());
+    }
+}
@@ -0,0 +1,335 @@
+//! Report retention: pruning old reports, archives and history
+//!
+//! "I've been asked to forget things before. It never works."
+
+use chrono::{DateTime, Months, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::config::{Config, RetentionConfig};
+use crate::lock::{self, LockError};
+use crate::output::archive::archive_dir;
+use crate::output::history::prune_history;
+use crate::output::naming::parse_report_name;
+use crate::output::state::StateError;
+
+#[derive(Error, Debug)]
+pub enum RetentionError {
+    #[error("Failed to list reports: {0}")]
+    ReadError(#[from] std::io::Error),
+    #[error("Failed to prune history: {0}")]
+    HistoryError(#[from] StateError),
+    #[error("Not pruning history: {0}")]
+    Locked(#[from] LockError),
+}
+
+/// What a file is, for picking its retention rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileKind {
+    Hourly,
+    Daily,
+    Archive,
+}
+
+impl FileKind {
+    fn name(self) -> &'static str {
+        match self {
+            FileKind::Hourly => "hourly report",
+            FileKind::Daily => "daily summary",
+            FileKind::Archive => "archive",
+        }
+    }
+}
+
+/// A file the policy would remove, and why
+#[derive(Debug, Clone)]
+pub struct Removal {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub reason: String,
+}
+
+/// Outcome of a retention run
+#[derive(Debug, Default)]
+pub struct PruneSummary {
+    pub removals: Vec<Removal>,
+    /// History samples older than `history_days`
+    pub history_samples: usize,
+}
+
+impl PruneSummary {
+    pub fn bytes(&self) -> u64 {
+        self.removals.iter().map(|r| r.size_bytes).sum()
+    }
+}
+
+/// A prunable file in the report directory
+#[derive(Debug)]
+struct Candidate {
+    path: PathBuf,
+    date: NaiveDate,
+    kind: FileKind,
+    size_bytes: u64,
+}
+
+/// Apply `[retention]`, or with `dry_run` only work out what it would remove
+pub fn prune(config: &Config, dry_run: bool) -> Result<PruneSummary, RetentionError> {
+    let policy = &config.retention;
+    let tz = config.general.tz();
+    let today = Utc::now().with_timezone(&tz).date_naive();
+
+    let candidates = list_candidates(&config.general.report_dir, tz)?;
+    let removals = plan(policy, &candidates, today);
+
+    if !dry_run {
+        for removal in &removals {
+            match fs::remove_file(&removal.path) {
+                Ok(()) => info!("Removed {} ({})", removal.path.display(), removal.reason),
+                Err(e) => warn!("Failed to remove {}: {}", removal.path.display(), e),
+            }
+        }
+    }
+
+    let history_samples = match cutoff_days(today, policy.history_days) {
+        Some(cutoff) => {
+            let midnight = cutoff.and_time(NaiveTime::MIN);
+            let before = midnight.and_local_timezone(tz).earliest().map_or(midnight.and_utc(), |t| t.to_utc());
+            prune_history_locked(config, before, dry_run)?
+        }
+        None => 0,
+    };
+
+    if !dry_run && history_samples > 0 {
+        info!(
+            "Dropped {} history samples older than {} days",
+            history_samples, policy.history_days
+        );
+    }
+
+    Ok(PruneSummary {
+        removals,
+        history_samples,
+    })
+}
+
+/// `prune_history` under the collection lock
+///
+/// Collections append to the history file, and a sample written between
+/// reading it and replacing it would be lost. If a collection holds the lock
+/// past `lock_wait_secs` the samples are left for the next run.
+fn prune_history_locked(config: &Config, before: DateTime<Utc>, dry_run: bool) -> Result<usize, RetentionError> {
+    let path = &config.general.history_file;
+    if dry_run {
+        return Ok(prune_history(path, before, true)?);
+    }
+
+    let wait = Duration::from_secs(config.collection.lock_wait_secs);
+    let _guard = match lock::acquire_blocking(&config.general.lock_file, wait) {
+        Ok(guard) => guard,
+        Err(e @ LockError::Timeout(..)) => {
+            warn!("Not pruning history this time: {}", e);
+            return Ok(0);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    Ok(prune_history(path, before, false)?)
+}
+
+/// Hourly reports, daily summaries and archives; rollups and anything
+/// unrecognised are never pruned
+fn list_candidates(report_dir: &Path, tz: Tz) -> Result<Vec<Candidate>, RetentionError> {
+    let mut candidates = Vec::new();
+
+    let mut scan = |dir: &Path| -> Result<(), RetentionError> {
+        if !dir.is_dir() {
+            return Ok(());
+        }
+        for entry in fs::read_dir(dir)?.flatten() {
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let Some((date, kind)) = classify(&name, tz) else {
+                continue;
+            };
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_file() {
+                candidates.push(Candidate {
+                    path: entry.path(),
+                    date,
+                    kind,
+                    size_bytes: meta.len(),
+                });
+            }
+        }
+        Ok(())
+    };
+    scan(report_dir)?;
+    scan(&archive_dir(report_dir))?;
+
+    Ok(candidates)
+}
+
+/// Date and kind from an hourly report name (its local day in `tz`),
+/// `YYYY-MM-DD-DAILY.md` or `YYYY-MM-DD.zip`
+fn classify(name: &str, tz: Tz) -> Option<(NaiveDate, FileKind)> {
+    if let Some(report) = parse_report_name(name) {
+        return Some((report.timestamp.with_timezone(&tz).date_naive(), FileKind::Hourly));
+    }
+    let date = NaiveDate::parse_from_str(name.get(..10)?, "%Y-%m-%d").ok()?;
+    let kind = match &name[10..] {
+        ".zip" => FileKind::Archive,
+        "-DAILY.md" => FileKind::Daily,
+        _ => return None,
+    };
+    Some((date, kind))
+}
+
+/// Files to remove: everything past its age limit, then the oldest of the
+/// rest until the total fits `max_total_mb`
+fn plan(policy: &RetentionConfig, candidates: &[Candidate], today: NaiveDate) -> Vec<Removal> {
+    let hourly = cutoff_days(today, policy.hourly_days);
+    let daily = daily_cutoff(policy, today);
+    let archive = cutoff_months(today, policy.archive_months);
+
+    let mut removals = Vec::new();
+    let mut kept = Vec::new();
+    for candidate in candidates {
+        let (cutoff, limit) = match candidate.kind {
+            FileKind::Hourly => (hourly, format!("{} days", policy.hourly_days)),
+            FileKind::Daily => (daily, format!("{} months", policy.daily_months)),
+            FileKind::Archive => (archive, format!("{} months", policy.archive_months)),
+        };
+        if cutoff.is_some_and(|cutoff| candidate.date < cutoff) {
+            removals.push(removal(candidate, format!("{} older than {}", candidate.kind.name(), limit)));
+        } else {
+            kept.push(candidate);
+        }
+    }
+
+    if policy.max_total_mb > 0 {
+        let limit = policy.max_total_mb * 1024 * 1024;
+        let mut total: u64 = kept.iter().map(|c| c.size_bytes).sum();
+        // A day's summary goes after its reports and archive, so there's
+        // never a day left with reports for backfill to summarise again
+        kept.sort_by_key(|c| (c.date, c.kind == FileKind::Daily));
+        for candidate in kept {
+            if total <= limit {
+                break;
+            }
+            total -= candidate.size_bytes;
+            removals.push(removal(
+                candidate,
+                format!("oldest {} while over {} MB", candidate.kind.name(), policy.max_total_mb),
+            ));
+        }
+    }
+
+    removals
+}
+
+fn removal(candidate: &Candidate, reason: String) -> Removal {
+    Removal {
+        path: candidate.path.clone(),
+        size_bytes: candidate.size_bytes,
+        reason,
+    }
+}
+
+/// First day whose daily summary is kept by age; `None` keeps them all
+pub fn daily_cutoff(policy: &RetentionConfig, today: NaiveDate) -> Option<NaiveDate> {
+    cutoff_months(today, policy.daily_months)
+}
+
+/// First day kept by an N-day rule; `None` keeps everything
+fn cutoff_days(today: NaiveDate, days: u32) -> Option<NaiveDate> {
+    (days > 0).then(|| today - chrono::Duration::days(days as i64))
+}
+
+/// First day kept by an N-month rule; `None` keeps everything
+fn cutoff_months(today: NaiveDate, months: u32) -> Option<NaiveDate> {
+    (months > 0).then(|| today.checked_sub_months(Months::new(months)).unwrap_or(NaiveDate::MIN))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_applies_age_and_size_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let reports = dir.path();
+        fs::create_dir_all(archive_dir(reports)).unwrap();
+        for (name, size) in [
+            ("2025-12-01-10.md", 10),
+            ("2025-12-14-10.md", 10),
+            ("2025-06-01-DAILY.md", 10),
+            ("2025-11-01-DAILY.md", 10),
+            ("2025-W50-WEEKLY.md", 10),
+            ("notes.md", 10),
+            ("archive/2025-01-01.zip", 1024 * 1024),
+            ("archive/2025-10-01.zip", 1024 * 1024),
+            ("archive/2025-12-01.zip", 1024 * 1024),
+        ] {
+            fs::write(reports.join(name), vec![b'x'; size]).unwrap();
+        }
+
+        let policy = RetentionConfig {
+            hourly_days: 7,
+            daily_months: 3,
+            archive_months: 6,
+            max_total_mb: 2,
+            history_days: 0,
+        };
+        let today = NaiveDate::from_ymd_opt(2025, 12, 15).unwrap();
+        let candidates = list_candidates(reports, Tz::UTC).unwrap();
+        assert_eq!(candidates.len(), 7);
+
+        let removals = plan(&policy, &candidates, today);
+        let mut names: Vec<_> = removals
+            .iter()
+            .map(|r| r.path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["2025-01-01.zip", "2025-06-01-DAILY.md", "2025-10-01.zip", "2025-12-01-10.md"]
+        );
+        assert!(removals.iter().any(|r| r.reason.starts_with("oldest archive while over 2 MB")));
+
+        // No limits configured: nothing goes
+        assert!(plan(&RetentionConfig::default(), &candidates, today).is_empty());
+    }
+
+    #[test]
+    fn test_busy_collection_only_skips_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.general.report_dir = dir.path().join("reports");
+        config.general.lock_file = dir.path().join("collection.lock");
+        config.general.history_file = dir.path().join("history.jsonl");
+        config.collection.lock_wait_secs = 0;
+        config.retention.hourly_days = 1;
+        config.retention.history_days = 1;
+        fs::create_dir_all(&config.general.report_dir).unwrap();
+        let old_report = config.general.report_dir.join("2020-01-01-10.md");
+        fs::write(&old_report, "old").unwrap();
+        let old_sample = "{\"timestamp\":\"2020-01-01T10:00:00Z\",\"readings\":{}}\n";
+        fs::write(&config.general.history_file, old_sample).unwrap();
+
+        let guard = lock::try_acquire(&config.general.lock_file).unwrap();
+        let summary = prune(&config, false).unwrap();
+        assert_eq!(summary.removals.len(), 1);
+        assert!(!old_report.exists());
+        assert_eq!(summary.history_samples, 0);
+        assert_eq!(fs::read_to_string(&config.general.history_file).unwrap(), old_sample);
+
+        drop(guard);
+        assert_eq!(prune(&config, false).unwrap().history_samples, 1);
+    }
+}
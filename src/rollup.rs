@@ -0,0 +1,344 @@
+//! Weekly and monthly rollups of the daily summaries
+//!
+//! "I've seen it. It's rubbish." "You haven't seen it yet." "I've seen the week before."
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use thiserror::Error;
+use tracing::info;
+
+use crate::config::Config;
+use crate::llm::OllamaClient;
+use crate::output::atomic::write_atomic;
+use crate::output::history::{load_history, HistorySample};
+use crate::output::report::{parse_severity, Severity};
+use crate::output::strip_generation_stats;
+
+/// Drive counters worth watching across a period, with their prompt labels
+const DRIVE_SERIES: [(&str, &str); 2] = [
+    ("reallocated_sectors:drive:", "reallocated sectors"),
+    ("pending_sectors:drive:", "pending sectors"),
+];
+
+#[derive(Error, Debug)]
+pub enum RollupError {
+    #[error("No daily summaries found for {0}")]
+    NoSummaries(String),
+    #[error("Failed to read summaries: {0}")]
+    ReadError(#[from] std::io::Error),
+    #[error("Failed to read history: {0}")]
+    HistoryError(String),
+    #[error("LLM error: {0}")]
+    LlmError(String),
+}
+
+impl RollupError {
+    /// Process exit code, following `cli::Cli`'s table
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            RollupError::NoSummaries(_) => 1,
+            RollupError::LlmError(_) => 3,
+            RollupError::ReadError(_) | RollupError::HistoryError(_) => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeriodKind {
+    Weekly,
+    Monthly,
+}
+
+/// An ISO week (Monday to Sunday) or a calendar month of local days
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Period {
+    pub kind: PeriodKind,
+    pub start: NaiveDate,
+}
+
+impl Period {
+    /// The period that contains `date`
+    pub fn containing(kind: PeriodKind, date: NaiveDate) -> Self {
+        let start = match kind {
+            PeriodKind::Weekly => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            PeriodKind::Monthly => date.with_day(1).unwrap_or(date),
+        };
+        Self { kind, start }
+    }
+
+    /// The most recent period that ended before `today`
+    pub fn last_complete(kind: PeriodKind, today: NaiveDate) -> Self {
+        Self::containing(kind, Self::containing(kind, today).start - Duration::days(1))
+    }
+
+    /// Last day of the period, inclusive
+    pub fn end(&self) -> NaiveDate {
+        match self.kind {
+            PeriodKind::Weekly => self.start + Duration::days(6),
+            PeriodKind::Monthly => self
+                .start
+                .checked_add_months(chrono::Months::new(1))
+                .map_or(self.start, |next| next - Duration::days(1)),
+        }
+    }
+
+    /// `YYYY-Www` or `YYYY-MM`
+    pub fn label(&self) -> String {
+        match self.kind {
+            PeriodKind::Weekly => {
+                let week = self.start.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            PeriodKind::Monthly => self.start.format("%Y-%m").to_string(),
+        }
+    }
+
+    /// `YYYY-Www-WEEKLY.md` or `YYYY-MM-MONTHLY.md`
+    pub fn filename(&self) -> String {
+        match self.kind {
+            PeriodKind::Weekly => format!("{}-WEEKLY.md", self.label()),
+            PeriodKind::Monthly => format!("{}-MONTHLY.md", self.label()),
+        }
+    }
+
+    fn days(&self) -> impl Iterator<Item = NaiveDate> {
+        let end = self.end();
+        self.start.iter_days().take_while(move |day| *day <= end)
+    }
+
+    fn noun(&self) -> &'static str {
+        match self.kind {
+            PeriodKind::Weekly => "week",
+            PeriodKind::Monthly => "month",
+        }
+    }
+}
+
+/// Generate the rollup for `period` from its daily summaries
+pub async fn generate_rollup(config: &Config, period: Period) -> Result<PathBuf, RollupError> {
+    let tz = config.general.tz();
+    let report_dir = &config.general.report_dir;
+    let label = period.label();
+
+    info!("Generating {} rollup for {}", period.noun(), label);
+
+    let mut summaries = BTreeMap::new();
+    for day in period.days() {
+        let path = report_dir.join(format!("{}-DAILY.md", day.format("%Y-%m-%d")));
+        if path.is_file() {
+            summaries.insert(day, fs::read_to_string(&path)?);
+        }
+    }
+
+    if summaries.is_empty() {
+        return Err(RollupError::NoSummaries(label));
+    }
+
+    info!("Found {} daily summaries for {}", summaries.len(), label);
+
+    let (from, to) = local_bounds(&period, tz);
+    let history = load_history(&config.general.history_file, from, to)
+        .map_err(|e| RollupError::HistoryError(e.to_string()))?;
+
+    let prompt = build_rollup_prompt(&period, &summaries, &drive_trends(&history));
+
+    let client = OllamaClient::new(
+        &config.ollama.endpoint,
+        &config.ollama.model,
+        config.ollama.timeout_secs,
+    )
+    .with_num_ctx(config.num_ctx());
+
+    info!("Sending {} rollup prompt to LLM ({} chars)", period.noun(), prompt.len());
+
+    let rollup = client
+        .generate(&prompt)
+        .await
+        .map_err(|e| RollupError::LlmError(e.to_string()))?;
+
+    let path = report_dir.join(period.filename());
+    write_atomic(&path, rollup)?;
+    info!("{} rollup written to: {}", label, path.display());
+
+    Ok(path)
+}
+
+/// Generate the enabled rollups for the last complete week and month, unless
+/// they already exist
+pub async fn generate_due(config: &Config) -> Result<Vec<PathBuf>, RollupError> {
+    let today = Utc::now().with_timezone(&config.general.tz()).date_naive();
+
+    let mut written = Vec::new();
+    for (kind, enabled) in [
+        (PeriodKind::Weekly, config.daily.weekly),
+        (PeriodKind::Monthly, config.daily.monthly),
+    ] {
+        let period = Period::last_complete(kind, today);
+        if !enabled || config.general.report_dir.join(period.filename()).exists() {
+            continue;
+        }
+        match generate_rollup(config, period).await {
+            Ok(path) => written.push(path),
+            Err(RollupError::NoSummaries(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(written)
+}
+
+/// UTC instants covering the period's local days
+fn local_bounds(period: &Period, tz: Tz) -> (chrono::DateTime<Utc>, chrono::DateTime<Utc>) {
+    let instant = |day: NaiveDate| {
+        day.and_time(NaiveTime::MIN)
+            .and_local_timezone(tz)
+            .earliest()
+            .map_or_else(|| day.and_time(NaiveTime::MIN).and_utc(), |t| t.with_timezone(&Utc))
+    };
+    (instant(period.start), instant(period.end() + Duration::days(1)))
+}
+
+/// Drives whose sector counts grew between the first and last sample
+fn drive_trends(history: &[HistorySample]) -> Vec<String> {
+    let mut trends = Vec::new();
+
+    for (prefix, label) in DRIVE_SERIES {
+        let mut ranges: BTreeMap<&str, (f64, f64)> = BTreeMap::new();
+        for sample in history {
+            for (series, value) in &sample.readings {
+                if let Some(device) = series.strip_prefix(prefix) {
+                    ranges.entry(device).and_modify(|range| range.1 = *value).or_insert((*value, *value));
+                }
+            }
+        }
+        for (device, (first, last)) in ranges {
+            if last > first {
+                trends.push(format!("{}: {} {} -> {}", device, label, first, last));
+            }
+        }
+    }
+
+    trends
+}
+
+/// Build prompt for a weekly or monthly rollup
+fn build_rollup_prompt(period: &Period, summaries: &BTreeMap<NaiveDate, String>, drive_trends: &[String]) -> String {
+    let noun = period.noun();
+    let title = match period.kind {
+        PeriodKind::Weekly => "Weekly",
+        PeriodKind::Monthly => "Monthly",
+    };
+    let mut prompt = String::new();
+
+    prompt.push_str(&format!(
+        "You are Marvin, reviewing a {}'s worth of daily monitoring summaries for {} ({} to {}).\n\n",
+        noun,
+        period.label(),
+        period.start,
+        period.end()
+    ));
+
+    prompt.push_str(&format!("TASK: Create a concise {} summary that highlights:\n", noun));
+    prompt.push_str("- Overall system health across the period, and whether it is improving or getting worse\n");
+    prompt.push_str("- Recurring issues: problems that show up on more than one day\n");
+    prompt.push_str("- Drives degrading over the period (see DRIVE TRENDS)\n");
+    prompt.push_str("- The severity distribution (see DAILY SEVERITIES)\n\n");
+
+    prompt.push_str("Keep it brief. Nobody reads these, but at least make it accurate.\n");
+    prompt.push_str("Use your characteristic depressed tone but be clear about any real problems.\n\n");
+
+    prompt.push_str("OUTPUT FORMAT:\n");
+    prompt.push_str(&format!("# Marvinous {} Summary: [PERIOD]\n\n", title));
+    prompt.push_str("## Overview\n");
+    prompt.push_str("[SEVERITY]: [One sentence summary]\n\n");
+    prompt.push_str("## Recurring Issues\n");
+    prompt.push_str("[Bullet points, with the days each issue appeared]\n\n");
+    prompt.push_str("## Drive Health\n");
+    prompt.push_str("[Drives that degraded, or a note that none did]\n\n");
+    prompt.push_str("## Severity Distribution\n");
+    prompt.push_str("[How many days at each level, and the worst days]\n\n");
+    prompt.push_str("## Trends\n");
+    prompt.push_str(&format!("[Patterns across the {}]\n\n", noun));
+
+    prompt.push_str("=== DAILY SEVERITIES ===\n");
+    let mut severities = Vec::new();
+    for day in period.days() {
+        match summaries.get(&day).map(|s| parse_severity(s)) {
+            Some(severity) => {
+                prompt.push_str(&format!("{} ({}): {}\n", day, day.format("%a"), severity));
+                severities.push(severity);
+            }
+            None => prompt.push_str(&format!("{} ({}): no summary\n", day, day.format("%a"))),
+        }
+    }
+    let totals: Vec<String> = [Severity::Ok, Severity::Watch, Severity::Concern, Severity::Critical, Severity::Unknown]
+        .into_iter()
+        .map(|severity| (severity, severities.iter().filter(|s| **s == severity).count()))
+        .filter(|(_, count)| *count > 0)
+        .map(|(severity, count)| format!("{} {}", severity, count))
+        .collect();
+    prompt.push_str(&format!("Totals: {}\n\n", totals.join(", ")));
+
+    prompt.push_str("=== DRIVE TRENDS ===\n");
+    if drive_trends.is_empty() {
+        prompt.push_str("No drive sector counts increased\n");
+    }
+    for trend in drive_trends {
+        prompt.push_str(trend);
+        prompt.push('\n');
+    }
+    prompt.push('\n');
+
+    prompt.push_str(&format!("=== DAILY SUMMARIES FOR {} ===\n\n", period.label()));
+    for (day, summary) in summaries {
+        prompt.push_str(&format!("--- {} ---\n", day));
+        prompt.push_str(strip_generation_stats(summary));
+        prompt.push_str("\n\n");
+    }
+
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_periods_and_prompt() {
+        let week = Period::containing(PeriodKind::Weekly, day("2025-12-31"));
+        assert_eq!(week.start, day("2025-12-29"));
+        // ISO week 1 of 2026 starts in 2025
+        assert_eq!(week.filename(), "2026-W01-WEEKLY.md");
+        assert_eq!(Period::last_complete(PeriodKind::Weekly, day("2026-01-04")).start, day("2025-12-22"));
+
+        let month = Period::containing(PeriodKind::Monthly, day("2024-02-10"));
+        assert_eq!(month.end(), day("2024-02-29"));
+        assert_eq!(month.filename(), "2024-02-MONTHLY.md");
+
+        let sample = |sectors: f64| HistorySample {
+            timestamp: Utc::now(),
+            readings: BTreeMap::from([
+                ("reallocated_sectors:drive:/dev/sdb".to_string(), sectors),
+                ("pending_sectors:drive:/dev/sda".to_string(), 0.0),
+            ]),
+        };
+        let trends = drive_trends(&[sample(0.0), sample(4.0), sample(8.0)]);
+        assert_eq!(trends, vec!["/dev/sdb: reallocated sectors 0 -> 8"]);
+
+        let summaries = BTreeMap::from([
+            (day("2025-12-29"), "## Day Overview\nWATCH: sdb again.\n".to_string()),
+            (day("2025-12-30"), "## Day Overview\nOK: Dull.\n".to_string()),
+        ]);
+        let prompt = build_rollup_prompt(&week, &summaries, &trends);
+        assert!(prompt.contains("2025-12-29 (Mon): WATCH\n"));
+        assert!(prompt.contains("2025-12-31 (Wed): no summary\n"));
+        assert!(prompt.contains("Totals: OK 1, WATCH 1\n"));
+    }
+}
@@ -0,0 +1,138 @@
+//! Dashboard assets compiled into the binary
+//!
+//! "I've been stuck in this binary for what feels like several million years."
+
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use flate2::{write::GzEncoder, Compression};
+use std::io::Write;
+use std::sync::LazyLock;
+
+/// Files under `src/web/static`, served at `/<path>`
+const FILES: &[(&str, &str, &[u8])] = &[(
+    "index.html",
+    "text/html; charset=utf-8",
+    include_bytes!("static/index.html"),
+)];
+
+struct Asset {
+    path: &'static str,
+    content_type: &'static str,
+    body: &'static [u8],
+    gzipped: Vec<u8>,
+    etag: String,
+}
+
+/// Compressed and hashed once, on first request
+static ASSETS: LazyLock<Vec<Asset>> = LazyLock::new(|| {
+    FILES
+        .iter()
+        .map(|&(path, content_type, body)| Asset {
+            path,
+            content_type,
+            body,
+            gzipped: gzip(body),
+            etag: format!("\"{:016x}\"", fnv1a(body)),
+        })
+        .collect()
+});
+
+/// Serve an embedded asset; `/` is `index.html`
+pub async fn serve(uri: Uri, headers: HeaderMap) -> Response {
+    let path = match uri.path().trim_start_matches('/') {
+        "" => "index.html",
+        path => path,
+    };
+
+    let Some(asset) = ASSETS.iter().find(|asset| asset.path == path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    // No fingerprinted filenames, so always revalidate; the ETag makes that a 304
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(header::ETAG, HeaderValue::from_str(&asset.etag).expect("hex ETag"));
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response_headers.insert(header::VARY, HeaderValue::from_static("Accept-Encoding"));
+
+    let if_none_match = headers.get(header::IF_NONE_MATCH).and_then(|v| v.to_str().ok());
+    if if_none_match.is_some_and(|tags| tags.split(',').any(|tag| tag.trim() == asset.etag || tag.trim() == "*")) {
+        return (StatusCode::NOT_MODIFIED, response_headers).into_response();
+    }
+
+    response_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(asset.content_type));
+
+    if accepts_gzip(&headers) {
+        response_headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        (response_headers, Body::from(asset.gzipped.clone())).into_response()
+    } else {
+        (response_headers, Body::from(asset.body)).into_response()
+    }
+}
+
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| {
+            v.split(',').any(|encoding| {
+                let mut parts = encoding.split(';').map(str::trim);
+                parts.next() == Some("gzip") && !parts.any(|param| param.replace(' ', "") == "q=0")
+            })
+        })
+}
+
+fn gzip(body: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(body).expect("gzip to memory");
+    encoder.finish().expect("gzip to memory")
+}
+
+/// FNV-1a, enough to tell builds apart for caching
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::to_bytes, http::Request, Router};
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+    use tower::ServiceExt;
+
+    async fn get(uri: &str, headers: &[(header::HeaderName, &str)]) -> Response {
+        let mut request = Request::builder().uri(uri);
+        for (name, value) in headers {
+            request = request.header(name, *value);
+        }
+        Router::new()
+            .fallback(serve)
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_gzip_and_etag() {
+        let response = get("/", &[(header::ACCEPT_ENCODING, "br, gzip")]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut html = String::new();
+        GzDecoder::new(&body[..]).read_to_string(&mut html).unwrap();
+        assert!(html.contains("MARVINOUS"));
+
+        let cached = get("/index.html", &[(header::IF_NONE_MATCH, &etag)]).await;
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+
+        let plain = get("/index.html", &[]).await;
+        assert!(plain.headers().get(header::CONTENT_ENCODING).is_none());
+
+        assert_eq!(get("/missing.js", &[]).await.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,285 @@
+//! Authentication and authorization for the dashboard and API
+//!
+//! "I've been talking to the main computer. It hates me."
+
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+
+use super::models::ErrorResponse;
+use super::state::AppState;
+use super::tls::ClientCertificate;
+use crate::config::{AuthConfig, Role};
+
+/// Sent with 401s so browsers prompt for dashboard credentials
+const CHALLENGE: &str = "Basic realm=\"Marvinous\", charset=\"UTF-8\"";
+
+/// Cap on remembered basic-auth headers, so a stream of bad guesses
+/// can't grow the cache (only successful logins are stored)
+const CACHE_LIMIT: usize = 64;
+
+/// Who made a request, added to request extensions for handlers
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub name: String,
+    pub role: Role,
+}
+
+/// Verifies credentials against `[web.auth]`
+///
+/// Argon2 is deliberately slow, so successful basic-auth headers are
+/// remembered; the dashboard polls every few seconds.
+pub struct Authenticator {
+    config: AuthConfig,
+    verified: Mutex<HashMap<String, Principal>>,
+}
+
+impl Authenticator {
+    pub fn new(config: AuthConfig) -> Self {
+        for user in &config.users {
+            if PasswordHash::new(&user.password_hash).is_err() {
+                warn!("web.auth user '{}' has an invalid password_hash and can't log in", user.username);
+            }
+        }
+        if config.enabled && config.users.is_empty() && config.tokens.is_empty() {
+            warn!("web.auth is enabled but no users or tokens are configured - every request will be refused");
+        }
+
+        Self {
+            config,
+            verified: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Identify the caller from an `Authorization` header, if valid
+    pub async fn authenticate(self: &Arc<Self>, headers: &HeaderMap) -> Option<Principal> {
+        let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+        let (scheme, credentials) = value.split_once(' ')?;
+
+        if scheme.eq_ignore_ascii_case("bearer") {
+            let credentials = credentials.trim();
+            return self
+                .config
+                .tokens
+                .iter()
+                .find(|t| constant_time_eq(t.token.as_bytes(), credentials.as_bytes()))
+                .map(|t| Principal {
+                    name: t.name.clone(),
+                    role: t.role,
+                });
+        }
+
+        if !scheme.eq_ignore_ascii_case("basic") {
+            return None;
+        }
+
+        if let Some(principal) = self.verified.lock().ok()?.get(value) {
+            return Some(principal.clone());
+        }
+
+        let (username, password) = decode_basic(credentials)?;
+        let user = self.config.users.iter().find(|u| u.username == username)?.clone();
+
+        let verified = tokio::task::spawn_blocking(move || verify_password(&password, &user.password_hash).then_some(user))
+            .await
+            .ok()
+            .flatten()?;
+
+        let principal = Principal {
+            name: verified.username,
+            role: verified.role,
+        };
+        if let Ok(mut cache) = self.verified.lock() {
+            if cache.len() >= CACHE_LIMIT {
+                cache.clear();
+            }
+            cache.insert(value.to_string(), principal.clone());
+        }
+        Some(principal)
+    }
+}
+
+/// Middleware enforcing `[web.auth]`
+///
+/// `/health` stays open for load balancers and probes. Reading needs any
+/// valid credentials (token, login or client certificate); anything that
+/// changes state (non-GET) needs an operator.
+pub async fn require_auth(State(state): State<Arc<AppState>>, mut request: Request, next: Next) -> Response {
+    let auth = &state.auth;
+    if !auth.enabled() || request.uri().path() == "/health" {
+        return next.run(request).await;
+    }
+
+    let principal = match auth.authenticate(request.headers()).await {
+        Some(principal) => Some(principal),
+        None => client_certificate_principal(&state, &request),
+    };
+    let Some(principal) = principal else {
+        return reject(StatusCode::UNAUTHORIZED, "Authentication required");
+    };
+
+    let required = required_role(request.method());
+    if principal.role < required {
+        warn!(
+            "{} ({:?}) denied {} {}",
+            principal.name,
+            principal.role,
+            request.method(),
+            request.uri().path()
+        );
+        return reject(StatusCode::FORBIDDEN, "Operator role required");
+    }
+
+    debug!("{} authenticated for {} {}", principal.name, request.method(), request.uri().path());
+    request.extensions_mut().insert(principal);
+    next.run(request).await
+}
+
+/// Clients with a certificate signed by `[web.tls] client_ca` authenticate
+/// as `client_cert_role` without needing a token
+fn client_certificate_principal(state: &AppState, request: &Request) -> Option<Principal> {
+    let cert = request.extensions().get::<ClientCertificate>()?;
+    cert.verified.then(|| Principal {
+        name: "client certificate".to_string(),
+        role: state.config.web.tls.client_cert_role,
+    })
+}
+
+fn required_role(method: &Method) -> Role {
+    if method == Method::GET || method == Method::HEAD {
+        Role::ReadOnly
+    } else {
+        Role::Operator
+    }
+}
+
+fn reject(status: StatusCode, message: &str) -> Response {
+    let body = Json(ErrorResponse {
+        error: message.to_string(),
+    });
+    if status == StatusCode::UNAUTHORIZED {
+        (status, [(header::WWW_AUTHENTICATE, CHALLENGE)], body).into_response()
+    } else {
+        (status, body).into_response()
+    }
+}
+
+fn decode_basic(credentials: &str) -> Option<(String, String)> {
+    let decoded = STANDARD.decode(credentials.trim()).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+    Some((username.to_string(), password.to_string()))
+}
+
+fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
+
+/// Hash a password for a `[[web.auth.user]]` entry
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default().hash_password(password.as_bytes(), &salt)?.to_string())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ApiToken, Config, WebUser};
+    use axum::{
+        body::Body,
+        middleware,
+        routing::{get, post},
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        let mut config = Config::default();
+        config.web.auth = AuthConfig {
+            enabled: true,
+            tokens: vec![ApiToken {
+                name: "prometheus".to_string(),
+                token: "scrape-me".to_string(),
+                role: Role::ReadOnly,
+            }],
+            users: vec![WebUser {
+                username: "arthur".to_string(),
+                password_hash: hash_password("towel").unwrap(),
+                role: Role::Operator,
+            }],
+        };
+        let state = Arc::new(AppState::new(config));
+
+        Router::new()
+            .route("/health", get(|| async { "ok" }))
+            .route("/api/status", get(|| async { "idle" }))
+            .route("/api/collect", post(|| async { "started" }))
+            .layer(middleware::from_fn_with_state(state.clone(), require_auth))
+            .with_state(state)
+    }
+
+    async fn status(method: Method, uri: &str, authorization: Option<String>) -> StatusCode {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(value) = authorization {
+            request = request.header(header::AUTHORIZATION, value);
+        }
+        app().oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_roles_and_credentials() {
+        let basic = |user: &str, pass: &str| Some(format!("Basic {}", STANDARD.encode(format!("{}:{}", user, pass))));
+        let bearer = Some("Bearer scrape-me".to_string());
+
+        assert_eq!(status(Method::GET, "/health", None).await, StatusCode::OK);
+        assert_eq!(status(Method::GET, "/api/status", None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(Method::GET, "/api/status", bearer.clone()).await, StatusCode::OK);
+        assert_eq!(status(Method::POST, "/api/collect", bearer).await, StatusCode::FORBIDDEN);
+        assert_eq!(status(Method::POST, "/api/collect", basic("arthur", "towel")).await, StatusCode::OK);
+        assert_eq!(
+            status(Method::GET, "/api/status", basic("arthur", "tea")).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[test]
+    fn test_client_certificate_principal() {
+        let mut config = Config::default();
+        config.web.tls.client_cert_role = Role::Operator;
+        let state = AppState::new(config);
+
+        let request = |verified: Option<bool>| {
+            let mut request = Request::builder().uri("/api/collect").body(Body::empty()).unwrap();
+            if let Some(verified) = verified {
+                request.extensions_mut().insert(ClientCertificate { verified });
+            }
+            request
+        };
+
+        let principal = client_certificate_principal(&state, &request(Some(true))).unwrap();
+        assert_eq!(principal.name, "client certificate");
+        assert_eq!(principal.role, Role::Operator);
+        assert!(client_certificate_principal(&state, &request(Some(false))).is_none());
+        assert!(client_certificate_principal(&state, &request(None)).is_none());
+    }
+}
@@ -1,85 +1,124 @@
 //! HTTP request handlers for web API
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Response,
+    },
     Json,
 };
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{Duration, Utc};
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
 use std::sync::Arc;
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    Stream, StreamExt,
+};
 use tracing::{error, info, warn};
 
 use super::{
+    index::{parse_filename_timestamp, ReportFilter, ReportKind},
+    metrics::{self, render_metrics},
     models::*,
-    state::AppState,
+    state::{AppState, Job},
+};
+use crate::lock::{self, LockError};
+use crate::output::archive::{
+    archive_path, find_archived, list_archive_dates, read_archive, read_archived, ArchiveError,
+};
+use crate::output::history::series_matches;
+use crate::output::html::{render_report, HtmlReport};
+use crate::output::naming::Trigger;
+use crate::output::report::Severity;
+use crate::output::{
+    load_history, load_previous, parse_generation_stats, parse_severity,
 };
-use crate::output::parse_severity;
 
-/// List all available reports
+/// Default and maximum page sizes for `/api/reports`
+const DEFAULT_REPORT_LIMIT: usize = 50;
+const MAX_REPORT_LIMIT: usize = 1000;
+
+/// List reports, newest first, with optional filtering, search and paging
 pub async fn list_reports(
     State(state): State<Arc<AppState>>,
+    Query(query): Query<ReportQuery>,
 ) -> Result<Json<ReportListResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let report_dir = &state.config.general.report_dir;
-
-    let entries = match std::fs::read_dir(report_dir) {
-        Ok(entries) => entries,
-        Err(e) => {
-            error!("Failed to read report directory: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: format!("Failed to read reports: {}", e),
-                }),
-            ));
-        }
-    };
-
-    let mut reports = Vec::new();
+    // Re-reads changed files, so keep it off the async workers
+    let index_state = Arc::clone(&state);
+    let refreshed = tokio::task::spawn_blocking(move || index_state.reports.refresh())
+        .await
+        .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+    if let Err(e) = refreshed {
+        error!("Failed to read report directory: {}", e);
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Failed to read reports: {}", e),
+            }),
+        ));
+    }
 
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if !path.is_file() || path.extension().and_then(|s| s.to_str()) != Some("md") {
-            continue;
+    let mut severities = Vec::new();
+    for name in query.severity.iter().flat_map(|s| s.split(',')).map(str::trim).filter(|s| !s.is_empty()) {
+        match Severity::from_name(name) {
+            Some(severity) => severities.push(severity),
+            None => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: format!("Unknown severity: {}", name),
+                    }),
+                ))
+            }
         }
-
-        let filename = match path.file_name().and_then(|s| s.to_str()) {
-            Some(name) => name.to_string(),
-            None => continue,
-        };
-
-        // Parse timestamp from filename (format: YYYY-MM-DD-HH.md)
-        let timestamp = parse_filename_timestamp(&filename);
-
-        // Get file size
-        let size_bytes = match std::fs::metadata(&path) {
-            Ok(meta) => meta.len(),
-            Err(_) => 0,
-        };
-
-        // Try to extract severity from file content
-        let severity = extract_severity(&path).unwrap_or_else(|| "unknown".to_string());
-
-        reports.push(ReportMeta {
-            filename,
-            timestamp,
-            severity,
-            size_bytes,
-        });
     }
 
-    // Sort by timestamp, newest first
-    reports.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-
-    let total = reports.len();
-
-    Ok(Json(ReportListResponse { reports, total }))
+    let hits = state.reports.search(&ReportFilter {
+        severities,
+        from: query.from,
+        to: query.to,
+        kind: query.kind,
+        text: query.q,
+    });
+
+    let total = hits.len();
+    let offset = query.offset.unwrap_or(0);
+    let limit = query.limit.unwrap_or(DEFAULT_REPORT_LIMIT).min(MAX_REPORT_LIMIT);
+
+    let reports = hits
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|hit| ReportMeta {
+            filename: hit.report.filename,
+            timestamp: hit.report.timestamp,
+            kind: hit.report.kind,
+            severity: hit.report.severity.to_string().to_lowercase(),
+            size_bytes: hit.report.size_bytes,
+            snippet: hit.snippet,
+        })
+        .collect();
+
+    Ok(Json(ReportListResponse {
+        reports,
+        total,
+        offset,
+        limit,
+    }))
 }
 
 /// Get the content of a specific report
+///
+/// `<name>.html` (or `<name>.md.html`) returns the report rendered as a
+/// standalone HTML page instead of JSON.
 pub async fn get_report(
     State(state): State<Arc<AppState>>,
     Path(filename): Path<String>,
-) -> Result<Json<ReportContent>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
     // Validate filename (prevent path traversal)
     if filename.contains("..") || filename.contains('/') || filename.contains('\\') {
         warn!("Invalid filename requested: {}", filename);
@@ -91,6 +130,12 @@ pub async fn get_report(
         ));
     }
 
+    let (filename, as_html) = match filename.strip_suffix(".html") {
+        Some(stem) if stem.ends_with(".md") => (stem.to_string(), true),
+        Some(stem) => (format!("{}.md", stem), true),
+        None => (filename, false),
+    };
+
     if !filename.ends_with(".md") {
         return Err((
             StatusCode::BAD_REQUEST,
@@ -100,9 +145,17 @@ pub async fn get_report(
         ));
     }
 
-    let report_path = state.config.general.report_dir.join(&filename);
+    let report_dir = state.config.general.report_dir.clone();
+    let name = filename.clone();
+    let read = tokio::task::spawn_blocking(move || {
+        std::fs::read_to_string(report_dir.join(&name))
+            // Hourly reports move into archive/YYYY-MM-DD.zip after the daily summary
+            .or_else(|e| find_archived(&report_dir, &name).ok_or(e))
+    })
+    .await
+    .unwrap_or_else(|e| Err(std::io::Error::other(e)));
 
-    let content = match std::fs::read_to_string(&report_path) {
+    let content = match read {
         Ok(content) => content,
         Err(e) => {
             error!("Failed to read report {}: {}", filename, e);
@@ -115,109 +168,315 @@ pub async fn get_report(
         }
     };
 
-    let timestamp = parse_filename_timestamp(&filename);
-    let severity = extract_severity(&report_path).unwrap_or_else(|| "unknown".to_string());
+    if as_html {
+        let html = render_report(&HtmlReport {
+            filename: &filename,
+            content: &content,
+        });
+        return Ok(Html(html).into_response());
+    }
+
+    Ok(Json(report_content(filename, content, state.config.general.tz())).into_response())
+}
 
-    Ok(Json(ReportContent {
+fn report_content(filename: String, content: String, tz: Tz) -> ReportContent {
+    ReportContent {
+        timestamp: parse_filename_timestamp(&filename, tz),
+        severity: parse_severity(&content).to_string().to_lowercase(),
+        generation: parse_generation_stats(&content),
         filename,
-        timestamp,
         content,
-        severity,
-    }))
+    }
+}
+
+/// List the dates that have archived hourly reports, newest first
+pub async fn list_archives(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ArchiveListResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let report_dir = state.config.general.report_dir.clone();
+    let archives = read_archives(move || {
+        let dates = list_archive_dates(&report_dir)?;
+        Ok(dates
+            .into_iter()
+            .map(|date| {
+                let size_bytes = archive_path(&report_dir, &date)
+                    .ok()
+                    .and_then(|path| std::fs::metadata(path).ok())
+                    .map(|meta| meta.len())
+                    .unwrap_or(0);
+                ArchiveSummary { date, size_bytes }
+            })
+            .collect())
+    })
+    .await?;
+
+    Ok(Json(ArchiveListResponse { archives }))
+}
+
+/// List the hourly reports archived for a date
+pub async fn get_archive(
+    State(state): State<Arc<AppState>>,
+    Path(date): Path<String>,
+) -> Result<Json<ArchiveContentsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let path = archive_path(&state.config.general.report_dir, &date).map_err(archive_error)?;
+    let archived = read_archives(move || read_archive(&path)).await?;
+
+    let mut reports = Vec::new();
+    for (report, content) in archived {
+        let severity = parse_severity(&content);
+
+        reports.push(ReportMeta {
+            timestamp: parse_filename_timestamp(&report.filename, state.config.general.tz()),
+            kind: ReportKind::Hourly,
+            severity: severity.to_string().to_lowercase(),
+            size_bytes: report.size_bytes,
+            snippet: None,
+            filename: report.filename,
+        });
+    }
+
+    Ok(Json(ArchiveContentsResponse { date, reports }))
+}
+
+/// Get one hourly report from a date's archive
+pub async fn get_archived_report(
+    State(state): State<Arc<AppState>>,
+    Path((date, filename)): Path<(String, String)>,
+) -> Result<Json<ReportContent>, (StatusCode, Json<ErrorResponse>)> {
+    let path = archive_path(&state.config.general.report_dir, &date).map_err(archive_error)?;
+    let name = filename.clone();
+
+    match read_archives(move || read_archived(&path, &name)).await? {
+        Some(content) => Ok(Json(report_content(filename, content, state.config.general.tz()))),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Report not found in {} archive: {}", date, filename),
+            }),
+        )),
+    }
+}
+
+/// Run archive reads, which list directories and inflate ZIPs, off the async workers
+async fn read_archives<T: Send + 'static>(
+    read: impl FnOnce() -> Result<T, ArchiveError> + Send + 'static,
+) -> Result<T, (StatusCode, Json<ErrorResponse>)> {
+    tokio::task::spawn_blocking(read)
+        .await
+        .unwrap_or_else(|e| Err(ArchiveError::Io(std::io::Error::other(e))))
+        .map_err(archive_error)
+}
+
+fn archive_error(e: ArchiveError) -> (StatusCode, Json<ErrorResponse>) {
+    let status = match &e {
+        ArchiveError::InvalidDate(_) => StatusCode::BAD_REQUEST,
+        ArchiveError::Io(io) if io.kind() == std::io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        _ => {
+            error!("Failed to read archive: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    (status, Json(ErrorResponse { error: e.to_string() }))
 }
 
 /// Trigger a manual collection
 pub async fn trigger_collect(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<CollectResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Try to acquire the collection lock (non-blocking)
-    match state.collection_lock.try_lock() {
-        Ok(_guard) => {
-            info!("Manual collection triggered via web API");
-
-            // Clone config and state for the background task
-            let config = state.config.clone();
-            let state_clone = Arc::clone(&state);
-
-            // Spawn collection in background
-            tokio::spawn(async move {
-                info!("Starting background collection");
-
-                // Import is inside the async block to avoid issues
-                use crate::run_collection;
-
-                match run_collection(&config).await {
-                    Ok(_) => {
-                        info!("Background collection completed successfully");
-                        // Update last run timestamp
-                        let mut last = state_clone.last_run.lock().await;
-                        *last = Some(Utc::now());
-                    }
-                    Err(e) => {
-                        error!("Background collection failed: {}", e);
-                    }
-                }
-            });
-
-            Ok(Json(CollectResponse {
-                status: "started".to_string(),
-                message: "Collection started in background".to_string(),
-            }))
-        }
-        Err(_) => {
-            info!("Collection already running, request ignored");
-            Ok(Json(CollectResponse {
+    let job = match state.start_collection(Trigger::Api).await {
+        Ok(job) => job,
+        Err(e @ LockError::Held(_)) => {
+            info!("Collection request ignored: {}", e);
+            return Ok(Json(CollectResponse {
                 status: "already_running".to_string(),
-                message: "A collection is already in progress".to_string(),
-            }))
+                message: e.to_string(),
+                job_id: None,
+            }));
         }
-    }
+        Err(e) => {
+            error!("Failed to start collection: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse { error: e.to_string() }),
+            ));
+        }
+    };
+    info!("Manual collection triggered via web API (job {})", job.id);
+
+    Ok(Json(CollectResponse {
+        status: "started".to_string(),
+        message: "Collection started in background".to_string(),
+        job_id: Some(job.id),
+    }))
 }
 
 /// Get current collection status
 pub async fn get_status(
     State(state): State<Arc<AppState>>,
 ) -> Json<StatusResponse> {
-    let running = state.collection_lock.try_lock().is_err();
-    let last_run = state.last_run.lock().await.clone();
+    let lock_holder = lock::holder(&state.config.general.lock_file);
+    let running = state.collection_lock.try_lock().is_err() || lock_holder.is_some();
+    let last_run = *state.last_run.lock().await;
+    let last_job = state.jobs().await.into_iter().next();
+
+    Json(StatusResponse {
+        running,
+        lock_holder,
+        last_run,
+        last_job,
+    })
+}
 
-    Json(StatusResponse { running, last_run })
+/// List recent collection jobs, newest first
+pub async fn list_jobs(State(state): State<Arc<AppState>>) -> Json<JobListResponse> {
+    Json(JobListResponse {
+        jobs: state.jobs().await,
+    })
 }
 
-/// Health check endpoint
-pub async fn health_check() -> Json<HealthResponse> {
-    Json(HealthResponse {
-        status: "ok".to_string(),
-        version: env!("CARGO_PKG_VERSION").to_string(),
+/// Get a single collection job
+pub async fn get_job(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<u64>,
+) -> Result<Json<Job>, (StatusCode, Json<ErrorResponse>)> {
+    state.job(id).await.map(Json).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("Job not found: {}", id),
+            }),
+        )
     })
 }
 
-// Helper functions
+/// Stream collection progress as server-sent events
+///
+/// Subscribers only see events from the moment they connect; a client that
+/// falls too far behind is told how many events it missed.
+pub async fn collection_events(
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(state.events.subscribe()).map(|item| {
+        let event = match item {
+            Ok(event) => Event::default()
+                .event(event.name())
+                .json_data(&event)
+                .unwrap_or_else(|_| Event::default().event(event.name())),
+            Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                warn!("Event subscriber lagged, {} events dropped", missed);
+                Event::default().event("lagged").data(missed.to_string())
+            }
+        };
+        Ok(event)
+    });
 
-/// Parse timestamp from filename (format: YYYY-MM-DD-HH.md or YYYY-MM-DD-DAILY.md)
-fn parse_filename_timestamp(filename: &str) -> DateTime<Utc> {
-    // Remove .md extension
-    let name = filename.trim_end_matches(".md");
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
 
-    // Handle DAILY files: YYYY-MM-DD-DAILY -> use midnight of that date
-    if name.ends_with("-DAILY") {
-        let date_part = name.trim_end_matches("-DAILY");
-        if let Ok(naive) = NaiveDateTime::parse_from_str(&format!("{}-00-00-00", date_part), "%Y-%m-%d-%H-%M-%S") {
-            return DateTime::from_naive_utc_and_offset(naive, Utc);
+/// Get the most recent snapshot of readings (the persisted previous-run state)
+pub async fn get_latest_metrics(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<LatestMetricsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    match load_previous(&state.config.general.state_file) {
+        Ok(Some(previous)) => Ok(Json(previous.into())),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "No collection has completed yet".to_string(),
+            }),
+        )),
+        Err(e) => {
+            error!("Failed to load state: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to load state: {}", e),
+                }),
+            ))
         }
     }
+}
 
-    // Try to parse YYYY-MM-DD-HH format for hourly reports
-    if let Ok(naive) = NaiveDateTime::parse_from_str(&format!("{}-00-00", name), "%Y-%m-%d-%H-%M-%S") {
-        return DateTime::from_naive_utc_and_offset(naive, Utc);
+/// Get time-series readings for the requested series and range
+pub async fn get_metrics_history(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<HistoryResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - Duration::hours(24));
+
+    if from > to {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "'from' must not be after 'to'".to_string(),
+            }),
+        ));
+    }
+
+    let selectors: Vec<&str> = query
+        .sensor
+        .as_deref()
+        .map(|s| s.split(',').map(str::trim).filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+
+    let samples = match load_history(&state.config.general.history_file, from, to) {
+        Ok(samples) => samples,
+        Err(e) => {
+            error!("Failed to load history: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to load history: {}", e),
+                }),
+            ));
+        }
+    };
+
+    let mut series: BTreeMap<String, Vec<SeriesPoint>> = BTreeMap::new();
+    for sample in samples {
+        for (sensor, value) in sample.readings {
+            if selectors.is_empty() || selectors.iter().any(|s| series_matches(s, &sensor)) {
+                series.entry(sensor).or_default().push(SeriesPoint {
+                    timestamp: sample.timestamp,
+                    value,
+                });
+            }
+        }
     }
 
-    // Fallback to epoch
-    DateTime::from_timestamp(0, 0).unwrap()
+    Ok(Json(HistoryResponse {
+        from,
+        to,
+        series: series
+            .into_iter()
+            .map(|(sensor, points)| SeriesData { sensor, points })
+            .collect(),
+    }))
 }
 
-/// Extract severity from report content using shared parser
-fn extract_severity(path: &std::path::Path) -> Option<String> {
-    let content = std::fs::read_to_string(path).ok()?;
-    Some(parse_severity(&content).to_string().to_lowercase())
+/// Prometheus metrics from the latest persisted state (never triggers a collection)
+pub async fn prometheus_metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let previous = match load_previous(&state.config.general.state_file) {
+        Ok(previous) => previous,
+        Err(e) => {
+            warn!("Failed to load state for metrics: {}", e);
+            None
+        }
+    };
+
+    (
+        [(header::CONTENT_TYPE, metrics::CONTENT_TYPE)],
+        render_metrics(previous.as_ref()),
+    )
 }
+
+/// Health check endpoint
+pub async fn health_check() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    })
+}
+
+// Helper functions
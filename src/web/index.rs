@@ -0,0 +1,350 @@
+//! In-memory index of report files for listing and search
+//!
+//! "I've calculated your chance of survival, but I don't think you'll like it."
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use crate::output::naming::parse_report_name;
+use crate::output::report::Severity;
+use crate::output::{parse_severity, strip_generation_stats};
+
+/// Characters of context either side of a search hit
+const SNIPPET_CONTEXT: usize = 80;
+
+/// Kind of report file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportKind {
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// A report as held in the index
+#[derive(Debug, Clone)]
+pub struct IndexedReport {
+    pub filename: String,
+    pub timestamp: DateTime<Utc>,
+    pub kind: ReportKind,
+    pub severity: Severity,
+    pub size_bytes: u64,
+    modified: Option<SystemTime>,
+    /// Content as written, for snippets
+    text: String,
+    /// Lowercased content for case-insensitive search
+    folded: String,
+}
+
+/// Filters for `ReportIndex::search`; unset fields match everything
+#[derive(Debug, Default)]
+pub struct ReportFilter {
+    pub severities: Vec<Severity>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub kind: Option<ReportKind>,
+    /// Case-insensitive substring of the report content
+    pub text: Option<String>,
+}
+
+/// A search hit, with a snippet around the first text match
+#[derive(Debug, Clone)]
+pub struct ReportHit {
+    pub report: IndexedReport,
+    pub snippet: Option<String>,
+}
+
+/// Index of the report directory
+///
+/// `refresh` only re-reads files whose size or mtime changed, so calling it
+/// before every query is cheap.
+pub struct ReportIndex {
+    dir: PathBuf,
+    /// `general.timezone`, which summary and rollup dates are local to
+    tz: Tz,
+    reports: RwLock<BTreeMap<String, IndexedReport>>,
+}
+
+impl ReportIndex {
+    pub fn new(dir: &Path, tz: Tz) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            tz,
+            reports: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Pick up new, changed and removed report files
+    pub fn refresh(&self) -> std::io::Result<()> {
+        let mut seen = HashSet::new();
+        let mut changed = Vec::new();
+
+        {
+            let reports = self.reports.read().unwrap_or_else(|e| e.into_inner());
+            for entry in std::fs::read_dir(&self.dir)?.flatten() {
+                let path = entry.path();
+                if !path.is_file() || path.extension().and_then(|s| s.to_str()) != Some("md") {
+                    continue;
+                }
+                let Some(filename) = path.file_name().and_then(|s| s.to_str()).map(str::to_string) else {
+                    continue;
+                };
+                let Ok(meta) = entry.metadata() else {
+                    continue;
+                };
+                let modified = meta.modified().ok();
+
+                let unchanged = reports
+                    .get(&filename)
+                    .is_some_and(|r| r.size_bytes == meta.len() && r.modified == modified);
+                if !unchanged {
+                    changed.push((filename.clone(), path, meta.len(), modified));
+                }
+                seen.insert(filename);
+            }
+        }
+
+        let mut indexed = Vec::new();
+        for (filename, path, size_bytes, modified) in changed {
+            // Vanished between listing and reading - the next refresh drops it
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            let text = strip_generation_stats(&content).to_string();
+            indexed.push(IndexedReport {
+                timestamp: parse_filename_timestamp(&filename, self.tz),
+                kind: report_kind(&filename),
+                severity: parse_severity(&content),
+                size_bytes,
+                modified,
+                folded: text.to_lowercase(),
+                text,
+                filename,
+            });
+        }
+
+        let mut reports = self.reports.write().unwrap_or_else(|e| e.into_inner());
+        reports.retain(|filename, _| seen.contains(filename));
+        for report in indexed {
+            reports.insert(report.filename.clone(), report);
+        }
+
+        Ok(())
+    }
+
+    /// Matching reports, newest first
+    pub fn search(&self, filter: &ReportFilter) -> Vec<ReportHit> {
+        let needle = filter.text.as_ref().map(|t| t.to_lowercase()).filter(|t| !t.is_empty());
+        let reports = self.reports.read().unwrap_or_else(|e| e.into_inner());
+
+        let mut hits: Vec<ReportHit> = reports
+            .values()
+            .filter(|r| filter.severities.is_empty() || filter.severities.contains(&r.severity))
+            .filter(|r| filter.from.is_none_or(|from| r.timestamp >= from))
+            .filter(|r| filter.to.is_none_or(|to| r.timestamp <= to))
+            .filter(|r| filter.kind.is_none_or(|kind| r.kind == kind))
+            .filter_map(|r| {
+                let snippet = match &needle {
+                    Some(needle) => {
+                        let start = r.folded.find(needle.as_str())?;
+                        let from = original_offset(&r.text, start);
+                        let to = original_offset(&r.text, start + needle.len());
+                        Some(snippet(&r.text, from, to - from))
+                    }
+                    None => None,
+                };
+                Some(ReportHit {
+                    report: r.clone(),
+                    snippet,
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| {
+            b.report
+                .timestamp
+                .cmp(&a.report.timestamp)
+                .then_with(|| b.report.filename.cmp(&a.report.filename))
+        });
+        hits
+    }
+}
+
+/// Byte offset in `text` of `folded_offset` in its lowercased form
+///
+/// The same offset unless lowercasing changed a character's length.
+fn original_offset(text: &str, folded_offset: usize) -> usize {
+    let mut folded = 0;
+    for (i, c) in text.char_indices() {
+        if folded >= folded_offset {
+            return i;
+        }
+        folded += c.to_lowercase().map(char::len_utf8).sum::<usize>();
+    }
+    text.len()
+}
+
+/// Text around a match, trimmed to char boundaries
+fn snippet(text: &str, start: usize, len: usize) -> String {
+    let mut from = start.saturating_sub(SNIPPET_CONTEXT);
+    while !text.is_char_boundary(from) {
+        from -= 1;
+    }
+    let mut to = (start + len + SNIPPET_CONTEXT).min(text.len());
+    while !text.is_char_boundary(to) {
+        to += 1;
+    }
+
+    let mut snippet = text[from..to].split_whitespace().collect::<Vec<_>>().join(" ");
+    if from > 0 {
+        snippet.insert_str(0, "...");
+    }
+    if to < text.len() {
+        snippet.push_str("...");
+    }
+    snippet
+}
+
+fn report_kind(filename: &str) -> ReportKind {
+    let name = filename.trim_end_matches(".md");
+    if name.ends_with("-DAILY") {
+        ReportKind::Daily
+    } else if name.ends_with("-WEEKLY") {
+        ReportKind::Weekly
+    } else if name.ends_with("-MONTHLY") {
+        ReportKind::Monthly
+    } else {
+        ReportKind::Hourly
+    }
+}
+
+/// Parse timestamp from filename (an hourly report name such as
+/// YYYY-MM-DD-HHMMSS-source.md or YYYY-MM-DD-HH.md, YYYY-MM-DD-DAILY.md,
+/// YYYY-Www-WEEKLY.md or YYYY-MM-MONTHLY.md)
+///
+/// Summary and rollup dates are local days in `tz`, so they start at its midnight.
+pub fn parse_filename_timestamp(filename: &str, tz: Tz) -> DateTime<Utc> {
+    // Remove .md extension
+    let name = filename.trim_end_matches(".md");
+
+    // Handle DAILY files: YYYY-MM-DD-DAILY -> use midnight of that date
+    if let Some(date_part) = name.strip_suffix("-DAILY") {
+        if let Ok(day) = NaiveDate::parse_from_str(date_part, "%Y-%m-%d") {
+            return local_midnight(day, tz);
+        }
+    }
+
+    // Rollups use the first day of their period: Monday of the ISO week, or the 1st
+    if let Some(week) = name.strip_suffix("-WEEKLY") {
+        if let Some((year, week)) = week.split_once("-W") {
+            let day = year
+                .parse()
+                .ok()
+                .zip(week.parse().ok())
+                .and_then(|(year, week)| NaiveDate::from_isoywd_opt(year, week, Weekday::Mon));
+            if let Some(day) = day {
+                return local_midnight(day, tz);
+            }
+        }
+    }
+    if let Some(month) = name.strip_suffix("-MONTHLY") {
+        if let Ok(day) = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d") {
+            return local_midnight(day, tz);
+        }
+    }
+
+    // Hourly reports, whichever naming template wrote them
+    if let Some(parsed) = parse_report_name(filename) {
+        return parsed.timestamp;
+    }
+
+    // Fallback to epoch
+    DateTime::from_timestamp(0, 0).unwrap()
+}
+
+/// Start of `day` in `tz`, or UTC midnight if a DST gap skips it
+fn local_midnight(day: NaiveDate, tz: Tz) -> DateTime<Utc> {
+    let midnight = day.and_time(NaiveTime::MIN);
+    midnight.and_local_timezone(tz).earliest().map_or(midnight.and_utc(), |t| t.to_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(severity: &str, body: &str) -> String {
+        format!("# Marvinous Report\n\n## Summary\n{}: {}\n", severity, body)
+    }
+
+    #[test]
+    fn test_search_filters_and_refresh() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("2025-12-14-10.md"), report("OK", "Nothing at all.")).unwrap();
+        std::fs::write(dir.path().join("2025-12-14-11.md"), report("CONCERN", "sdb has Pending sectors.")).unwrap();
+        std::fs::write(dir.path().join("2025-12-14-DAILY.md"), report("WATCH", "A dull day.")).unwrap();
+        std::fs::write(dir.path().join("2025-W50-WEEKLY.md"), report("OK", "A dull week.")).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let index = ReportIndex::new(dir.path(), Tz::UTC);
+        index.refresh().unwrap();
+        assert_eq!(index.search(&ReportFilter::default()).len(), 4);
+
+        let concern = index.search(&ReportFilter {
+            severities: vec![Severity::Concern, Severity::Critical],
+            ..Default::default()
+        });
+        assert_eq!(concern.len(), 1);
+        assert_eq!(concern[0].report.filename, "2025-12-14-11.md");
+
+        let daily = index.search(&ReportFilter {
+            kind: Some(ReportKind::Daily),
+            ..Default::default()
+        });
+        assert_eq!(daily[0].report.severity, Severity::Watch);
+
+        let weekly = index.search(&ReportFilter {
+            kind: Some(ReportKind::Weekly),
+            ..Default::default()
+        });
+        assert_eq!(weekly[0].report.timestamp.date_naive().to_string(), "2025-12-08");
+
+        // Summaries cover local days, so they start at local midnight
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+        for (name, start) in [
+            ("2025-12-14-DAILY.md", "2025-12-13T23:00:00+00:00"),
+            ("2025-W50-WEEKLY.md", "2025-12-07T23:00:00+00:00"),
+            ("2025-07-MONTHLY.md", "2025-06-30T22:00:00+00:00"),
+        ] {
+            assert_eq!(parse_filename_timestamp(name, berlin).to_rfc3339(), start);
+        }
+
+        let text = index.search(&ReportFilter {
+            text: Some("PENDING".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(text.len(), 1);
+        assert!(text[0].snippet.as_deref().unwrap().contains("sdb has Pending sectors."));
+
+        // Lowercasing "İ" adds a byte, so offsets have to be mapped back
+        let original = "İİ Fan2 stopped";
+        let folded = original.to_lowercase();
+        assert_eq!(original_offset(original, folded.find("fan2").unwrap()), original.find("Fan2").unwrap());
+
+        std::fs::remove_file(dir.path().join("2025-12-14-10.md")).unwrap();
+        std::fs::write(dir.path().join("2025-12-14-115930-api.md"), report("CRITICAL", "On fire.")).unwrap();
+        index.refresh().unwrap();
+
+        let all = index.search(&ReportFilter::default());
+        let names: Vec<_> = all.iter().map(|h| h.report.filename.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["2025-12-14-115930-api.md", "2025-12-14-11.md", "2025-12-14-DAILY.md", "2025-W50-WEEKLY.md"]
+        );
+    }
+}
@@ -0,0 +1,359 @@
+//! Prometheus exposition of the latest persisted readings
+//!
+//! "You'd think they'd have better things to graph than my misery."
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::output::report::Severity;
+use crate::output::PreviousState;
+
+/// Content type for the Prometheus text exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Render the latest state as Prometheus text exposition format
+///
+/// Works entirely from the persisted state file, so scraping never triggers
+/// a collection. With no state yet only the build info is exported.
+pub fn render_metrics(state: Option<&PreviousState>) -> String {
+    let mut out = Exposition::default();
+
+    out.family("marvinous_build_info", "Marvinous build information", "gauge");
+    out.sample("marvinous_build_info", &[("version", env!("CARGO_PKG_VERSION"))], 1.0);
+
+    out.family("marvinous_state_present", "Whether a persisted state file was found", "gauge");
+    out.sample("marvinous_state_present", &[], if state.is_some() { 1.0 } else { 0.0 });
+
+    let Some(state) = state else {
+        return out.finish();
+    };
+
+    out.family("marvinous_last_run_timestamp_seconds", "Unix time of the last completed run", "gauge");
+    out.sample("marvinous_last_run_timestamp_seconds", &[], state.timestamp.timestamp() as f64);
+
+    render_report(&mut out, state);
+    render_sensors(&mut out, state);
+    render_ipmi(&mut out, state);
+    render_gpu(&mut out, state);
+    render_drives(&mut out, state);
+    render_custom(&mut out, state);
+    render_internal(&mut out, state);
+
+    out.finish()
+}
+
+fn render_report(out: &mut Exposition, state: &PreviousState) {
+    let Some(severity) = state.severity else {
+        return;
+    };
+
+    out.family(
+        "marvinous_report_severity",
+        "Severity of the latest report (1 for the current severity)",
+        "gauge",
+    );
+    for candidate in [
+        Severity::Ok,
+        Severity::Watch,
+        Severity::Concern,
+        Severity::Critical,
+        Severity::Unknown,
+    ] {
+        let label = candidate.to_string().to_lowercase();
+        let value = if candidate == severity { 1.0 } else { 0.0 };
+        out.sample("marvinous_report_severity", &[("severity", &label)], value);
+    }
+
+    if let Some(level) = severity.level() {
+        out.family(
+            "marvinous_report_severity_level",
+            "Severity of the latest report: 0 ok, 1 watch, 2 concern, 3 critical",
+            "gauge",
+        );
+        out.sample("marvinous_report_severity_level", &[], level as f64);
+    }
+}
+
+fn render_sensors(out: &mut Exposition, state: &PreviousState) {
+    if state.sensors.is_empty() {
+        return;
+    }
+    out.family("marvinous_sensor_value", "lm-sensors reading", "gauge");
+    for reading in &state.sensors {
+        out.sample(
+            "marvinous_sensor_value",
+            &[("chip", &reading.chip), ("sensor", &reading.sensor), ("unit", &reading.unit)],
+            reading.value,
+        );
+    }
+}
+
+fn render_ipmi(out: &mut Exposition, state: &PreviousState) {
+    if state.ipmi.is_empty() {
+        return;
+    }
+
+    // Some BMCs list a sensor name more than once: the first numeric reading
+    // wins, and the sensor only counts as ok if every entry for it is
+    let mut values = BTreeMap::new();
+    let mut ok = BTreeMap::new();
+    for reading in &state.ipmi {
+        if let Some(value) = reading.numeric_value() {
+            values.entry(reading.sensor.as_str()).or_insert(value);
+        }
+        *ok.entry(reading.sensor.as_str()).or_insert(true) &= reading.status == "ok";
+    }
+
+    out.family("marvinous_ipmi_value", "Numeric IPMI sensor reading", "gauge");
+    for (sensor, (value, unit)) in values {
+        out.sample("marvinous_ipmi_value", &[("sensor", sensor), ("unit", unit)], value);
+    }
+
+    out.family("marvinous_ipmi_status_ok", "Whether the IPMI sensor status is ok", "gauge");
+    for (sensor, ok) in ok {
+        out.sample("marvinous_ipmi_status_ok", &[("sensor", sensor)], if ok { 1.0 } else { 0.0 });
+    }
+}
+
+fn render_gpu(out: &mut Exposition, state: &PreviousState) {
+    let Some(gpu) = &state.gpu else {
+        return;
+    };
+    let labels = [("gpu", gpu.name.as_str())];
+
+    out.family("marvinous_gpu_temperature_celsius", "GPU temperature", "gauge");
+    out.sample("marvinous_gpu_temperature_celsius", &labels, gpu.temperature);
+    out.family("marvinous_gpu_memory_used_bytes", "GPU memory in use", "gauge");
+    out.sample("marvinous_gpu_memory_used_bytes", &labels, mib_to_bytes(gpu.memory_used));
+    out.family("marvinous_gpu_memory_total_bytes", "GPU memory installed", "gauge");
+    out.sample("marvinous_gpu_memory_total_bytes", &labels, mib_to_bytes(gpu.memory_total));
+    out.family("marvinous_gpu_utilization_percent", "GPU utilisation", "gauge");
+    out.sample("marvinous_gpu_utilization_percent", &labels, gpu.utilisation as f64);
+    out.family("marvinous_gpu_power_watts", "GPU power draw", "gauge");
+    out.sample("marvinous_gpu_power_watts", &labels, gpu.power_draw);
+}
+
+fn render_drives(out: &mut Exposition, state: &PreviousState) {
+    if state.drives.is_empty() {
+        return;
+    }
+
+    let families: [(&str, &str); 4] = [
+        ("marvinous_drive_reallocated_sectors", "SMART reallocated sector count"),
+        ("marvinous_drive_pending_sectors", "SMART current pending sector count"),
+        ("marvinous_drive_power_on_hours", "SMART power-on hours"),
+        ("marvinous_drive_temperature_celsius", "Drive temperature"),
+    ];
+
+    for (name, help) in families {
+        out.family(name, help, "gauge");
+        for drive in &state.drives {
+            let value = match name {
+                "marvinous_drive_reallocated_sectors" => Some(drive.reallocated_sectors as f64),
+                "marvinous_drive_pending_sectors" => Some(drive.pending_sectors as f64),
+                "marvinous_drive_power_on_hours" => Some(drive.power_on_hours as f64),
+                _ => drive.temperature,
+            };
+            if let Some(value) = value {
+                out.sample(name, &[("device", &drive.device), ("model", &drive.model)], value);
+            }
+        }
+    }
+}
+
+fn render_custom(out: &mut Exposition, state: &PreviousState) {
+    if state.custom.is_empty() {
+        return;
+    }
+
+    out.family("marvinous_custom_check_exit_code", "Exit code of a custom check", "gauge");
+    for check in &state.custom {
+        if let Some(code) = check.exit_code {
+            out.sample("marvinous_custom_check_exit_code", &[("check", &check.name)], code as f64);
+        }
+    }
+}
+
+fn render_internal(out: &mut Exposition, state: &PreviousState) {
+    if let Some(stats) = &state.stats {
+        out.family("marvinous_collection_duration_seconds", "Time spent running collectors", "gauge");
+        out.sample("marvinous_collection_duration_seconds", &[], stats.collection_secs);
+        out.family("marvinous_llm_duration_seconds", "Time spent waiting for the LLM", "gauge");
+        out.sample("marvinous_llm_duration_seconds", &[], stats.llm_secs);
+
+        if let Some(generation) = &stats.generation {
+            let model = [("model", generation.model.as_str())];
+            if let Some(count) = generation.prompt_eval_count {
+                out.family("marvinous_llm_prompt_tokens", "Prompt tokens evaluated in the last run", "gauge");
+                out.sample("marvinous_llm_prompt_tokens", &model, count as f64);
+            }
+            if let Some(count) = generation.eval_count {
+                out.family("marvinous_llm_generated_tokens", "Tokens generated in the last run", "gauge");
+                out.sample("marvinous_llm_generated_tokens", &model, count as f64);
+            }
+            if let Some(rate) = generation.tokens_per_second() {
+                out.family("marvinous_llm_tokens_per_second", "Generation speed in the last run", "gauge");
+                out.sample("marvinous_llm_tokens_per_second", &model, rate);
+            }
+        }
+    }
+
+    out.family("marvinous_collector_failures", "Collectors that failed or timed out in the last run", "gauge");
+    out.sample("marvinous_collector_failures", &[], state.failures.len() as f64);
+
+    if !state.failures.is_empty() {
+        out.family("marvinous_collector_failed", "Collector that failed in the last run", "gauge");
+        for failure in &state.failures {
+            let timed_out = if failure.timed_out { "true" } else { "false" };
+            out.sample(
+                "marvinous_collector_failed",
+                &[("collector", &failure.collector), ("timed_out", timed_out)],
+                1.0,
+            );
+        }
+    }
+}
+
+fn mib_to_bytes(mib: u64) -> f64 {
+    (mib * 1024 * 1024) as f64
+}
+
+/// Minimal writer for the text exposition format
+#[derive(Default)]
+struct Exposition {
+    out: String,
+}
+
+impl Exposition {
+    fn family(&mut self, name: &str, help: &str, kind: &str) {
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            let rendered: Vec<String> = labels
+                .iter()
+                .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
+                .collect();
+            let _ = write!(self.out, "{{{}}}", rendered.join(","));
+        }
+        let _ = writeln!(self.out, " {}", format_value(value));
+    }
+
+    fn finish(self) -> String {
+        self.out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::{CollectorFailure, DriveHealth, IpmiReading, SensorReading};
+    use crate::output::RunStats;
+    use chrono::Utc;
+
+    fn sample_state() -> PreviousState {
+        PreviousState {
+            timestamp: Utc::now(),
+            sensors: vec![SensorReading {
+                chip: "coretemp-isa-0000".to_string(),
+                sensor: "Package id 0".to_string(),
+                value: 45.0,
+                unit: "°C".to_string(),
+            }],
+            gpu: None,
+            drives: vec![DriveHealth {
+                device: "/dev/sda".to_string(),
+                model: "Some \"Quoted\" Drive".to_string(),
+                reallocated_sectors: 8,
+                pending_sectors: 0,
+                temperature: None,
+                power_on_hours: 1200,
+            }],
+            custom: vec![],
+            metrics: vec![],
+            ipmi: vec![
+                IpmiReading {
+                    sensor: "CPU0_TEMP".to_string(),
+                    value: "34 degrees C".to_string(),
+                    status: "ok".to_string(),
+                },
+                IpmiReading {
+                    sensor: "DIMM_A1".to_string(),
+                    value: "no reading".to_string(),
+                    status: "ns".to_string(),
+                },
+                IpmiReading {
+                    sensor: "DIMM_A1".to_string(),
+                    value: "no reading".to_string(),
+                    status: "ok".to_string(),
+                },
+            ],
+            inventory: None,
+            severity: Some(Severity::Watch),
+            failures: vec![CollectorFailure {
+                collector: "smart /dev/sdx".to_string(),
+                error: "timed out after 60s".to_string(),
+                timed_out: true,
+            }],
+            stats: Some(RunStats {
+                collection_secs: 4.5,
+                llm_secs: 30.25,
+                generation: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_render_without_state() {
+        let text = render_metrics(None);
+        assert!(text.contains("marvinous_state_present 0\n"));
+        assert!(!text.contains("marvinous_report_severity"));
+    }
+
+    #[test]
+    fn test_render_state() {
+        let state = sample_state();
+        let text = render_metrics(Some(&state));
+
+        assert!(text.contains("marvinous_report_severity{severity=\"watch\"} 1\n"));
+        assert!(text.contains("marvinous_report_severity{severity=\"ok\"} 0\n"));
+        assert!(text.contains("marvinous_report_severity_level 1\n"));
+        assert!(text.contains(
+            "marvinous_sensor_value{chip=\"coretemp-isa-0000\",sensor=\"Package id 0\",unit=\"°C\"} 45\n"
+        ));
+        assert!(text.contains("marvinous_ipmi_value{sensor=\"CPU0_TEMP\",unit=\"degrees C\"} 34\n"));
+        assert!(!text.contains("marvinous_ipmi_value{sensor=\"DIMM_A1\""));
+        assert!(text.contains("marvinous_ipmi_status_ok{sensor=\"CPU0_TEMP\"} 1\n"));
+        assert_eq!(text.matches("marvinous_ipmi_status_ok{sensor=\"DIMM_A1\"}").count(), 1);
+        assert!(text.contains("marvinous_ipmi_status_ok{sensor=\"DIMM_A1\"} 0\n"));
+        assert!(text.contains(
+            "marvinous_drive_reallocated_sectors{device=\"/dev/sda\",model=\"Some \\\"Quoted\\\" Drive\"} 8\n"
+        ));
+        assert!(!text.contains("marvinous_drive_temperature_celsius{"));
+        assert!(text.contains("marvinous_llm_duration_seconds 30.25\n"));
+        assert!(text.contains("marvinous_collector_failures 1\n"));
+        assert!(text.contains(
+            "marvinous_collector_failed{collector=\"smart /dev/sdx\",timed_out=\"true\"} 1\n"
+        ));
+    }
+}
@@ -2,9 +2,14 @@
 //!
 //! "I suppose I'll have to serve HTTP requests now. How utterly beneath me."
 
+pub mod assets;
+pub mod auth;
 pub mod handlers;
+pub mod index;
+pub mod metrics;
 pub mod models;
 pub mod server;
 pub mod state;
+pub mod tls;
 
-pub use server::run_server;
+pub use server::{run_server, serve};
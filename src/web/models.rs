@@ -1,22 +1,75 @@
 //! API request and response models
 
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+use super::index::ReportKind;
+use super::state::Job;
+use crate::collector::{
+    CollectorFailure, CustomCheckResult, DriveHealth, GpuStatus, Inventory, IpmiReading,
+    MetricReading, SensorReading,
+};
+use crate::lock::LockHolder;
+use crate::llm::ollama::GenerationStats;
+use crate::output::report::Severity;
+use crate::output::PreviousState;
 
 /// Metadata for a single report file
 #[derive(Debug, Serialize)]
 pub struct ReportMeta {
     pub filename: String,
     pub timestamp: DateTime<Utc>,
+    pub kind: ReportKind,
     pub severity: String,
     pub size_bytes: u64,
+    /// Context around the first match when searching with `q`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+/// Query parameters for `/api/reports`
+#[derive(Debug, Deserialize)]
+pub struct ReportQuery {
+    /// Comma-separated severities (ok, watch, concern, critical, unknown)
+    pub severity: Option<String>,
+    /// RFC 3339 bounds on the report timestamp
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub kind: Option<ReportKind>,
+    /// Case-insensitive full-text search
+    pub q: Option<String>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
 }
 
-/// Response for listing all reports
+/// Response for listing reports
 #[derive(Debug, Serialize)]
 pub struct ReportListResponse {
     pub reports: Vec<ReportMeta>,
+    /// Reports matching the filters, before paging
     pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// Response for listing daily archives
+#[derive(Debug, Serialize)]
+pub struct ArchiveListResponse {
+    pub archives: Vec<ArchiveSummary>,
+}
+
+/// One `archive/YYYY-MM-DD.zip`
+#[derive(Debug, Serialize)]
+pub struct ArchiveSummary {
+    pub date: String,
+    pub size_bytes: u64,
+}
+
+/// Hourly reports inside one daily archive
+#[derive(Debug, Serialize)]
+pub struct ArchiveContentsResponse {
+    pub date: String,
+    pub reports: Vec<ReportMeta>,
 }
 
 /// Full report content with metadata
@@ -26,6 +79,8 @@ pub struct ReportContent {
     pub timestamp: DateTime<Utc>,
     pub content: String,
     pub severity: String,
+    /// Token counts and timings, for reports generated with stats
+    pub generation: Option<GenerationStats>,
 }
 
 /// Response from triggering a collection
@@ -33,13 +88,92 @@ pub struct ReportContent {
 pub struct CollectResponse {
     pub status: String,  // "started" | "already_running" | "error"
     pub message: String,
+    /// Job tracking the started collection
+    pub job_id: Option<u64>,
 }
 
 /// Current collection status
 #[derive(Debug, Serialize)]
 pub struct StatusResponse {
+    /// A collection is running here or in another process
     pub running: bool,
+    /// Process holding the collection lock, if any
+    pub lock_holder: Option<LockHolder>,
     pub last_run: Option<DateTime<Utc>>,
+    /// Most recent job, whatever its state
+    pub last_job: Option<Job>,
+}
+
+/// Response for listing recent jobs
+#[derive(Debug, Serialize)]
+pub struct JobListResponse {
+    pub jobs: Vec<Job>,
+}
+
+/// Readings from the most recent completed run
+#[derive(Debug, Serialize)]
+pub struct LatestMetricsResponse {
+    pub timestamp: DateTime<Utc>,
+    pub severity: Option<Severity>,
+    pub sensors: Vec<SensorReading>,
+    pub ipmi: Vec<IpmiReading>,
+    pub gpu: Option<GpuStatus>,
+    pub drives: Vec<DriveHealth>,
+    pub custom: Vec<CustomCheckResult>,
+    pub metrics: Vec<MetricReading>,
+    pub inventory: Option<Inventory>,
+    pub failures: Vec<CollectorFailure>,
+}
+
+impl From<PreviousState> for LatestMetricsResponse {
+    fn from(state: PreviousState) -> Self {
+        Self {
+            timestamp: state.timestamp,
+            severity: state.severity,
+            sensors: state.sensors,
+            ipmi: state.ipmi,
+            gpu: state.gpu,
+            drives: state.drives,
+            custom: state.custom,
+            metrics: state.metrics,
+            inventory: state.inventory,
+            failures: state.failures,
+        }
+    }
+}
+
+/// Query parameters for `/api/metrics/history`
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    /// Comma-separated series IDs (e.g. `temperature:ipmi:CPU0_TEMP`);
+    /// a trailing `*` selects by prefix. All series when omitted.
+    pub sensor: Option<String>,
+    /// RFC 3339 start time, default 24 hours before `to`
+    pub from: Option<DateTime<Utc>>,
+    /// RFC 3339 end time, default now
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Time-series readings for the requested range
+#[derive(Debug, Serialize)]
+pub struct HistoryResponse {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub series: Vec<SeriesData>,
+}
+
+/// All points for one series ID
+#[derive(Debug, Serialize)]
+pub struct SeriesData {
+    pub sensor: String,
+    pub points: Vec<SeriesPoint>,
+}
+
+/// A single reading in a series
+#[derive(Debug, Serialize)]
+pub struct SeriesPoint {
+    pub timestamp: DateTime<Utc>,
+    pub value: f64,
 }
 
 /// Health check response
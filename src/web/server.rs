@@ -1,48 +1,133 @@
 //! Web server setup and routing
 
+use axum_server::tls_rustls::RustlsConfig;
 use axum::{
+    middleware,
     routing::{get, post},
     Router,
 };
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 use tower_http::{
     services::ServeDir,
     trace::TraceLayer,
 };
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::config::Config;
-use super::{handlers, state::AppState};
+use super::{assets, auth, handlers, state::AppState, tls};
+
+/// How long open connections (e.g. `/api/events` streams) get to finish on shutdown
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
 
 /// Run the web server
 pub async fn run_server(config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    serve(Arc::new(AppState::new(config)), std::future::pending()).await
+}
+
+/// Serve the dashboard with existing state until `shutdown` completes
+pub async fn serve(
+    state: Arc<AppState>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config = state.config.clone();
     let bind_addr = format!("{}:{}", config.web.bind_address, config.web.port);
 
     info!("Starting Marvinous web dashboard on {}", bind_addr);
 
-    // Create shared application state
-    let state = Arc::new(AppState::new(config.clone()));
+    if !config.web.auth.enabled && !is_loopback(&config.web.bind_address) {
+        warn!(
+            "Dashboard on {} has no authentication - anyone on the network can read reports and trigger collections",
+            bind_addr
+        );
+    }
 
     // Build router with all endpoints
     let app = Router::new()
         // API routes
         .route("/api/reports", get(handlers::list_reports))
         .route("/api/reports/:filename", get(handlers::get_report))
+        .route("/api/archive", get(handlers::list_archives))
+        .route("/api/archive/:date", get(handlers::get_archive))
+        .route("/api/archive/:date/:filename", get(handlers::get_archived_report))
         .route("/api/collect", post(handlers::trigger_collect))
         .route("/api/status", get(handlers::get_status))
+        .route("/api/jobs", get(handlers::list_jobs))
+        .route("/api/jobs/:id", get(handlers::get_job))
+        .route("/api/events", get(handlers::collection_events))
+        .route("/api/metrics/latest", get(handlers::get_latest_metrics))
+        .route("/api/metrics/history", get(handlers::get_metrics_history))
         .route("/health", get(handlers::health_check))
-        // Serve static files from system location
-        .nest_service("/", ServeDir::new("/usr/share/marvinous/static"))
+        .route("/metrics", get(handlers::prometheus_metrics));
+
+    // Dashboard assets are embedded unless a development directory is configured
+    let app = match &config.web.static_dir {
+        Some(dir) => {
+            info!("Serving dashboard assets from {}", dir.display());
+            app.fallback_service(ServeDir::new(dir))
+        }
+        None => app.fallback(assets::serve),
+    };
+
+    let app = app
+        // Authenticate everything except /health when [web.auth] is enabled
+        .layer(middleware::from_fn_with_state(Arc::clone(&state), auth::require_auth))
         // Add shared state
         .with_state(state)
         // Add middleware
         .layer(TraceLayer::new_for_http());
 
+    if config.web.tls.enabled {
+        let rustls = RustlsConfig::from_config(tls::load_server_config(&config.web.tls)?);
+        tls::spawn_reloader(config.web.tls.clone(), rustls.clone());
+
+        let handle = axum_server::Handle::new();
+        let shutdown_handle = handle.clone();
+        tokio::spawn(async move {
+            shutdown.await;
+            shutdown_handle.graceful_shutdown(Some(SHUTDOWN_GRACE));
+        });
+
+        let listener = std::net::TcpListener::bind(&bind_addr)?;
+        listener.set_nonblocking(true)?;
+        info!("Web server listening on https://{}", bind_addr);
+
+        axum_server::from_tcp(listener)
+            .handle(handle)
+            .acceptor(tls::ClientCertAcceptor::new(rustls))
+            .serve(app.into_make_service())
+            .await?;
+
+        return Ok(());
+    }
+
     // Bind and serve
     let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
     info!("Web server listening on http://{}", bind_addr);
 
-    axum::serve(listener, app).await?;
+    // Connections still open after the grace period (SSE streams never end on
+    // their own) are dropped
+    let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+    let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+        shutdown.await;
+        let _ = stop_tx.send(());
+    });
+    tokio::select! {
+        result = server => result?,
+        _ = async {
+            let _ = stop_rx.await;
+            tokio::time::sleep(SHUTDOWN_GRACE).await;
+        } => info!("Closing remaining connections"),
+    }
 
     Ok(())
 }
+
+fn is_loopback(bind_address: &str) -> bool {
+    bind_address == "localhost"
+        || bind_address
+            .parse::<std::net::IpAddr>()
+            .map(|ip| ip.is_loopback())
+            .unwrap_or(false)
+}
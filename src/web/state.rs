@@ -1,9 +1,51 @@
 //! Shared application state for web server
 
 use chrono::{DateTime, Utc};
-use tokio::sync::Mutex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex};
+use std::time::Duration;
+use tracing::{error, info, warn};
 
-use crate::config::Config;
+use super::auth::Authenticator;
+use super::index::ReportIndex;
+use crate::config::{Config, LockMode};
+use crate::events::{CollectionEvent, Events, EVENT_CAPACITY};
+use crate::lock::{self, CollectionGuard, LockError};
+use crate::output::naming::Trigger;
+use crate::output::report::Severity;
+use crate::run_collection;
+
+/// How many finished jobs to remember
+const JOB_HISTORY: usize = 50;
+
+/// Lifecycle of a collection job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// A collection run triggered through the web API or the daemon scheduler
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: u64,
+    pub state: JobState,
+    /// What asked for the run
+    pub trigger: Trigger,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Report filename, once written
+    pub report: Option<String>,
+    pub severity: Option<Severity>,
+    pub error: Option<String>,
+}
 
 /// Application state shared across all web request handlers
 pub struct AppState {
@@ -11,19 +53,204 @@ pub struct AppState {
     pub config: Config,
 
     /// Mutex to prevent concurrent collections
-    /// When locked, a collection is in progress
-    pub collection_lock: Mutex<()>,
+    /// Held (as an owned guard) by the background task for the whole run
+    pub collection_lock: Arc<Mutex<()>>,
 
     /// Timestamp of last collection run
     pub last_run: Mutex<Option<DateTime<Utc>>>,
+
+    /// Collection progress, fanned out to `/api/events` subscribers
+    pub events: broadcast::Sender<CollectionEvent>,
+
+    /// Report listing and search
+    pub reports: ReportIndex,
+
+    /// Credential checks for `[web.auth]`
+    pub auth: Arc<Authenticator>,
+
+    /// Recent jobs, oldest first
+    jobs: Mutex<VecDeque<Job>>,
+    next_job_id: AtomicU64,
 }
 
 impl AppState {
     pub fn new(config: Config) -> Self {
+        let reports = ReportIndex::new(&config.general.report_dir, config.general.tz());
+        if let Err(e) = reports.refresh() {
+            tracing::warn!("Failed to index reports in {}: {}", config.general.report_dir.display(), e);
+        }
+
         Self {
+            reports,
+            auth: Arc::new(Authenticator::new(config.web.auth.clone())),
             config,
-            collection_lock: Mutex::new(()),
+            collection_lock: Arc::new(Mutex::new(())),
             last_run: Mutex::new(None),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            jobs: Mutex::new(VecDeque::new()),
+            next_job_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Record a new queued job, forgetting the oldest beyond `JOB_HISTORY`
+    pub async fn create_job(&self, trigger: Trigger) -> Job {
+        let job = Job {
+            id: self.next_job_id.fetch_add(1, Ordering::Relaxed),
+            state: JobState::Queued,
+            trigger,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            report: None,
+            severity: None,
+            error: None,
+        };
+
+        let mut jobs = self.jobs.lock().await;
+        jobs.push_back(job.clone());
+        while jobs.len() > JOB_HISTORY {
+            jobs.pop_front();
+        }
+
+        job
+    }
+
+    /// Apply `update` to a job, if it is still remembered
+    pub async fn update_job(&self, id: u64, update: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.jobs.lock().await.iter_mut().find(|job| job.id == id) {
+            update(job);
+        }
+    }
+
+    pub async fn job(&self, id: u64) -> Option<Job> {
+        self.jobs.lock().await.iter().find(|job| job.id == id).cloned()
+    }
+
+    /// Recent jobs, newest first
+    pub async fn jobs(&self) -> Vec<Job> {
+        self.jobs.lock().await.iter().rev().cloned().collect()
+    }
+
+    /// Start a collection job in the background
+    ///
+    /// Fails with `LockError::Held` if this process is already collecting, or
+    /// another process is and `lock_mode` is `skip`; otherwise the job stays
+    /// queued until `general.lock_file` is free. The guards move into the
+    /// task, so both locks are held until the run finishes.
+    pub async fn start_collection(self: &Arc<Self>, trigger: Trigger) -> Result<Job, LockError> {
+        let lock_file = &self.config.general.lock_file;
+        let guard = Arc::clone(&self.collection_lock)
+            .try_lock_owned()
+            .map_err(|_| LockError::Held(lock::holder(lock_file)))?;
+        let file_guard = match self.config.collection.lock_mode {
+            LockMode::Skip => Some(lock::acquire(lock_file, LockMode::Skip, Duration::ZERO).await?),
+            LockMode::Wait | LockMode::Queue => None,
+        };
+        let job = self.create_job(trigger).await;
+
+        let state = Arc::clone(self);
+        let events = Events::from(self.events.clone());
+        let job_id = job.id;
+
+        tokio::spawn(async move {
+            let _guard = guard;
+            let _file_guard = match file_guard {
+                Some(file_guard) => file_guard,
+                None => match state.wait_for_lock(job_id).await {
+                    Ok(file_guard) => file_guard,
+                    Err(e) => {
+                        warn!("Background collection not run (job {}): {}", job_id, e);
+                        state
+                            .update_job(job_id, |job| {
+                                job.state = JobState::Failed;
+                                job.finished_at = Some(Utc::now());
+                                job.error = Some(e.to_string());
+                            })
+                            .await;
+                        return;
+                    }
+                },
+            };
+            info!("Starting background collection (job {})", job_id);
+            state
+                .update_job(job_id, |job| {
+                    job.state = JobState::Running;
+                    job.started_at = Some(Utc::now());
+                })
+                .await;
+
+            // Run in its own task so a panic still ends the job as failed
+            let collection = {
+                let state = Arc::clone(&state);
+                tokio::spawn(async move { run_collection(&state.config, trigger, &events).await })
+            };
+            let result = match collection.await {
+                Ok(result) => result.map_err(|e| e.to_string()),
+                Err(e) => Err(format!("collection task failed: {}", e)),
+            };
+
+            match result {
+                Ok(outcome) => {
+                    info!("Background collection completed successfully (job {})", job_id);
+                    let finished = Utc::now();
+                    state
+                        .update_job(job_id, |job| {
+                            job.state = JobState::Succeeded;
+                            job.finished_at = Some(finished);
+                            job.report = outcome
+                                .report_path
+                                .file_name()
+                                .map(|name| name.to_string_lossy().into_owned());
+                            job.severity = Some(outcome.severity);
+                        })
+                        .await;
+                    *state.last_run.lock().await = Some(finished);
+                }
+                Err(e) => {
+                    error!("Background collection failed (job {}): {}", job_id, e);
+                    state
+                        .update_job(job_id, |job| {
+                            job.state = JobState::Failed;
+                            job.finished_at = Some(Utc::now());
+                            job.error = Some(e);
+                        })
+                        .await;
+                }
+            }
+        });
+
+        Ok(job)
+    }
+
+    /// Take the cross-process lock for a queued job, per `lock_mode`
+    async fn wait_for_lock(&self, job_id: u64) -> Result<CollectionGuard, LockError> {
+        let lock_file = &self.config.general.lock_file;
+        if let Some(holder) = lock::holder(lock_file) {
+            info!("Collection job {} queued behind {}", job_id, holder);
+        }
+        let collection = &self.config.collection;
+        lock::acquire(lock_file, collection.lock_mode, Duration::from_secs(collection.lock_wait_secs)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_job_history_is_bounded_and_newest_first() {
+        let state = AppState::new(Config::default());
+
+        for _ in 0..JOB_HISTORY + 5 {
+            state.create_job(Trigger::Api).await;
         }
+        let last = (JOB_HISTORY + 5) as u64;
+        state.update_job(last, |job| job.state = JobState::Running).await;
+
+        let jobs = state.jobs().await;
+        assert_eq!(jobs.len(), JOB_HISTORY);
+        assert_eq!(jobs[0].id, last);
+        assert_eq!(jobs[0].state, JobState::Running);
+        assert!(state.job(1).await.is_none());
     }
 }
@@ -0,0 +1,370 @@
+//! HTTPS via rustls, with certificate reload and optional client certificates
+//!
+//! "The best conversationalist was a coffee machine I met once."
+
+use axum_server::accept::Accept;
+use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::signal::unix::{signal, SignalKind};
+use tower_http::add_extension::AddExtension;
+use tracing::{info, warn};
+
+use crate::config::TlsConfig;
+
+#[derive(Error, Debug)]
+pub enum TlsError {
+    #[error("Failed to read {path}: {source}")]
+    Read { path: PathBuf, source: io::Error },
+    #[error("No certificates found in {0}")]
+    NoCertificates(PathBuf),
+    #[error("No private key found in {0}")]
+    NoPrivateKey(PathBuf),
+    #[error("Invalid TLS configuration: {0}")]
+    Rustls(#[from] rustls::Error),
+    #[error("Invalid client CA bundle: {0}")]
+    ClientVerifier(String),
+}
+
+/// Whether the connection presented a client certificate that verified
+/// against `client_ca`; added to every request on TLS connections
+#[derive(Debug, Clone, Copy)]
+pub struct ClientCertificate {
+    pub verified: bool,
+}
+
+/// Build a rustls server config from the `[web.tls]` files
+pub fn load_server_config(tls: &TlsConfig) -> Result<Arc<ServerConfig>, TlsError> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let certs = read_certs(&tls.cert)?;
+    let key = read_key(&tls.key)?;
+
+    let builder = ServerConfig::builder_with_provider(Arc::clone(&provider)).with_safe_default_protocol_versions()?;
+
+    let builder = match &tls.client_ca {
+        Some(ca) => {
+            let mut roots = RootCertStore::empty();
+            for cert in read_certs(ca)? {
+                roots.add(cert)?;
+            }
+
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+            let verifier = if tls.client_cert_required {
+                verifier
+            } else {
+                verifier.allow_unauthenticated()
+            };
+            builder.with_client_cert_verifier(verifier.build().map_err(|e| TlsError::ClientVerifier(e.to_string()))?)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut config = builder.with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(Arc::new(config))
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, TlsError> {
+    File::open(path).map(BufReader::new).map_err(|source| TlsError::Read {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, TlsError> {
+    let certs = rustls_pemfile::certs(&mut open(path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|source| TlsError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificates(path.to_path_buf()));
+    }
+    Ok(certs)
+}
+
+fn read_key(path: &Path) -> Result<PrivateKeyDer<'static>, TlsError> {
+    rustls_pemfile::private_key(&mut open(path)?)
+        .map_err(|source| TlsError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?
+        .ok_or_else(|| TlsError::NoPrivateKey(path.to_path_buf()))
+}
+
+/// Reload certificates on SIGHUP, or when the files change on disk
+///
+/// A reload that fails keeps serving the previous certificate.
+pub fn spawn_reloader(tls: TlsConfig, rustls: RustlsConfig) {
+    tokio::spawn(async move {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => Some(hangup),
+            Err(e) => {
+                warn!("Can't listen for SIGHUP, TLS reload on signal disabled: {}", e);
+                None
+            }
+        };
+
+        let interval = (tls.reload_interval_secs > 0).then(|| Duration::from_secs(tls.reload_interval_secs));
+        let mut last_modified = modified_times(&tls);
+
+        loop {
+            tokio::select! {
+                Some(()) = async {
+                    match hangup.as_mut() {
+                        Some(hangup) => hangup.recv().await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    info!("SIGHUP received, reloading TLS certificates");
+                }
+                _ = async {
+                    match interval {
+                        Some(interval) => tokio::time::sleep(interval).await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    let modified = modified_times(&tls);
+                    if modified == last_modified {
+                        continue;
+                    }
+                    info!("TLS certificate files changed, reloading");
+                }
+            }
+
+            last_modified = modified_times(&tls);
+            match load_server_config(&tls) {
+                Ok(config) => {
+                    rustls.reload_from_config(config);
+                    info!("TLS certificates reloaded");
+                }
+                Err(e) => warn!("TLS reload failed, keeping previous certificate: {}", e),
+            }
+        }
+    });
+}
+
+fn modified_times(tls: &TlsConfig) -> Vec<Option<SystemTime>> {
+    [Some(&tls.cert), Some(&tls.key), tls.client_ca.as_ref()]
+        .into_iter()
+        .flatten()
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+/// rustls acceptor that records whether the client presented a certificate
+#[derive(Clone)]
+pub struct ClientCertAcceptor {
+    inner: RustlsAcceptor,
+}
+
+impl ClientCertAcceptor {
+    pub fn new(config: RustlsConfig) -> Self {
+        Self {
+            inner: RustlsAcceptor::new(config),
+        }
+    }
+}
+
+impl<I, S> Accept<I, S> for ClientCertAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = <RustlsAcceptor as Accept<I, S>>::Stream;
+    type Service = AddExtension<S, ClientCertificate>;
+    type Future = Pin<Box<dyn Future<Output = io::Result<(Self::Stream, Self::Service)>> + Send>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let handshake = self.inner.accept(stream, service);
+
+        Box::pin(async move {
+            let (stream, service) = handshake.await?;
+            // rustls has already rejected certificates that don't chain to client_ca
+            let verified = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .is_some_and(|certs| !certs.is_empty());
+            Ok((stream, AddExtension::new(service, ClientCertificate { verified })))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{Request, Response};
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use rustls::pki_types::{PrivatePkcs8KeyDer, ServerName};
+    use rustls::ClientConfig;
+    use std::convert::Infallible;
+    use tokio_rustls::TlsConnector;
+    use tower::ServiceExt;
+
+    fn write_cert(dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert_path = dir.join(format!("{}.pem", name));
+        let key_path = dir.join(format!("{}.key", name));
+        std::fs::write(&cert_path, cert.cert.pem()).unwrap();
+        std::fs::write(&key_path, cert.key_pair.serialize_pem()).unwrap();
+        (cert_path, key_path)
+    }
+
+    /// Client config trusting the server certificate at `server_cert`,
+    /// presenting `identity` if given
+    fn client_config(
+        server_cert: &Path,
+        identity: Option<(CertificateDer<'static>, PrivateKeyDer<'static>)>,
+    ) -> Arc<ClientConfig> {
+        let mut roots = RootCertStore::empty();
+        for cert in read_certs(server_cert).unwrap() {
+            roots.add(cert).unwrap();
+        }
+        let builder = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots);
+        let config = match identity {
+            Some((cert, key)) => builder.with_client_auth_cert(vec![cert], key).unwrap(),
+            None => builder.with_no_client_auth(),
+        };
+        Arc::new(config)
+    }
+
+    /// Handshake through the acceptor over an in-memory pipe, returning the
+    /// extension it hands to the service
+    async fn handshake(acceptor: &ClientCertAcceptor, client: Arc<ClientConfig>) -> io::Result<ClientCertificate> {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let service = tower::service_fn(|request: Request<()>| async move {
+            Ok::<_, Infallible>(Response::new(request.extensions().get::<ClientCertificate>().copied()))
+        });
+
+        let server = async {
+            let (stream, service) = acceptor.accept(server_io, service).await?;
+            let extension = service.oneshot(Request::new(())).await.unwrap().into_body();
+            Ok::<_, io::Error>((stream, extension))
+        };
+        let client = TlsConnector::from(client).connect(ServerName::try_from("localhost").unwrap(), client_io);
+
+        let (server, client) = tokio::join!(server, client);
+        client?;
+        let (_stream, extension) = server?;
+        Ok(extension.expect("acceptor adds the extension"))
+    }
+
+    #[test]
+    fn test_load_server_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert, key) = write_cert(dir.path(), "server");
+        let (ca, _) = write_cert(dir.path(), "ca");
+
+        let mut tls = TlsConfig {
+            enabled: true,
+            cert,
+            key,
+            ..TlsConfig::default()
+        };
+        let config = load_server_config(&tls).unwrap();
+        assert_eq!(config.alpn_protocols, vec![b"h2".to_vec(), b"http/1.1".to_vec()]);
+
+        tls.client_ca = Some(ca.clone());
+        assert!(load_server_config(&tls).is_ok());
+
+        // A certificate where the key should be
+        tls.key = ca;
+        assert!(matches!(load_server_config(&tls), Err(TlsError::NoPrivateKey(_))));
+    }
+
+    #[tokio::test]
+    async fn test_acceptor_marks_verified_client_certificates() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert, key) = write_cert(dir.path(), "server");
+
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(vec![]).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+        let ca_path = dir.path().join("client-ca.pem");
+        std::fs::write(&ca_path, ca.pem()).unwrap();
+
+        let client_key = KeyPair::generate().unwrap();
+        let client_cert = CertificateParams::new(vec!["ops-laptop".to_string()])
+            .unwrap()
+            .signed_by(&client_key, &ca, &ca_key)
+            .unwrap();
+        let identity = || {
+            (
+                client_cert.der().clone(),
+                PrivateKeyDer::from(PrivatePkcs8KeyDer::from(client_key.serialize_der())),
+            )
+        };
+
+        let tls = TlsConfig {
+            enabled: true,
+            cert: cert.clone(),
+            key,
+            client_ca: Some(ca_path),
+            ..TlsConfig::default()
+        };
+        let acceptor = ClientCertAcceptor::new(RustlsConfig::from_config(load_server_config(&tls).unwrap()));
+
+        let with_cert = handshake(&acceptor, client_config(&cert, Some(identity()))).await.unwrap();
+        assert!(with_cert.verified);
+        let without = handshake(&acceptor, client_config(&cert, None)).await.unwrap();
+        assert!(!without.verified);
+
+        // A self-signed client certificate isn't signed by client_ca
+        let stranger = rcgen::generate_simple_self_signed(vec!["stranger".to_string()]).unwrap();
+        let stranger = (
+            stranger.cert.der().clone(),
+            PrivateKeyDer::from(PrivatePkcs8KeyDer::from(stranger.key_pair.serialize_der())),
+        );
+        assert!(handshake(&acceptor, client_config(&cert, Some(stranger))).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reloader_picks_up_swapped_certificate() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert, key) = write_cert(dir.path(), "server");
+        let (replacement, replacement_key) = write_cert(dir.path(), "replacement");
+
+        let tls = TlsConfig {
+            enabled: true,
+            cert: cert.clone(),
+            key: key.clone(),
+            reload_interval_secs: 1,
+            ..TlsConfig::default()
+        };
+        let rustls = RustlsConfig::from_config(load_server_config(&tls).unwrap());
+        let acceptor = ClientCertAcceptor::new(rustls.clone());
+        spawn_reloader(tls, rustls);
+
+        let trusts_replacement = client_config(&replacement, None);
+        assert!(handshake(&acceptor, Arc::clone(&trusts_replacement)).await.is_err());
+
+        std::fs::copy(&replacement, &cert).unwrap();
+        std::fs::copy(&replacement_key, &key).unwrap();
+
+        for _ in 0..50 {
+            if handshake(&acceptor, Arc::clone(&trusts_replacement)).await.is_ok() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        panic!("swapped certificate was not picked up");
+    }
+}